@@ -0,0 +1,332 @@
+/// Module for computer-controlled paddles that predict where the ball will arrive
+use bevy::prelude::*;
+use crate::ball::{Ball, BounceCount, RallyTime, Velocity};
+use crate::paddle::{self, Paddle, PaddleInput, PaddleSlot, Side};
+use crate::powerup::PowerUpState;
+use crate::scoreboard::MatchRules;
+use crate::simulation::SimulationSettings;
+use crate::settings::PongSettings;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+/// Distance in pixels from its target at which an AI paddle stops moving
+const AI_DEAD_ZONE: f32 = 2.0;
+
+/// How well the computer opponent plays
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    /// Slow to react, sloppy predictions and a sluggish paddle
+    Easy,
+    /// A fair match for most players
+    Normal,
+    /// Reacts almost instantly and rarely misjudges the ball
+    Hard,
+}
+
+impl Difficulty {
+    /// Parses a difficulty from its lowercase name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    /// Name of the difficulty as shown in menus
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    /// The next harder difficulty, wrapping around to Easy after Hard
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// Seconds between the AI taking a fresh look at the ball
+    pub fn reaction_delay(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.4,
+            Difficulty::Normal => 0.2,
+            Difficulty::Hard => 0.05,
+        }
+    }
+
+    /// Largest error in pixels the AI makes when predicting where the ball will arrive
+    pub fn prediction_error(self) -> f32 {
+        match self {
+            Difficulty::Easy => 30.0,
+            Difficulty::Normal => 14.0,
+            Difficulty::Hard => 4.0,
+        }
+    }
+
+    /// Top speed of the AI paddle as a fraction of the paddle speed
+    pub fn speed_factor(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.45,
+            Difficulty::Normal => 0.7,
+            Difficulty::Hard => 1.0,
+        }
+    }
+}
+
+/// Selects which paddles are controlled by the computer, and how well they play
+#[derive(Resource, Default)]
+pub struct AiPlayers {
+    /// Difficulty of the left paddle's AI, or None for a human player
+    pub left: Option<Difficulty>,
+    /// Difficulty of the right paddle's AI, or None for a human player
+    pub right: Option<Difficulty>,
+    /// Difficulty of the top paddle's AI in a four-player match, or None for a human player
+    pub top: Option<Difficulty>,
+    /// Difficulty of the bottom paddle's AI in a four-player match, or None for a human player
+    pub bottom: Option<Difficulty>,
+    /// Difficulty of the left forward paddle's AI in a doubles match, or None for a human player
+    pub left_forward: Option<Difficulty>,
+    /// Difficulty of the right forward paddle's AI in a doubles match, or None for a human player
+    pub right_forward: Option<Difficulty>,
+}
+
+impl AiPlayers {
+    /// Reads the AI setup from command line arguments
+    ///
+    /// Accepts `--ai-left <difficulty>` and `--ai-right <difficulty>`, along with
+    /// `--ai-top <difficulty>` and `--ai-bottom <difficulty>` for four-player
+    /// matches and `--ai-left-forward <difficulty>` and `--ai-right-forward
+    /// <difficulty>` for doubles matches, where the difficulty is one of easy,
+    /// normal or hard. Unknown difficulties are reported and ignored.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut players = AiPlayers::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let slot = match arg.as_str() {
+                "--ai-left" => &mut players.left,
+                "--ai-right" => &mut players.right,
+                "--ai-top" => &mut players.top,
+                "--ai-bottom" => &mut players.bottom,
+                "--ai-left-forward" => &mut players.left_forward,
+                "--ai-right-forward" => &mut players.right_forward,
+                _ => continue,
+            };
+            let name = args.next().unwrap_or_default();
+            match Difficulty::from_name(&name) {
+                Some(difficulty) => *slot = Some(difficulty),
+                None => warn!("Unknown AI difficulty '{}' for {}", name, arg),
+            }
+        }
+
+        players
+    }
+
+    /// Difficulty of the AI playing `paddle`, or None for a human player
+    ///
+    /// Four-player matches have no forward paddles, so the top and bottom
+    /// paddles are always the back ones.
+    pub fn get(&self, paddle: Paddle) -> Option<Difficulty> {
+        match (paddle.side, paddle.slot) {
+            (Side::Left, PaddleSlot::Back) => self.left,
+            (Side::Right, PaddleSlot::Back) => self.right,
+            (Side::Left, PaddleSlot::Forward) => self.left_forward,
+            (Side::Right, PaddleSlot::Forward) => self.right_forward,
+            (Side::Top, _) => self.top,
+            (Side::Bottom, _) => self.bottom,
+        }
+    }
+}
+
+/// Component for paddles that are driven by the computer
+#[derive(Component)]
+pub struct AiController {
+    /// How well this paddle plays
+    pub difficulty: Difficulty,
+    /// Counts down to the next time the AI looks at the ball
+    reaction_timer: Timer,
+    /// Position along its edge the paddle is currently heading for
+    target: f32,
+    /// Misjudgement added to every prediction of the current approach
+    aim_error: f32,
+    /// Bounce count of the ball when the aim error was last rolled
+    aim_bounce: Option<u32>,
+    /// Source of the aim errors
+    ///
+    /// Kept apart from the GameRng so the ball plays out the same whether a
+    /// paddle is steered live by the AI or by its recorded inputs in a replay.
+    rng: ChaCha12Rng,
+}
+
+impl AiController {
+    /// Creates an AI controller with the given difficulty, rolling its mistakes from `seed`
+    pub fn new(difficulty: Difficulty, seed: u64) -> Self {
+        AiController {
+            difficulty,
+            reaction_timer: Timer::from_seconds(difficulty.reaction_delay(), TimerMode::Repeating),
+            target: 0.0,
+            aim_error: 0.0,
+            aim_bounce: None,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+/// Hands paddles over to the computer according to the AiPlayers resource
+///
+/// Runs once the paddles have been spawned and attaches an AiController to
+/// each paddle that should not be controlled from the keyboard.
+pub fn assign_ai_controllers(
+    mut commands: Commands,
+    ai_players: Res<AiPlayers>,
+    settings: Res<SimulationSettings>,
+    paddle_query: Query<(Entity, &Paddle)>,
+) {
+    for (entity, paddle) in paddle_query.iter() {
+        // Each side rolls its own mistakes, so the AIs never mirror each other
+        if let Some(difficulty) = ai_players.get(*paddle) {
+            let seed = settings.seed.wrapping_add(paddle.side.index() as u64 + 4 * paddle.slot as u64);
+            commands.entity(entity).insert(AiController::new(difficulty, seed));
+        }
+    }
+}
+
+/// Steers AI paddles towards the point where the ball will reach them
+///
+/// Every reaction delay the AI predicts where the nearest approaching ball will
+/// cross its paddle, including bounces off the walls either side of it, and adds
+/// its difficulty's prediction error. In between it keeps heading for the last
+/// target, moving no faster than its difficulty allows. With no ball approaching
+/// the paddle drifts back to the center. Paddles that slide under the
+/// MatchRules' PaddleMovement let go early enough to stop on the target, and
+/// never leave their starting line. A Reverse power-up mixes up the AI's
+/// controls just like a player's. Paddles along the top and bottom work out the
+/// same with the axes swapped.
+pub fn control_ai_paddles(
+    mut paddle_query: Query<(&Transform, &Velocity, &mut PaddleInput, &mut AiController, &Paddle)>,
+    ball_query: Query<(&Transform, &Velocity, &BounceCount, &RallyTime), With<Ball>>,
+    settings: Res<PongSettings>,
+    rules: Res<MatchRules>,
+    power_ups: Res<PowerUpState>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+
+    for (paddle_transform, paddle_velocity, mut input, mut ai, paddle) in paddle_query.iter_mut() {
+        let side = paddle.side;
+        let paddle_pos = side.orient(paddle_transform.translation.truncate());
+
+        ai.reaction_timer.tick(time.delta());
+        if ai.reaction_timer.just_finished() {
+            let wall_limit = side.orient(settings.field_size - settings.ball_size).y / 2.0;
+            // The face of the paddle that points towards the middle of the field
+            let face_x = paddle_pos.x - paddle_pos.x.signum() * (settings.paddle_size.x + side.orient(settings.ball_size).x) / 2.0;
+
+            let approach = ball_query
+                .iter()
+                .filter_map(|(transform, velocity, bounce_count, rally_time)| {
+                    let movement = side.orient(
+                        rules.speed.ball_velocity(Vec2::new(velocity.x, velocity.y), bounce_count.0, rally_time.0),
+                    );
+                    let ball_pos = side.orient(transform.translation.truncate());
+                    let arrival = (face_x - ball_pos.x) / movement.x;
+                    predict_intercept(ball_pos, movement, face_x, wall_limit)
+                        .map(|intercept| (arrival, intercept, bounce_count.0))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+
+            ai.target = match approach {
+                Some((_, intercept, bounce_count)) => {
+                    if ai.aim_bounce != Some(bounce_count) {
+                        let error = ai.difficulty.prediction_error();
+                        ai.aim_error = ai.rng.random_range(-error..=error);
+                        ai.aim_bounce = Some(bounce_count);
+                    }
+                    intercept + ai.aim_error
+                }
+                None => 0.0,
+            };
+        }
+
+        let speed = side.orient(Vec2::new(paddle_velocity.x, paddle_velocity.y)).y;
+        let distance = ai.target - paddle_pos.y - rules.movement.stopping_distance(speed);
+        let max_axis = ai.difficulty.speed_factor();
+        input.axis = if distance.abs() < AI_DEAD_ZONE || delta <= 0.0 {
+            0.0
+        } else {
+            (distance / (settings.paddle_speed * delta)).clamp(-max_axis, max_axis)
+                * paddle::control_direction(&power_ups, side)
+        };
+    }
+}
+
+/// Predicts the height at which a ball will reach `target_x`
+///
+/// Follows the ball along its current movement, reflecting it off the top and
+/// bottom walls whenever it would pass `wall_limit`, the furthest the ball's
+/// center gets from the middle of the field. Returns None if the ball is
+/// travelling away from `target_x`.
+pub fn predict_intercept(ball_pos: Vec2, movement: Vec2, target_x: f32, wall_limit: f32) -> Option<f32> {
+    let distance = target_x - ball_pos.x;
+    if movement.x == 0.0 || distance.signum() != movement.x.signum() {
+        return None;
+    }
+
+    let unfolded_y = ball_pos.y + movement.y * distance / movement.x;
+
+    // Bouncing between two walls repeats every two field heights
+    let span = wall_limit * 2.0;
+    let folded = (unfolded_y + wall_limit).rem_euclid(span * 2.0);
+    let folded = if folded > span { span * 2.0 - folded } else { folded };
+
+    Some(folded - wall_limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn straight_shot_arrives_at_its_own_height() {
+        let intercept = predict_intercept(Vec2::new(0.0, 30.0), Vec2::new(100.0, 0.0), 300.0, 200.0);
+
+        assert_eq!(intercept, Some(30.0));
+    }
+
+    #[test]
+    fn ball_moving_away_has_no_intercept() {
+        let intercept = predict_intercept(Vec2::new(0.0, 0.0), Vec2::new(-100.0, 20.0), 300.0, 200.0);
+
+        assert_eq!(intercept, None);
+    }
+
+    #[test]
+    fn prediction_follows_wall_bounces() {
+        // Rises 250 on the way: hits the top wall at 200 and comes back down to 150
+        let one_bounce = predict_intercept(Vec2::new(0.0, 0.0), Vec2::new(100.0, 100.0), 250.0, 200.0);
+        // Rises 650: up to the top wall, down to the bottom wall, then back up to -150
+        let two_bounces = predict_intercept(Vec2::new(0.0, 0.0), Vec2::new(-100.0, 100.0), -650.0, 200.0);
+
+        assert!((one_bounce.unwrap() - 150.0).abs() < 1e-3);
+        assert!((two_bounces.unwrap() + 150.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn difficulty_is_parsed_from_args() {
+        let args = ["--ai-right", "hard", "--ai-left", "sideways", "--ai-left-forward", "easy"].map(String::from);
+
+        let players = AiPlayers::from_args(args);
+
+        assert_eq!(players.right, Some(Difficulty::Hard));
+        assert_eq!(players.left, None);
+        assert_eq!(players.get(Paddle::back(Side::Right)), Some(Difficulty::Hard));
+        assert_eq!(players.get(Paddle { side: Side::Left, slot: PaddleSlot::Forward }), Some(Difficulty::Easy));
+    }
+}
@@ -0,0 +1,602 @@
+/// Module for arenas: the obstacles, portals and goals laid out on the field
+use std::f32::consts::TAU;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::paddle::{Paddle, Side};
+use crate::scoreboard::{MatchRules, MatchStatus};
+use crate::settings::PongSettings;
+use crate::state::InGame;
+
+/// Folder the Level Select screen looks in for arena files
+pub const LEVEL_DIR: &str = "levels";
+/// Most obstacles an arena may have
+pub const MAX_OBSTACLES: usize = 16;
+/// Most pairs of portals an arena may have
+pub const MAX_PORTALS: usize = 4;
+/// Thinnest an obstacle may be, so players can see it
+pub const MIN_OBSTACLE_SIZE: f32 = 8.0;
+/// Color of the obstacles and the walls beside narrow goals
+const BLOCK_COLOR: Color = Color::srgb(0.55, 0.55, 0.6);
+/// Colors of the portal pairs, in order
+const PORTAL_COLORS: [Color; MAX_PORTALS] = [
+    Color::srgb(0.2, 0.8, 0.9),
+    Color::srgb(1.0, 0.55, 0.1),
+    Color::srgb(0.9, 0.2, 0.6),
+    Color::srgb(0.6, 0.9, 0.2),
+];
+/// Arena files that come with the game
+const BUILT_IN: [&str; 4] = [
+    include_str!("../assets/arenas/pillars.ron"),
+    include_str!("../assets/arenas/sweepers.ron"),
+    include_str!("../assets/arenas/portals.ron"),
+    include_str!("../assets/arenas/fortress.ron"),
+];
+
+/// How a moving obstacle travels
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Motion {
+    /// Offset from the obstacle's starting position to the far end of its path
+    pub travel: Vec2,
+    /// Seconds of play to go to the far end and back
+    pub period: f32,
+}
+
+/// A block the ball bounces off
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Obstacle {
+    /// Center of the block at the start of the match
+    pub position: Vec2,
+    /// Width and height of the block
+    pub size: Vec2,
+    /// Path the block follows, or None for a block that stays put
+    #[serde(default)]
+    pub motion: Option<Motion>,
+}
+
+impl Obstacle {
+    /// Center of the block after `elapsed` seconds of play
+    ///
+    /// A moving block eases back and forth between its starting position and the
+    /// far end of its path, so where it is depends on the match clock alone.
+    pub fn position_at(&self, elapsed: f32) -> Vec2 {
+        match self.motion {
+            Some(motion) if motion.period > 0.0 => {
+                let along = (1.0 - (elapsed / motion.period * TAU).cos()) / 2.0;
+                self.position + motion.travel * along
+            }
+            _ => self.position,
+        }
+    }
+
+    /// Smallest rectangle holding the block wherever it is on its path, as its center and size
+    fn reach(&self) -> (Vec2, Vec2) {
+        let travel = self.motion.map_or(Vec2::ZERO, |motion| motion.travel);
+        (self.position + travel / 2.0, self.size + travel.abs())
+    }
+}
+
+/// Two linked squares that send a ball entering one out of the other
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Portal {
+    /// Centers of the two ends
+    pub ends: [Vec2; 2],
+    /// Width and height of each end
+    pub size: Vec2,
+}
+
+impl Portal {
+    /// Where a ball at `position` moving along `velocity` comes out, if it has entered either end
+    ///
+    /// The ball keeps its place relative to the end it went into, and is then moved on
+    /// in its direction of travel until it is clear of the other end.
+    pub fn exit(&self, position: Vec2, velocity: Vec2) -> Option<Vec2> {
+        let half = self.size / 2.0;
+        let [first, second] = self.ends;
+        let (entrance, exit) = if is_inside(position, first, half) {
+            (first, second)
+        } else if is_inside(position, second, half) {
+            (second, first)
+        } else {
+            return None;
+        };
+
+        let offset = position - entrance;
+        let direction = velocity.normalize_or_zero();
+        let clear = [(offset.x, direction.x, half.x), (offset.y, direction.y, half.y)]
+            .into_iter()
+            .filter(|&(_, along, _)| along != 0.0)
+            .map(|(offset, along, half)| (half * along.signum() - offset) / along)
+            .fold(f32::INFINITY, f32::min);
+        let clear = if clear.is_finite() { clear + 1.0 } else { 0.0 };
+        Some(exit + offset + direction * clear)
+    }
+}
+
+/// Whether `point` is inside the rectangle centered on `center` reaching `half` each way
+fn is_inside(point: Vec2, center: Vec2, half: Vec2) -> bool {
+    let gap = (point - center).abs();
+    gap.x < half.x && gap.y < half.y
+}
+
+/// Layout of the field a match is played on
+///
+/// Every arena is laid out on the default field, with the origin in the middle.
+/// Obstacles and portals are kept out of the paddles' lanes and the middle of the
+/// field, where balls are launched, and a goal narrower than the field has walls
+/// beside it that send the ball back like the top and bottom walls do.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Arena {
+    /// Name shown on the menus
+    pub name: String,
+    /// Blocks the ball bounces off
+    pub obstacles: Vec<Obstacle>,
+    /// Portals the ball passes through
+    pub portals: Vec<Portal>,
+    /// Height of the opening in the left end of the field, or None for the whole end
+    pub left_goal: Option<f32>,
+    /// Height of the opening in the right end of the field, or None for the whole end
+    pub right_goal: Option<f32>,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Arena {
+            name: "Classic".to_string(),
+            obstacles: Vec::new(),
+            portals: Vec::new(),
+            left_goal: None,
+            right_goal: None,
+        }
+    }
+}
+
+impl Arena {
+    /// The arenas that come with the game, starting with the empty Classic field
+    pub fn built_in() -> Vec<Arena> {
+        let files = BUILT_IN.iter().map(|text| ron::from_str(text).expect("built-in arenas are valid"));
+        std::iter::once(Arena::default()).chain(files).collect()
+    }
+
+    /// Reads the arena from `--arena <name|path>` on the command line
+    ///
+    /// The name of a built-in arena picks it, and anything else is loaded as an
+    /// arena file. Arenas that fail to load are reported and the Classic field is used.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut arena = Arena::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg != "--arena" {
+                continue;
+            }
+            let value = args.next().unwrap_or_default();
+            let built_in = Arena::built_in().into_iter().find(|arena| arena.name.eq_ignore_ascii_case(&value));
+            match built_in.map_or_else(|| Arena::load(Path::new(&value), &PongSettings::default()), Ok) {
+                Ok(loaded) => arena = loaded,
+                Err(err) => warn!("Could not load arena from '{}': {}", value, err),
+            }
+        }
+
+        arena
+    }
+
+    /// Loads and checks an arena file
+    pub fn load(path: &Path, settings: &PongSettings) -> Result<Arena, ArenaError> {
+        let arena: Arena = ron::from_str(&fs::read_to_string(path)?)?;
+        arena.validate(settings)?;
+        Ok(arena)
+    }
+
+    /// Loads every arena file in `dir`, sorted by file name
+    ///
+    /// A missing folder has no arenas in it. Files that fail to load are listed
+    /// with the reason.
+    pub fn load_dir(dir: &Path, settings: &PongSettings) -> Vec<(PathBuf, Result<Arena, ArenaError>)> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+            .collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|path| {
+                let arena = Arena::load(&path, settings);
+                (path, arena)
+            })
+            .collect()
+    }
+
+    /// Checks that the arena fits on the field and leaves room to play
+    ///
+    /// Every problem found is listed in the ArenaError::Invalid returned.
+    pub fn validate(&self, settings: &PongSettings) -> Result<(), ArenaError> {
+        let half_field = settings.field_size / 2.0;
+        // Room for the ball between a paddle's face and anything in front of it
+        let lane = settings.paddle_position(Paddle::back(Side::Right)).x - settings.paddle_size.x / 2.0 - settings.ball_size.x;
+        let launch = settings.ball_size;
+        let mut problems = Vec::new();
+
+        if self.name.trim().is_empty() {
+            problems.push("the arena has no name".to_string());
+        }
+        if self.obstacles.len() > MAX_OBSTACLES {
+            problems.push(format!("{} obstacles is more than the {} allowed", self.obstacles.len(), MAX_OBSTACLES));
+        }
+        if self.portals.len() > MAX_PORTALS {
+            problems.push(format!("{} portals is more than the {} allowed", self.portals.len(), MAX_PORTALS));
+        }
+
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            let label = format!("obstacle {}", index + 1);
+            if obstacle.size.min_element() < MIN_OBSTACLE_SIZE {
+                problems.push(format!("{label} is thinner than {MIN_OBSTACLE_SIZE} pixels"));
+            }
+            if let Some(motion) = obstacle.motion
+                && (motion.period.is_nan() || motion.period <= 0.0)
+            {
+                problems.push(format!("{label} moves with a period that is not above zero"));
+            }
+            let (center, size) = obstacle.reach();
+            problems.extend(placement_problems(&label, center, size, half_field, lane, launch));
+        }
+
+        for (index, portal) in self.portals.iter().enumerate() {
+            let label = format!("portal {}", index + 1);
+            if portal.size.min_element() <= 0.0 {
+                problems.push(format!("{label} has no size"));
+            }
+            let [first, second] = portal.ends;
+            if is_inside(first, second, portal.size) {
+                problems.push(format!("{label} has ends that overlap"));
+            }
+            for (end, center) in ["first", "second"].into_iter().zip(portal.ends) {
+                let label = format!("the {end} end of {label}");
+                problems.extend(placement_problems(&label, center, portal.size, half_field, lane, launch));
+            }
+        }
+
+        for (side, goal) in [("left", self.left_goal), ("right", self.right_goal)] {
+            if let Some(width) = goal
+                && (width.is_nan() || width <= settings.ball_size.y || width > settings.field_size.y)
+            {
+                problems.push(format!(
+                    "the {side} goal is {width} pixels, which must be wider than the ball and at most the field's height"
+                ));
+            }
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(ArenaError::Invalid(problems)) }
+    }
+
+    /// Height of the goal on `side`, or None if it is the whole end of the field
+    ///
+    /// The goals along the top and bottom of a four-player match always span the field.
+    pub fn goal(&self, side: Side) -> Option<f32> {
+        match side {
+            Side::Left => self.left_goal,
+            Side::Right => self.right_goal,
+            Side::Top | Side::Bottom => None,
+        }
+    }
+
+    /// Whether a ball at height `y` can go into the goal on `side`
+    pub fn goal_open(&self, side: Side, y: f32) -> bool {
+        self.goal(side).is_none_or(|width| y.abs() <= width / 2.0)
+    }
+
+    /// What is in the way of `paddles`, for the report
+    ///
+    /// validate only keeps the lanes of the left and right back paddles clear. Every
+    /// paddle in a match also needs the strip of field it slides along kept clear, with
+    /// room for the ball either side, so forward paddles and those along the top and
+    /// bottom are checked here. Obstacles are checked over their whole path.
+    pub fn lane_problems(&self, settings: &PongSettings, paddles: &[Paddle]) -> Vec<String> {
+        let obstacles = self
+            .obstacles
+            .iter()
+            .enumerate()
+            .map(|(index, obstacle)| (format!("obstacle {}", index + 1), obstacle.reach()));
+        let portals = self.portals.iter().enumerate().flat_map(|(index, portal)| {
+            ["first", "second"]
+                .into_iter()
+                .zip(portal.ends)
+                .map(move |(end, center)| (format!("the {end} end of portal {}", index + 1), (center, portal.size)))
+        });
+        let rectangles: Vec<(String, (Vec2, Vec2))> = obstacles.chain(portals).collect();
+
+        let mut problems = Vec::new();
+        for &paddle in paddles {
+            let side = paddle.side;
+            let line = side.orient(settings.paddle_position(paddle)).x;
+            let room = settings.paddle_size.x / 2.0 + side.orient(settings.ball_size).x;
+            for (label, (center, size)) in &rectangles {
+                if (side.orient(*center).x - line).abs() < side.orient(*size).x / 2.0 + room {
+                    problems.push(format!("{label} is in the way of the {} paddle", paddle.name()));
+                }
+            }
+        }
+        problems
+    }
+
+    /// How far in front of its starting line `paddle` can go before it gets in the way of the arena
+    ///
+    /// Stops short of the nearest obstacle anywhere on its path or portal end
+    /// ahead of the paddle, with room for the ball in between, and is infinite
+    /// when there is nothing ahead.
+    pub fn room_in_front(&self, settings: &PongSettings, paddle: Paddle) -> f32 {
+        let side = paddle.side;
+        let inward = side.orient(side.inward()).x;
+        let line = side.orient(settings.paddle_position(paddle)).x;
+        let room = settings.paddle_size.x / 2.0 + side.orient(settings.ball_size).x;
+        let obstacles = self.obstacles.iter().map(Obstacle::reach);
+        let portals = self.portals.iter().flat_map(|portal| portal.ends.map(|center| (center, portal.size)));
+
+        obstacles
+            .chain(portals)
+            .filter_map(|(center, size)| {
+                let ahead = inward * (side.orient(center).x - line);
+                let half_depth = side.orient(size).x / 2.0;
+                // Anything entirely behind the paddle is never in its way
+                (ahead + half_depth > -room).then(|| (ahead - half_depth - room).max(0.0))
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Center and size of every obstacle after `elapsed` seconds of play
+    pub fn blocks_at(&self, elapsed: f32) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        self.obstacles.iter().map(move |obstacle| (obstacle.position_at(elapsed), obstacle.size))
+    }
+}
+
+/// What is wrong with a rectangle of an arena centered on `center`, for the report
+fn placement_problems(label: &str, center: Vec2, size: Vec2, half_field: Vec2, lane: f32, launch: Vec2) -> Vec<String> {
+    let mut problems = Vec::new();
+    let reach = center.abs() + size / 2.0;
+    if reach.y > half_field.y {
+        problems.push(format!("{label} goes past the top or bottom of the field"));
+    }
+    if reach.x > lane {
+        problems.push(format!("{label} is in a paddle's lane, more than {lane} pixels from the middle"));
+    }
+    if is_inside(Vec2::ZERO, center, (size + launch) / 2.0) {
+        problems.push(format!("{label} covers the middle of the field, where balls are launched"));
+    }
+    problems
+}
+
+/// Errors that can happen while loading an arena
+#[derive(Debug)]
+pub enum ArenaError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not a valid arena
+    Parse(ron::error::SpannedError),
+    /// The arena was read but doesn't fit the field, with every problem found
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ArenaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArenaError::Io(error) => write!(f, "{}", error),
+            ArenaError::Parse(error) => write!(f, "invalid arena file: {}", error),
+            ArenaError::Invalid(problems) => {
+                write!(f, "arena has {} problem(s):", problems.len())?;
+                for problem in problems {
+                    write!(f, "\n- {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArenaError {}
+
+impl From<io::Error> for ArenaError {
+    fn from(error: io::Error) -> Self {
+        ArenaError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for ArenaError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        ArenaError::Parse(error)
+    }
+}
+
+/// Component for the sprite of an obstacle, with its index in the arena
+#[derive(Component)]
+pub struct ObstacleSprite(pub usize);
+
+/// Plugin drawing the arena of the MatchRules
+///
+/// Obstacles, portals and the walls beside narrow goals are spawned on entering
+/// InGame, and moving obstacles are drawn where the match clock puts them. The
+/// ball's collisions with them are worked out by the BallPlugin.
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(InGame), spawn_arena)
+            .add_systems(Update, place_obstacles.run_if(in_state(InGame)));
+    }
+}
+
+/// Spawns the sprites of the arena's obstacles, portals and goal walls
+pub fn spawn_arena(mut commands: Commands, rules: Res<MatchRules>, settings: Res<PongSettings>) {
+    let arena = &rules.arena;
+    let block = |size: Vec2| Sprite {
+        color: BLOCK_COLOR,
+        custom_size: Some(size),
+        ..default()
+    };
+
+    for (index, obstacle) in arena.obstacles.iter().enumerate() {
+        commands.spawn((
+            block(obstacle.size),
+            Transform::from_translation(obstacle.position.extend(0.2)),
+            ObstacleSprite(index),
+            StateScoped(InGame),
+        ));
+    }
+
+    for (portal, color) in arena.portals.iter().zip(PORTAL_COLORS) {
+        for end in portal.ends {
+            commands.spawn((
+                Sprite {
+                    color: color.with_alpha(0.6),
+                    custom_size: Some(portal.size),
+                    ..default()
+                },
+                Transform::from_translation(end.extend(0.1)),
+                StateScoped(InGame),
+            ));
+        }
+    }
+
+    // Walls above and below a goal that doesn't take up the whole end
+    let half_field = settings.field_size / 2.0;
+    let thickness = settings.paddle_size.x / 2.0;
+    for side in [Side::Left, Side::Right] {
+        let Some(width) = arena.goal(side) else {
+            continue;
+        };
+        let height = (settings.field_size.y - width) / 2.0;
+        let x = (half_field.x - thickness / 2.0) * if side == Side::Left { -1.0 } else { 1.0 };
+        for direction in [1.0, -1.0] {
+            let y = direction * (half_field.y - height / 2.0);
+            commands.spawn((
+                block(Vec2::new(thickness, height)),
+                Transform::from_translation(Vec3::new(x, y, 0.2)),
+                StateScoped(InGame),
+            ));
+        }
+    }
+}
+
+/// Draws the obstacles where the match clock puts them
+pub fn place_obstacles(
+    rules: Res<MatchRules>,
+    status: Res<MatchStatus>,
+    mut sprite_query: Query<(&mut Transform, &ObstacleSprite)>,
+) {
+    for (mut transform, sprite) in sprite_query.iter_mut() {
+        if let Some(obstacle) = rules.arena.obstacles.get(sprite.0) {
+            let position = obstacle.position_at(status.elapsed);
+            if transform.translation.truncate() != position {
+                transform.translation = position.extend(transform.translation.z);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_arenas_are_valid() {
+        let settings = PongSettings::default();
+        for arena in Arena::built_in() {
+            assert_eq!(arena.validate(&settings).map_err(|err| err.to_string()), Ok(()), "{}", arena.name);
+        }
+    }
+
+    #[test]
+    fn malformed_arena_lists_every_problem() {
+        let text = "(
+            name: \"Broken\",
+            obstacles: [
+                (position: (0.0, 0.0), size: (40.0, 40.0)),
+                (position: (0.0, 200.0), size: (4.0, 100.0)),
+                (position: (330.0, 100.0), size: (20.0, 20.0)),
+            ],
+            left_goal: Some(5.0),
+        )";
+        let arena: Arena = ron::from_str(text).unwrap();
+
+        let Err(ArenaError::Invalid(problems)) = arena.validate(&PongSettings::default()) else {
+            panic!("arena should not be valid");
+        };
+        assert_eq!(problems.len(), 5, "{problems:?}");
+        assert!(problems[0].contains("obstacle 1") && problems[0].contains("middle"));
+        assert!(problems.iter().any(|problem| problem.contains("left goal")));
+        assert!(ron::from_str::<Arena>("(obstacles: 3)").is_err());
+    }
+
+    #[test]
+    fn arenas_in_the_way_of_forward_paddles_are_reported() {
+        let settings = PongSettings::default();
+        let doubles = MatchRules { doubles: true, ..default() }.paddles();
+        let four_players = MatchRules { four_player: Some(default()), ..default() }.paddles();
+
+        let built_in = |name: &str| Arena::built_in().into_iter().find(|arena| arena.name == name).unwrap();
+        for name in ["Classic", "Pillars", "Fortress"] {
+            let arena = built_in(name);
+            assert!(arena.lane_problems(&settings, &doubles).is_empty(), "{name}");
+            assert!(arena.lane_problems(&settings, &four_players).is_empty(), "{name}");
+        }
+        // The sweepers come too close to the top and bottom paddles for the ball to get between
+        let problems = built_in("Sweepers").lane_problems(&settings, &four_players);
+        assert!(problems.iter().any(|problem| problem.contains("Top paddle")), "{problems:?}");
+        let portals = built_in("Portals");
+        let problems = portals.lane_problems(&settings, &doubles);
+        assert!(problems.iter().any(|problem| problem.contains("Left Forward")), "{problems:?}");
+
+        let mut rules = MatchRules { doubles: true, arena: portals, ..default() };
+        assert!(rules.fit_arena(&settings));
+        assert_eq!(rules.arena, Arena::default());
+    }
+
+    #[test]
+    fn paddles_have_room_up_to_the_nearest_obstacle_ahead() {
+        let settings = PongSettings::default();
+        let left = Paddle::back(Side::Left);
+        let line = settings.paddle_position(left).x;
+        let arena = Arena {
+            obstacles: vec![
+                Obstacle { position: Vec2::new(-150.0, 100.0), size: Vec2::new(20.0, 40.0), motion: None },
+                Obstacle { position: Vec2::new(150.0, 100.0), size: Vec2::new(20.0, 40.0), motion: None },
+            ],
+            ..default()
+        };
+
+        assert_eq!(Arena::default().room_in_front(&settings, left), f32::INFINITY);
+        let room = -160.0 - line - settings.paddle_size.x / 2.0 - settings.ball_size.x;
+        assert_eq!(arena.room_in_front(&settings, left), room);
+    }
+
+    #[test]
+    fn moving_obstacle_goes_to_the_end_of_its_path_and_back() {
+        let obstacle = Obstacle {
+            position: Vec2::new(50.0, -100.0),
+            size: Vec2::splat(20.0),
+            motion: Some(Motion { travel: Vec2::new(0.0, 200.0), period: 4.0 }),
+        };
+
+        assert_eq!(obstacle.position_at(0.0), obstacle.position);
+        assert!((obstacle.position_at(2.0) - Vec2::new(50.0, 100.0)).length() < 1e-3);
+        assert!((obstacle.position_at(4.0) - obstacle.position).length() < 1e-3);
+    }
+
+    #[test]
+    fn portal_sends_the_ball_out_of_the_other_end() {
+        let portal = Portal { ends: [Vec2::new(-100.0, 0.0), Vec2::new(100.0, 50.0)], size: Vec2::splat(20.0) };
+
+        let exit = portal.exit(Vec2::new(-95.0, 2.0), Vec2::new(10.0, 0.0)).unwrap();
+
+        assert!(exit.x > 110.0 && exit.x < 112.0, "came out at {exit}");
+        assert_eq!(exit.y, 52.0);
+        assert_eq!(portal.exit(exit, Vec2::new(10.0, 0.0)), None);
+        assert_eq!(portal.exit(Vec2::ZERO, Vec2::new(10.0, 0.0)), None);
+    }
+}
@@ -1,23 +1,30 @@
 /// Module for handling the game's background visual elements
 use bevy::prelude::*;
-use crate::{paddle::PADDLE_WIDTH, window::{WIDTH, HEIGHT}};
+use crate::settings::PongSettings;
 
-/// Width of the dotted line in the middle of the playing field
-pub const MIDDLE_LINE_WIDTH: f32 = PADDLE_WIDTH;
+/// Plugin drawing the playing field and its dotted center line at startup
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_background);
+    }
+}
 
 /// Spawns the game background with dark play area and dotted centerline
 ///
 /// Creates a dark rectangle covering the entire play area as the background,
 /// then adds a series of evenly spaced small rectangles to form a dotted line
 /// down the center of the screen, creating the classic Pong visual style.
-pub fn spawn_background(mut commands: Commands) {
-    let half_width = WIDTH as f32 / 2.0;
-    let half_height = HEIGHT as f32 / 2.0;
+/// The dots are as wide as a paddle.
+pub fn spawn_background(mut commands: Commands, settings: Res<PongSettings>) {
+    let half_width = settings.field_size.x / 2.0;
+    let half_height = settings.field_size.y / 2.0;
 
     // Create grid border
     commands.spawn((
         Sprite {
-            color: Color::srgb(0.1, 0.1, 0.1),
+            color: settings.background_color,
             custom_size: Some(Vec2::new(half_width * 2.0, half_height * 2.0)),
             ..default()
         },
@@ -25,15 +32,15 @@ pub fn spawn_background(mut commands: Commands) {
     ));
 
     // Create center line
-    let center_pixel_size = MIDDLE_LINE_WIDTH;
+    let center_pixel_size = settings.paddle_size.x;
     let center_pixel_height = center_pixel_size * 2.0;
     let center_offset = 0.0;
 
-    for y in -((HEIGHT / 2) as i32)..(HEIGHT / 2) as i32 {
+    for y in -(half_height as i32)..half_height as i32 {
         if y % 2 == 0 {
             commands.spawn((
                 Sprite {
-                    color: Color::srgb(0.3, 0.3, 0.3),
+                    color: settings.center_line_color,
                     custom_size: Some(Vec2::new(center_pixel_size, center_pixel_height)),
                     ..default()
                 },
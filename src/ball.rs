@@ -1,23 +1,40 @@
 /// Module for handling the ball's behavior, physics, and collision detection
 use bevy::prelude::*;
-use crate::paddle::{LeftPaddle, RightPaddle, PADDLE_WIDTH};
-use crate::scoreboard::ScoreEvent;
-use crate::window::{WIDTH, HEIGHT};
-use rand::Rng;
+use crate::events::{BallEventWriters, PaddleHit, RallyEnded, Serve, Wall, WallBounce};
+use crate::game::PongSet;
+use crate::paddle::{Paddle, PaddleInput, PaddleSlot, Side, PADDLE_WIDTH};
+use crate::multiball::RallyEnd;
+use crate::powerup::PowerUpState;
+use crate::scoreboard::{LifeLost, MatchRules, MatchStatus, ScoreBoard, ScoreEvent};
+use crate::settings::PongSettings;
+use crate::speed::SpeedProgression;
+use crate::simulation::Interpolated;
+use crate::state::{GameState, InGame};
+use serde::{Deserialize, Serialize};
 
-/// Height of the ball sprite
+/// Default height of the ball sprite
 pub const BALL_HEIGHT: f32 = PADDLE_WIDTH;
-/// Width of the ball sprite
+/// Default width of the ball sprite
 pub const BALL_WIDTH: f32 = PADDLE_WIDTH;
-/// Initial speed of the ball when the game starts or after scoring
+/// Default initial speed of the ball when the game starts or after scoring
 pub const INITIAL_BALL_SPEED: f32 = 125.0;
+/// Maximum number of collisions resolved for a single ball in one frame
+const MAX_COLLISION_STEPS: usize = 4;
+/// Steepest angle from the horizontal, in radians, at which the ball can leave a paddle
+pub const MAX_BOUNCE_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+/// Share of the paddle's velocity along its edge passed on to the ball when they touch
+///
+/// Set to zero to make the bounce depend on the contact point alone.
+pub const PADDLE_VELOCITY_INFLUENCE: f32 = 0.25;
+/// Longest a sticky paddle holds on to the ball before letting it go, in seconds
+pub const STICKY_HOLD: f32 = 1.0;
 
 /// Component for identifying the ball entity
 #[derive(Component)]
 pub struct Ball;
 
 /// Component for entities that have movement velocity
-#[derive(Component)]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Velocity {
     /// X-axis velocity component
     pub x: f32,
@@ -26,38 +43,158 @@ pub struct Velocity {
 }
 
 /// Tracks ball bounces to control speed progression
-#[derive(Component, Default)]
-pub struct BounceCount(u32);
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BounceCount(pub u32);
+
+/// Seconds the ball has been in play since it was last served
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RallyTime(pub f32);
+
+/// Whether a ball is taking part in the rally
+///
+/// Every ball a match can need is spawned when it starts, and the ones not in
+/// play wait hidden in the middle of the field until a multi-ball rally calls
+/// for them.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InPlay(pub bool);
+
+/// Where a sticky paddle is holding the ball
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hold {
+    /// Side of the paddle holding the ball
+    pub side: Side,
+    /// Which of the side's paddles is holding the ball
+    #[serde(default)]
+    pub slot: PaddleSlot,
+    /// Distance of the ball from the paddle's center along the paddle, up or to the right
+    pub offset: f32,
+    /// Seconds before the paddle lets go on its own
+    pub remaining: f32,
+}
 
-/// Spawns the ball entity at the center of the screen with initial velocity
+/// Component for a ball caught by a sticky paddle
 ///
-/// Creates a white rectangular sprite to represent the ball,
-/// positioned at the center of the screen. Attaches Velocity and
-/// BounceCount components to control its movement and difficulty progression.
-pub fn spawn_ball(mut commands: Commands) {
-    commands.spawn((
+/// A held ball moves with the paddle until its player serves it or STICKY_HOLD
+/// runs out, then leaves with the velocity it was given when it was caught.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Held(pub Option<Hold>);
+
+/// Component for the paddle that last sent the ball on its way
+///
+/// Set when a paddle serves or returns the ball and cleared when the rally is
+/// over. Balls put in play from the middle of the field have no last hit until
+/// a paddle touches them.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LastHit(pub Option<Paddle>);
+
+/// A bounce found while stepping the ball, sent as an event once the step is done
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bounce {
+    /// The ball came off a paddle
+    Paddle(PaddleHit),
+    /// The ball came off a wall
+    Wall(WallBounce),
+}
+
+/// Position and size of a paddle as seen by the ball's collision checks
+struct PaddleBounds {
+    /// Side the paddle guards
+    side: Side,
+    /// Whether this is the side's back or forward paddle
+    slot: PaddleSlot,
+    /// Center of the paddle
+    position: Vec3,
+    /// Width and height of the paddle
+    size: Vec2,
+    /// Current movement of the paddle in pixels per second
+    velocity: Vec2,
+    /// Whether the paddle catches the ball instead of returning it
+    sticky: bool,
+}
+
+/// The edges and obstacles of the field as seen by the ball's collision checks
+struct FieldBounds<'a> {
+    /// Half the width and height of the field
+    half_size: Vec2,
+    /// Whether the top and bottom edges are walls, rather than goals with a player defending them
+    walls: [bool; 2],
+    /// Center and size of every obstacle
+    blocks: &'a [(Vec3, Vec2)],
+}
+
+/// Plugin spawning the ball and running its physics
+///
+/// The balls are spawned on entering InGame, moved and bounced in PongSet::Physics
+/// and taken out of play when the rally ends in PongSet::Scoring. Sends the
+/// gameplay events for serves, paddle hits, wall bounces, rally milestones and
+/// the end of each rally.
+pub struct BallPlugin;
+
+impl Plugin for BallPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(InGame), spawn_ball)
+            .add_systems(OnTransition { exited: GameState::Countdown, entered: GameState::Playing }, serve_ball)
+            .add_systems(FixedUpdate, update_ball.in_set(PongSet::Physics))
+            .add_systems(FixedUpdate, reset_ball_system.in_set(PongSet::Scoring))
+            .add_systems(Update, show_balls_in_play);
+    }
+}
+
+/// Spawns every ball the match can need at the center of the screen
+///
+/// One ball is spawned in play with its initial velocity, plus as many resting
+/// balls as the MatchRules can put in play at once.
+pub fn spawn_ball(mut commands: Commands, settings: Res<PongSettings>, rules: Res<MatchRules>) {
+    let served = Velocity {
+        x: settings.ball_speed,
+        y: settings.ball_speed,
+    };
+    commands.spawn((ball_bundle(&settings, Vec3::ZERO, served), InPlay(true), StateScoped(InGame)));
+
+    let resting = Velocity { x: 0.0, y: 0.0 };
+    for _ in 1..rules.ball_pool_size() {
+        commands.spawn((ball_bundle(&settings, Vec3::ZERO, resting), InPlay(false), StateScoped(InGame)));
+    }
+}
+
+/// Components of a ball at `translation` moving with `velocity`
+///
+/// Creates a rectangular sprite with the size and color from PongSettings.
+/// Attaches Velocity, BounceCount and RallyTime components to control its
+/// movement and difficulty progression, and a LastHit for the paddle that
+/// last touched it.
+pub fn ball_bundle(settings: &PongSettings, translation: Vec3, velocity: Velocity) -> impl Bundle {
+    (
         Sprite {
-            color: Color::WHITE,
-            custom_size: Some(Vec2::new(BALL_WIDTH, BALL_HEIGHT)),
+            color: settings.ball_color,
+            custom_size: Some(settings.ball_size),
             ..default()
         },
         Transform {
-            translation: Vec3::new(0.0, 0.0, 0.0),
+            translation,
             ..default()
         },
         Ball,
-        Velocity {
-            x: INITIAL_BALL_SPEED,
-            y: INITIAL_BALL_SPEED,
-        },
+        velocity,
         BounceCount(0),
-    ));
+        RallyTime(0.0),
+        Held::default(),
+        LastHit::default(),
+        Interpolated::at(translation),
+    )
+}
+
+/// Hides the balls that are not in play
+pub fn show_balls_in_play(mut ball_query: Query<(&InPlay, &mut Visibility), (With<Ball>, Changed<InPlay>)>) {
+    for (in_play, mut visibility) in ball_query.iter_mut() {
+        *visibility = if in_play.0 { Visibility::Inherited } else { Visibility::Hidden };
+    }
 }
 
 /// Check if two rectangles are colliding
 ///
 /// Uses axis-aligned bounding box (AABB) collision detection to determine
-/// if the ball is overlapping with a paddle. Returns true if a collision is detected.
+/// if the ball is overlapping with a paddle or obstacle. Returns true if a collision is detected.
 fn is_colliding(
     ball_pos: Vec3,
     ball_size: Vec2,
@@ -70,102 +207,472 @@ fn is_colliding(
     ball_pos.y - ball_size.y/2.0 <= paddle_pos.y + paddle_size.y/2.0
 }
 
-/// Get speed multiplier based on bounce count to increase difficulty over time
-///
-/// Returns a Vec2 with multipliers for the x and y velocities based on
-/// how many times the ball has bounced. Creates a difficulty progression
-/// where the ball moves faster horizontally as the rally continues.
-fn get_speed_multiplier(bounce_count: u32) -> Vec2 {
-    match bounce_count {
-        0..=3 => Vec2::new(1.0, 1.0),
-        4..=11 => Vec2::new(1.6, 1.0),
-        _ => Vec2::new(2.1, 1.0)
-    }
-}
-
 /// Main ball update system - handles movement, collisions and scoring
 ///
-/// Updates the ball's position based on its velocity, detects and responds to
-/// collisions with paddles and walls, and triggers scoring events when the ball
-/// goes beyond the screen boundaries. Also handles progressive difficulty increases.
+/// Updates the position of every ball in play based on its velocity, detects and
+/// responds to collisions with paddles and walls, and takes a ball out of play
+/// when it goes beyond the screen boundaries. Which of those balls score a point,
+/// or cost a life in a four-player match, depends on the MultiBallRules'
+/// RallyEnd, and once the last one is out a RallyEnded event is sent. The ball
+/// speeds up over the rally as the SpeedProgression in the MatchRules says.
+/// Running power-ups make balls faster, paddles sticky and shields bounce balls
+/// back from behind the goals. The MatchRules' Arena adds obstacles to bounce
+/// off, portals to pass through and walls beside goals narrower than the field.
+/// The goal of a player who is out of a four-player match is walled off.
+#[allow(clippy::too_many_arguments)]
 pub fn update_ball(
-    mut ball_query: Query<(&mut Transform, &mut Velocity, &mut BounceCount, &Sprite), With<Ball>>,
-    left_paddle_query: Query<(&Transform, &Sprite), (With<LeftPaddle>, Without<Ball>)>,
-    right_paddle_query: Query<(&Transform, &Sprite), (With<RightPaddle>, Without<Ball>)>,
+    mut ball_query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut BounceCount,
+            &mut RallyTime,
+            &mut InPlay,
+            &mut Held,
+            &mut LastHit,
+            &Sprite,
+        ),
+        With<Ball>,
+    >,
+    paddle_query: Query<(&Paddle, &Transform, &Sprite, &Velocity, &PaddleInput), Without<Ball>>,
+    settings: Res<PongSettings>,
+    rules: Res<MatchRules>,
+    power_ups: Res<PowerUpState>,
+    status: Res<MatchStatus>,
+    scoreboard: Res<ScoreBoard>,
     time: Res<Time>,
     mut score_event_writer: EventWriter<ScoreEvent>,
+    mut life_lost_writer: EventWriter<LifeLost>,
+    mut ball_events: BallEventWriters,
 ) {
     // Early return if paddles don't exist yet
-    let Ok((left_paddle, left_paddle_sprite)) = left_paddle_query.get_single() else {
-        return;
-    };
-    let Ok((right_paddle, right_paddle_sprite)) = right_paddle_query.get_single() else {
+    if paddle_query.is_empty() {
         return;
+    }
+
+    let half_size = settings.field_size / 2.0;
+    let mut bounces = Vec::new();
+    let mut balls_in_play = 0;
+    let mut points = Vec::new();
+    let blocks: Vec<(Vec3, Vec2)> = rules
+        .arena
+        .blocks_at(status.elapsed)
+        .map(|(position, size)| (position.extend(0.0), size))
+        .collect();
+    let field = FieldBounds {
+        half_size,
+        walls: [Side::Top, Side::Bottom].map(|side| !scoreboard.in_play(&rules, side)),
+        blocks: &blocks,
     };
+    // Sorted so paddles the ball reaches at the same moment are always taken in the same order
+    let mut paddles: Vec<PaddleBounds> = paddle_query
+        .iter()
+        .filter(|(paddle, ..)| scoreboard.in_play(&rules, paddle.side))
+        .map(|(paddle, transform, sprite, velocity, _)| PaddleBounds {
+            side: paddle.side,
+            slot: paddle.slot,
+            position: transform.translation,
+            size: sprite.custom_size.unwrap(),
+            velocity: Vec2::new(velocity.x, velocity.y),
+            sticky: power_ups.sticky(paddle.side),
+        })
+        .collect();
+    paddles.sort_by_key(|paddle| (paddle.side.index(), paddle.slot));
+
+    for (
+        mut transform,
+        mut velocity,
+        mut bounce_count,
+        mut rally_time,
+        mut in_play,
+        mut held,
+        mut last_hit,
+        ball_sprite,
+    ) in ball_query.iter_mut()
+    {
+        if !in_play.0 {
+            continue;
+        }
+        balls_in_play += 1;
 
-    let half_width = WIDTH as f32 / 2.0;
-    let half_height = HEIGHT as f32 / 2.0;
-    
-    for (mut transform, mut velocity, mut bounce_count, ball_sprite) in ball_query.iter_mut() {
         let ball_size = ball_sprite.custom_size.unwrap();
-        let left_paddle_size = left_paddle_sprite.custom_size.unwrap();
-        let right_paddle_size = right_paddle_sprite.custom_size.unwrap();
-        
-        // Speed increases after multiple bounces
-        let speed_multiplier = get_speed_multiplier(bounce_count.0);
 
-        // Calculate and apply movement
-        let delta = time.delta_secs();
-        let movement = Vec2::new(velocity.x * delta * speed_multiplier.x, velocity.y * delta * speed_multiplier.y);
-        
-        transform.translation.x += movement.x;
-        transform.translation.y += movement.y;
-        
-        handle_paddle_collisions(
-            &mut transform, 
-            &mut velocity, 
+        if let Some(hold) = held.0 {
+            // The ball rides on the face of the paddle holding it
+            let side = hold.side;
+            let Some((_, paddle, paddle_sprite, _, input)) =
+                paddle_query.iter().find(|(paddle, ..)| **paddle == Paddle { side, slot: hold.slot })
+            else {
+                held.0 = None;
+                continue;
+            };
+            let paddle_size = side.orient(paddle_sprite.custom_size.unwrap());
+            let mut position = side.orient(paddle.translation.truncate());
+            position.x += side.orient(side.inward()).x * (paddle_size.x + side.orient(ball_size).x) / 2.0;
+            position.y += hold.offset;
+            transform.translation = side.orient(position).extend(transform.translation.z);
+            let remaining = hold.remaining - time.delta_secs();
+            held.0 = (remaining > 0.0 && !input.serve).then_some(Hold { remaining, ..hold });
+            rally_time.0 += time.delta_secs();
+            continue;
+        }
+
+        let pace = BallPace {
+            progression: &rules.speed,
+            rally_time: rally_time.0,
+            sides: rules.sides(),
+            burst: Side::ALL.map(|side| power_ups.burst(side)),
+        };
+        step_ball(
+            &mut transform,
+            &mut velocity,
             &mut bounce_count,
+            &mut held.0,
+            &mut last_hit.0,
+            &pace,
             ball_size,
-            left_paddle.translation, 
-            left_paddle_size,
-            right_paddle.translation, 
-            right_paddle_size
+            time.delta_secs(),
+            &paddles,
+            &field,
+            &mut bounces,
+        );
+        rally_time.0 += time.delta_secs();
+        let heading = Vec2::new(velocity.x, velocity.y);
+        if let Some(exit) =
+            rules.arena.portals.iter().find_map(|portal| portal.exit(transform.translation.truncate(), heading))
+        {
+            transform.translation = exit.extend(transform.translation.z);
+        }
+        let position = transform.translation.truncate();
+        bounce_off_ends(
+            &mut transform,
+            &mut velocity,
+            ball_size,
+            half_size,
+            Side::ALL.map(|side| {
+                power_ups.shielded(side)
+                    || !rules.arena.goal_open(side, side.orient(position).y)
+                    || !scoreboard.in_play(&rules, side)
+            }),
         );
         
-        handle_wall_collisions(&mut transform, &mut velocity, ball_size, half_height);
-        
-        check_for_scoring(&transform, half_width, &mut score_event_writer);
+        if let Some(conceded) = check_for_scoring(&transform, half_size) {
+            in_play.0 = false;
+            *velocity = Velocity { x: 0.0, y: 0.0 };
+            points.push(conceded);
+        }
+    }
+
+    for bounce in bounces {
+        match bounce {
+            Bounce::Paddle(hit) => ball_events.send_paddle_hit(hit),
+            Bounce::Wall(wall_bounce) => {
+                ball_events.wall_bounces.send(wall_bounce);
+            }
+        }
+    }
+
+    let mut concede = |side: Side| {
+        if rules.four_player.is_some() {
+            life_lost_writer.send(LifeLost { side });
+        } else if let Some(point) = ScoreEvent::conceded_by(side) {
+            score_event_writer.send(point);
+        }
+    };
+    let rally_over = !points.is_empty() && points.len() == balls_in_play;
+    match rules.multi_ball.rally_end {
+        RallyEnd::EveryBallScores => {
+            points.iter().copied().for_each(&mut concede);
+        }
+        // Only the ball that ends the rally counts, even if several leave at once
+        RallyEnd::LastBallOut => {
+            if rally_over && let Some(&conceded) = points.last() {
+                concede(conceded);
+            }
+        }
+    }
+    if rally_over && let Some(&conceded) = points.last() {
+        ball_events.rally_ends.send(RallyEnded { conceded });
+    }
+}
+
+/// How fast the ball moves at this point in its rally
+struct BallPace<'a> {
+    /// The match's speed progression
+    progression: &'a SpeedProgression,
+    /// Seconds since the ball was served
+    rally_time: f32,
+    /// The sides with a paddle in the match
+    sides: &'a [Side],
+    /// Extra speed multipliers for balls heading towards each player, indexed like Side::ALL
+    burst: [f32; 4],
+}
+
+impl BallPace<'_> {
+    /// Movement per second of a ball with `velocity` after `bounces` paddle hits
+    fn velocity(&self, velocity: &Velocity, bounces: u32) -> Vec2 {
+        let heading = Vec2::new(velocity.x, velocity.y);
+        let burst = Side::toward(heading, self.sides.iter().copied()).map_or(1.0, |side| self.burst[side.index()]);
+        self.progression.ball_velocity(heading, bounces, self.rally_time) * burst
+    }
+}
+
+/// Advances the ball by `delta` seconds, bouncing off paddles, walls and obstacles on the way
+///
+/// The frame is split at every paddle, wall and obstacle contact: the ball travels
+/// up to the point of impact, bounces, and then spends the rest of the frame moving
+/// in its new direction at the speed that matches its new bounce count. This keeps
+/// fast balls and long frames from tunnelling through paddles and obstacles, and
+/// keeps the ball on the field while it is swept against the paddles. Any time left
+/// after MAX_COLLISION_STEPS contacts is spent on one more straight move. Every
+/// bounce is added to `bounces`.
+/// A sticky paddle sets `held` and the ball stops there for the rest of the frame.
+#[allow(clippy::too_many_arguments)]
+fn step_ball(
+    transform: &mut Transform,
+    velocity: &mut Velocity,
+    bounce_count: &mut BounceCount,
+    held: &mut Option<Hold>,
+    last_hit: &mut Option<Paddle>,
+    pace: &BallPace,
+    ball_size: Vec2,
+    delta: f32,
+    paddles: &[PaddleBounds],
+    field: &FieldBounds,
+    bounces: &mut Vec<Bounce>,
+) {
+    let mut remaining = delta;
+
+    for _ in 0..MAX_COLLISION_STEPS {
+        if remaining <= 0.0 {
+            break;
+        }
+
+        // Speed increases as the rally goes on
+        let movement = pace.velocity(velocity, bounce_count.0) * remaining;
+        // Only go as far as the first wall or obstacle in the way, unless a paddle is hit first
+        let obstacle = sweep_field(transform.translation.truncate(), ball_size, movement, field);
+        let reach = obstacle.map_or(1.0, |(time_of_impact, ..)| time_of_impact);
+        let hits = bounce_count.0;
+
+        let travelled = reach * handle_paddle_collisions(
+            transform,
+            velocity,
+            bounce_count,
+            held,
+            last_hit,
+            pace,
+            ball_size,
+            movement * reach,
+            paddles,
+            bounces,
+        );
+        if let Some((_, across_x, wall)) = obstacle
+            && bounce_count.0 == hits
+            && held.is_none()
+        {
+            if across_x {
+                velocity.x = -velocity.x;
+            } else {
+                velocity.y = -velocity.y;
+            }
+            bounces.push(Bounce::Wall(WallBounce { wall }));
+        }
+
+        handle_wall_collisions(transform, velocity, ball_size, field, bounces);
+        handle_obstacle_collisions(transform, velocity, ball_size, field.blocks, bounces);
+
+        remaining -= remaining * travelled;
+    }
+
+    // Still bouncing after every step, so finish the frame without looking for more contacts
+    if remaining > 0.0 && held.is_none() {
+        let movement = pace.velocity(velocity, bounce_count.0) * remaining;
+        transform.translation += movement.extend(0.0);
+        handle_wall_collisions(transform, velocity, ball_size, field, bounces);
+        handle_obstacle_collisions(transform, velocity, ball_size, field.blocks, bounces);
     }
 }
 
+/// Sweeps the ball along its movement and returns the time of impact with a paddle face
+///
+/// Works in the frame of a left or right paddle, as given by Side::orient, and
+/// for obstacle faces with the axes swapped as needed. Treats the
+/// ball's leading edge as a ray travelling towards `face_x` and returns the fraction
+/// of `movement` covered when it gets there, provided the ball and paddle overlap
+/// along the paddle at that moment. Returns None if the face is not reached this step.
+fn sweep_paddle_face(
+    ball_pos: Vec2,
+    ball_size: Vec2,
+    movement: Vec2,
+    paddle_pos: Vec2,
+    paddle_size: Vec2,
+    face_x: f32,
+) -> Option<f32> {
+    if movement.x == 0.0 {
+        return None;
+    }
+
+    let leading_edge = ball_pos.x + ball_size.x/2.0 * movement.x.signum();
+    let time_of_impact = (face_x - leading_edge) / movement.x;
+    if !(0.0..=1.0).contains(&time_of_impact) {
+        return None;
+    }
+
+    let contact_y = ball_pos.y + movement.y * time_of_impact;
+    let reach = (ball_size.y + paddle_size.y) / 2.0;
+    ((contact_y - paddle_pos.y).abs() <= reach).then_some(time_of_impact)
+}
+
+/// Sweeps the ball along its movement and returns the time of impact with the first wall or obstacle it reaches
+///
+/// The top and bottom walls are reached when the ball's edge gets to them, and each
+/// face of an obstacle is swept like a paddle face, so a ball can't pass through
+/// however fast it goes. Also says whether the face that was hit lies across the x
+/// axis, so the caller knows which way to bounce, and which wall it was. Walls and
+/// obstacles the ball already overlaps are left to handle_wall_collisions and
+/// handle_obstacle_collisions.
+fn sweep_field(ball_pos: Vec2, ball_size: Vec2, movement: Vec2, field: &FieldBounds) -> Option<(f32, bool, Wall)> {
+    let limit = field.half_size.y - ball_size.y / 2.0;
+    let walls = [(Wall::Top, field.walls[0], limit), (Wall::Bottom, field.walls[1], -limit)]
+        .into_iter()
+        .filter(|&(_, closed, edge)| closed && movement.y * edge > 0.0)
+        .map(|(wall, _, edge)| ((edge - ball_pos.y) / movement.y, false, wall))
+        .filter(|(time_of_impact, ..)| (0.0..=1.0).contains(time_of_impact));
+
+    let obstacles = field.blocks.iter().flat_map(|&(position, size)| {
+        let position = position.truncate();
+        let face_x = position.x - movement.x.signum() * size.x / 2.0;
+        let face_y = position.y - movement.y.signum() * size.y / 2.0;
+        let across_x = sweep_paddle_face(ball_pos, ball_size, movement, position, size, face_x);
+        let across_y = sweep_paddle_face(ball_pos.yx(), ball_size.yx(), movement.yx(), position.yx(), size.yx(), face_y);
+        [across_x.map(|time| (time, true, Wall::Obstacle)), across_y.map(|time| (time, false, Wall::Obstacle))]
+    });
+
+    walls.chain(obstacles.flatten()).min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
 /// Handles ball collisions with paddles
 ///
-/// Detects when the ball collides with either paddle and reverses its horizontal
-/// velocity. Also adjusts the ball position to prevent it from getting stuck
-/// inside paddles, and increments the bounce count for difficulty progression.
+/// Moves the ball along `movement`, stopping at the first paddle it would hit on the way.
+/// On contact the ball is sent back at an angle that depends on where it struck the
+/// paddle, placed against the paddle face, the paddle is kept as the ball's last
+/// hit, and the bounce count is incremented for difficulty progression. A sticky
+/// paddle holds on to the ball instead, which uses up the whole of `movement`.
+/// Paddles along the top and bottom are handled like the left and right ones with
+/// the axes swapped. Returns the fraction of `movement` that was used, so the
+/// caller can spend the rest of the frame.
+#[allow(clippy::too_many_arguments)]
 fn handle_paddle_collisions(
     transform: &mut Transform,
     velocity: &mut Velocity,
     bounce_count: &mut BounceCount,
+    held: &mut Option<Hold>,
+    last_hit: &mut Option<Paddle>,
+    pace: &BallPace,
     ball_size: Vec2,
-    left_paddle_pos: Vec3,
-    left_paddle_size: Vec2,
-    right_paddle_pos: Vec3,
-    right_paddle_size: Vec2,
-) {
-    // Check left paddle collision
-    if is_colliding(transform.translation, ball_size, left_paddle_pos, left_paddle_size) && velocity.x < 0.0 {
-        velocity.x = -velocity.x;
-        // Push the ball outside the paddle to prevent sticking
-        transform.translation.x = left_paddle_pos.x + left_paddle_size.x/2.0 + ball_size.x/2.0;
-        bounce_count.0 += 1;
+    movement: Vec2,
+    paddles: &[PaddleBounds],
+    bounces: &mut Vec<Bounce>,
+) -> f32 {
+    let heading = Vec2::new(velocity.x, velocity.y);
+    let ball_pos = transform.translation.truncate();
+
+    // A paddle may have moved into the ball since the last step, in which case the
+    // bounce happens straight away instead of being swept
+    let contact = paddles
+        .iter()
+        .filter(|paddle| paddle.side.inward().dot(heading) < 0.0)
+        .filter_map(|paddle| {
+            let side = paddle.side;
+            let time_of_impact = if is_colliding(transform.translation, ball_size, paddle.position, paddle.size) {
+                Some(0.0)
+            } else {
+                let paddle_pos = side.orient(paddle.position.truncate());
+                let paddle_size = side.orient(paddle.size);
+                let face_x = paddle_pos.x + side.orient(side.inward()).x * paddle_size.x/2.0;
+                let (ball_pos, ball_size, movement) =
+                    (side.orient(ball_pos), side.orient(ball_size), side.orient(movement));
+                sweep_paddle_face(ball_pos, ball_size, movement, paddle_pos, paddle_size, face_x)
+            };
+            time_of_impact.map(|time_of_impact| (time_of_impact, paddle))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0));
+
+    let Some((time_of_impact, paddle)) = contact else {
+        transform.translation.x += movement.x;
+        transform.translation.y += movement.y;
+        return 1.0;
+    };
+
+    // Work as if this were a left or right paddle
+    let side = paddle.side;
+    let direction = side.orient(side.inward()).x;
+    let paddle_pos = side.orient(paddle.position.truncate());
+    let paddle_size = side.orient(paddle.size);
+    let ball_depth = side.orient(ball_size);
+    let mut position = side.orient(ball_pos + movement * time_of_impact);
+    // Place the ball against the paddle face to prevent sticking
+    position.x = paddle_pos.x + direction * (paddle_size.x + ball_depth.x)/2.0;
+
+    let reach = (ball_depth.y + paddle_size.y) / 2.0;
+    let contact_offset = ((position.y - paddle_pos.y) / reach).clamp(-1.0, 1.0);
+    let incoming = side.orient(heading);
+    let deflected = deflect_off_paddle(
+        &Velocity { x: incoming.x, y: incoming.y },
+        contact_offset,
+        side.orient(paddle.velocity),
+        direction,
+    );
+    let outgoing = side.orient(Vec2::new(deflected.x, deflected.y));
+    *velocity = Velocity { x: outgoing.x, y: outgoing.y };
+    transform.translation = side.orient(position).extend(transform.translation.z);
+    *last_hit = Some(Paddle { side, slot: paddle.slot });
+    bounce_count.0 += 1;
+
+    bounces.push(Bounce::Paddle(PaddleHit {
+        side,
+        contact_offset,
+        speed: pace.velocity(velocity, bounce_count.0).length(),
+        bounces: bounce_count.0,
+    }));
+
+    if paddle.sticky {
+        *held = Some(Hold {
+            side,
+            slot: paddle.slot,
+            offset: position.y - paddle_pos.y,
+            remaining: STICKY_HOLD,
+        });
+        return 1.0;
     }
-    // Check right paddle collision
-    else if is_colliding(transform.translation, ball_size, right_paddle_pos, right_paddle_size) && velocity.x > 0.0 {
-        velocity.x = -velocity.x;
-        // Push the ball outside the paddle to prevent sticking
-        transform.translation.x = right_paddle_pos.x - right_paddle_size.x/2.0 - ball_size.x/2.0;
-        bounce_count.0 += 1;
+    time_of_impact
+}
+
+/// Works out the ball's velocity as it leaves a paddle
+///
+/// `contact_offset` runs from -1 at the bottom edge of the paddle to 1 at the top edge.
+/// A hit in the center sends the ball back flat and hits towards the edges send it away
+/// more steeply, with part of the paddle's own vertical velocity added on top. The angle
+/// is capped at MAX_BOUNCE_ANGLE and the ball keeps its speed. `direction` is the sign of
+/// the outgoing horizontal velocity. Hits off the top and bottom paddles are worked out
+/// with the axes swapped.
+fn deflect_off_paddle(
+    velocity: &Velocity,
+    contact_offset: f32,
+    paddle_velocity: Vec2,
+    direction: f32,
+) -> Velocity {
+    let speed = Vec2::new(velocity.x, velocity.y).length();
+    let angle = contact_offset * MAX_BOUNCE_ANGLE;
+
+    let aimed = Vec2::new(angle.cos(), angle.sin()) * speed;
+    let spun = aimed + Vec2::new(0.0, paddle_velocity.y * PADDLE_VELOCITY_INFLUENCE);
+    let angle = spun.y.atan2(spun.x).clamp(-MAX_BOUNCE_ANGLE, MAX_BOUNCE_ANGLE);
+
+    Velocity {
+        x: angle.cos() * speed * direction,
+        y: angle.sin() * speed,
     }
 }
 
@@ -173,73 +680,507 @@ fn handle_paddle_collisions(
 ///
 /// Detects when the ball hits the top or bottom of the screen and
 /// reverses its vertical velocity. Also adjusts the ball position
-/// to prevent it from going beyond the screen boundaries.
+/// to prevent it from going beyond the screen boundaries. Edges that
+/// are goals in a four-player match are left open.
 fn handle_wall_collisions(
     transform: &mut Transform,
     velocity: &mut Velocity,
     ball_size: Vec2,
-    half_height: f32
+    field: &FieldBounds,
+    bounces: &mut Vec<Bounce>,
 ) {
+    let [top_wall, bottom_wall] = field.walls;
+    let half_height = field.half_size.y;
     // Bounce off the top wall
-    if transform.translation.y > half_height - ball_size.y/2.0 {
+    if top_wall && transform.translation.y > half_height - ball_size.y/2.0 {
         velocity.y = -velocity.y.abs(); // Ensure negative
         transform.translation.y = half_height - ball_size.y/2.0;
+        bounces.push(Bounce::Wall(WallBounce { wall: Wall::Top }));
     } 
     // Bounce off the bottom wall
-    else if transform.translation.y < -half_height + ball_size.y/2.0 {
+    else if bottom_wall && transform.translation.y < -half_height + ball_size.y/2.0 {
         velocity.y = velocity.y.abs(); // Ensure positive
         transform.translation.y = -half_height + ball_size.y/2.0;
+        bounces.push(Bounce::Wall(WallBounce { wall: Wall::Bottom }));
     }
 }
 
-/// Checks if ball went past paddles and sends appropriate score events
+/// Handles ball collisions with the arena's obstacles
 ///
-/// Determines if the ball has gone beyond the left or right screen boundaries
-/// and sends the corresponding ScoreEvent to update the score and reset the ball.
-fn check_for_scoring(
-    transform: &Transform, 
-    half_width: f32,
-    score_event_writer: &mut EventWriter<ScoreEvent>
+/// A ball overlapping an obstacle is pushed back out along the axis it overlaps
+/// least, and sent away from the obstacle along that axis if it was heading into it.
+fn handle_obstacle_collisions(
+    transform: &mut Transform,
+    velocity: &mut Velocity,
+    ball_size: Vec2,
+    blocks: &[(Vec3, Vec2)],
+    bounces: &mut Vec<Bounce>,
 ) {
-    // Right side (left player scores)
-    if transform.translation.x > half_width {
-        score_event_writer.send(ScoreEvent::LeftScored);
-    } 
-    // Left side (right player scores)
-    else if transform.translation.x < -half_width {
-        score_event_writer.send(ScoreEvent::RightScored);
+    for &(position, size) in blocks {
+        if !is_colliding(transform.translation, ball_size, position, size) {
+            continue;
+        }
+        let reach = (ball_size + size) / 2.0;
+        let gap = transform.translation.truncate() - position.truncate();
+        let overlap = reach - gap.abs();
+
+        let heading_in = if overlap.x < overlap.y {
+            let side = gap.x.signum();
+            transform.translation.x = position.x + side * reach.x;
+            let heading_in = velocity.x * side < 0.0;
+            velocity.x = side * velocity.x.abs();
+            heading_in
+        } else {
+            let side = gap.y.signum();
+            transform.translation.y = position.y + side * reach.y;
+            let heading_in = velocity.y * side < 0.0;
+            velocity.y = side * velocity.y.abs();
+            heading_in
+        };
+        if heading_in {
+            bounces.push(Bounce::Wall(WallBounce { wall: Wall::Obstacle }));
+        }
     }
 }
 
-/// Resets ball position and sets velocity after scoring
+/// Sends balls back into play off the ends of the field where they can't score
 ///
-/// Listens for ScoreEvent events and resets the ball to the center of the screen
-/// with a new random velocity direction. The horizontal direction is based on
-/// which player just scored, while the vertical direction is randomized.
-pub fn reset_ball_system(
-    mut score_events: EventReader<ScoreEvent>,
-    mut ball_query: Query<(&mut Transform, &mut Velocity, &mut BounceCount), With<Ball>>,
+/// `closed` says, for each side in Side::ALL order, whether a ball at its current
+/// position is kept out of that side's goal, by a shield, by the walls beside a
+/// narrow goal or because the player there is out of the match. A ball whose
+/// trailing edge reaches a closed end of the field is turned around there.
+fn bounce_off_ends(
+    transform: &mut Transform,
+    velocity: &mut Velocity,
+    ball_size: Vec2,
+    half_size: Vec2,
+    closed: [bool; 4],
 ) {
-    for event in score_events.read() {
-        if let Ok((mut transform, mut velocity, mut bounce_count)) = ball_query.get_single_mut() {
-            // Reset ball position and bounce count
-            transform.translation = Vec3::new(0.0, 0.0, 0.0);
-            bounce_count.0 = 0;
-            
-            // Determine direction based on who scored
-            let send_right = match event {
-                ScoreEvent::LeftScored => false, // Left scored, send to right
-                ScoreEvent::RightScored => true, // Right scored, send to left
-            };
-            
-            let direction = if send_right { 1.0 } else { -1.0 };
-            
-            // Random up or down direction
-            let y_direction = if rand::rng().random_bool(0.5) { 1.0 } else { -1.0 };
-            
-            // Set velocity
-            velocity.x = INITIAL_BALL_SPEED * direction;
-            velocity.y = INITIAL_BALL_SPEED * y_direction;
+    for side in Side::ALL.into_iter().filter(|side| closed[side.index()]) {
+        let outward = -side.inward();
+        let edge = (half_size - ball_size/2.0).dot(outward.abs());
+        let position = transform.translation.truncate();
+        let heading = Vec2::new(velocity.x, velocity.y);
+        if heading.dot(outward) > 0.0 && position.dot(outward) > edge {
+            let turned = heading - outward * 2.0 * heading.dot(outward);
+            *velocity = Velocity { x: turned.x, y: turned.y };
+            let placed = position - outward * (position.dot(outward) - edge);
+            transform.translation = placed.extend(transform.translation.z);
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Checks if ball went past a paddle and returns the side it got past
+///
+/// Determines if the ball has gone beyond any edge of the screen. In a
+/// two-player match only the left and right edges can be reached.
+fn check_for_scoring(transform: &Transform, half_size: Vec2) -> Option<Side> {
+    let position = transform.translation.truncate();
+    Side::ALL
+        .into_iter()
+        .find(|side| position.dot(-side.inward()) > half_size.dot(side.inward().abs()))
+}
+
+/// Sends a Serve for every ball as it is put in play at the end of the countdown
+pub fn serve_ball(
+    ball_query: Query<(&Velocity, &InPlay), With<Ball>>,
+    rules: Res<MatchRules>,
+    scoreboard: Res<ScoreBoard>,
+    mut serve_writer: EventWriter<Serve>,
+) {
+    for (velocity, _) in ball_query.iter().filter(|(_, in_play)| in_play.0) {
+        if let Some(toward) = Side::toward(Vec2::new(velocity.x, velocity.y), scoreboard.sides_in_play(&rules)) {
+            serve_writer.send(Serve { toward });
+        }
+    }
+}
+
+/// The ball components cleared between rallies
+pub type RallyBall = (
+    &'static mut Transform,
+    &'static mut Velocity,
+    &'static mut BounceCount,
+    &'static mut RallyTime,
+    &'static mut InPlay,
+    &'static mut Held,
+    &'static mut LastHit,
+);
+
+/// Gathers the balls up after a rally
+///
+/// Listens for RallyEnded events and puts the balls back with reset_balls.
+pub fn reset_ball_system(mut rally_ends: EventReader<RallyEnded>, mut ball_query: Query<RallyBall, With<Ball>>) {
+    if rally_ends.read().count() == 0 {
+        return;
+    }
+    reset_balls(&mut ball_query);
+}
+
+/// Stops every ball in the middle of the field
+///
+/// Each ball has its bounce count, rally time and last hit cleared and no paddle
+/// holding it. Only the first ball stays in play, to be put on the serving
+/// player's paddle when the serve countdown starts.
+pub fn reset_balls(ball_query: &mut Query<RallyBall, With<Ball>>) {
+    for (index, (mut transform, mut velocity, mut bounce_count, mut rally_time, mut in_play, mut held, mut last_hit)) in
+        ball_query.iter_mut().enumerate()
+    {
+        transform.translation = Vec3::ZERO;
+        *velocity = Velocity { x: 0.0, y: 0.0 };
+        bounce_count.0 = 0;
+        rally_time.0 = 0.0;
+        held.0 = None;
+        last_hit.0 = None;
+        in_play.set_if_neq(InPlay(index == 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::window::{HEIGHT, WIDTH};
+
+    const BALL_SIZE: Vec2 = Vec2::new(BALL_WIDTH, BALL_HEIGHT);
+    const PADDLE_SIZE: Vec2 = Vec2::new(PADDLE_WIDTH, 28.0);
+    const LEFT_PADDLE: Vec3 = Vec3::new(-330.0, 0.0, 0.0);
+    const RIGHT_PADDLE: Vec3 = Vec3::new(330.0, 0.0, 0.0);
+
+    fn pace(progression: &SpeedProgression) -> BallPace<'_> {
+        BallPace { progression, rally_time: 0.0, sides: &Side::ALL[..2], burst: [1.0; 4] }
+    }
+
+    fn paddle(side: Side, slot: PaddleSlot, position: Vec3, sticky: bool) -> PaddleBounds {
+        PaddleBounds { side, slot, position, size: side.orient(PADDLE_SIZE), velocity: Vec2::ZERO, sticky }
+    }
+
+    fn paddles(sticky: bool) -> [PaddleBounds; 2] {
+        [
+            paddle(Side::Left, PaddleSlot::Back, LEFT_PADDLE, sticky),
+            paddle(Side::Right, PaddleSlot::Back, RIGHT_PADDLE, false),
+        ]
+    }
+
+    fn field(blocks: &[(Vec3, Vec2)]) -> FieldBounds<'_> {
+        FieldBounds { half_size: Vec2::new(WIDTH as f32, HEIGHT as f32) / 2.0, walls: [true; 2], blocks }
+    }
+
+    fn step(transform: &mut Transform, velocity: &mut Velocity, bounce_count: &mut BounceCount, delta: f32) {
+        step_with_bounces(transform, velocity, bounce_count, delta);
+    }
+
+    fn step_with_bounces(
+        transform: &mut Transform,
+        velocity: &mut Velocity,
+        bounce_count: &mut BounceCount,
+        delta: f32,
+    ) -> Vec<Bounce> {
+        let mut bounces = Vec::new();
+        step_ball(
+            transform,
+            velocity,
+            bounce_count,
+            &mut None,
+            &mut None,
+            &pace(&SpeedProgression::default()),
+            BALL_SIZE,
+            delta,
+            &paddles(false),
+            &field(&[]),
+            &mut bounces,
+        );
+        bounces
+    }
+
+    #[test]
+    fn fast_ball_does_not_tunnel_through_paddle() {
+        let mut transform = Transform::from_xyz(-250.0, 0.0, 0.0);
+        // Fast enough to cross the whole paddle several times over in one frame
+        let mut velocity = Velocity { x: -20_000.0, y: 0.0 };
+        let mut bounce_count = BounceCount(0);
+
+        step(&mut transform, &mut velocity, &mut bounce_count, 1.0 / 60.0);
+
+        assert!(velocity.x > 0.0);
+        assert_eq!(bounce_count.0, 1);
+        assert!(transform.translation.x >= LEFT_PADDLE.x + PADDLE_SIZE.x / 2.0 + BALL_SIZE.x / 2.0);
+    }
+
+    #[test]
+    fn bounce_uses_rest_of_frame() {
+        let face = RIGHT_PADDLE.x - PADDLE_SIZE.x / 2.0 - BALL_SIZE.x / 2.0;
+        let mut transform = Transform::from_xyz(face - 100.0, 0.0, 0.0);
+        let mut velocity = Velocity { x: 1_000.0, y: 0.0 };
+        let mut bounce_count = BounceCount(0);
+
+        // Reaches the paddle after a tenth of a second and spends the rest coming back
+        step(&mut transform, &mut velocity, &mut bounce_count, 0.2);
+
+        assert_eq!(bounce_count.0, 1);
+        assert!(velocity.x < 0.0);
+        assert!((transform.translation.x - (face - 100.0)).abs() < 0.01);
+    }
+
+    #[test]
+    fn large_delta_bounces_off_both_paddles() {
+        let mut transform = Transform::from_xyz(0.0, 0.0, 0.0);
+        let mut velocity = Velocity { x: -INITIAL_BALL_SPEED, y: 0.0 };
+        let mut bounce_count = BounceCount(0);
+
+        // A multi-second hitch covers the width of the field more than once
+        step(&mut transform, &mut velocity, &mut bounce_count, 9.0);
+
+        assert_eq!(bounce_count.0, 2);
+        assert!(transform.translation.x > LEFT_PADDLE.x && transform.translation.x < RIGHT_PADDLE.x);
+    }
+
+    #[test]
+    fn ball_passing_beside_paddle_is_not_deflected() {
+        let mut transform = Transform::from_xyz(-250.0, 200.0, 0.0);
+        let mut velocity = Velocity { x: -20_000.0, y: 0.0 };
+        let mut bounce_count = BounceCount(0);
+
+        step(&mut transform, &mut velocity, &mut bounce_count, 1.0 / 60.0);
+
+        assert!(velocity.x < 0.0);
+        assert_eq!(bounce_count.0, 0);
+        assert!(transform.translation.x < LEFT_PADDLE.x);
+    }
+
+    #[test]
+    fn bounces_are_reported_in_order() {
+        let face = LEFT_PADDLE.x + PADDLE_SIZE.x / 2.0 + BALL_SIZE.x / 2.0;
+        // Hits the paddle near its top edge, then the top wall on the way back
+        let mut transform = Transform::from_xyz(face + 5.0, 12.0, 0.0);
+        let mut velocity = Velocity { x: -100.0, y: 0.0 };
+        let mut bounce_count = BounceCount(3);
+
+        let bounces = step_with_bounces(&mut transform, &mut velocity, &mut bounce_count, 4.0);
+
+        let [Bounce::Paddle(hit), Bounce::Wall(wall_bounce)] = bounces[..] else {
+            panic!("expected a paddle hit and then a wall bounce, got {bounces:?}");
+        };
+        assert_eq!((hit.side, hit.bounces), (Side::Left, 4));
+        assert!(hit.contact_offset > 0.5 && hit.contact_offset < 1.0);
+        assert!((hit.speed - Vec2::new(velocity.x * 1.6, velocity.y).length()).abs() < 1e-3);
+        assert_eq!(wall_bounce.wall, Wall::Top);
+    }
+
+    #[test]
+    fn center_hit_returns_flat() {
+        let velocity = Velocity { x: -100.0, y: 100.0 };
+
+        let deflected = deflect_off_paddle(&velocity, 0.0, Vec2::ZERO, 1.0);
+
+        assert!(deflected.y.abs() < 1e-3);
+        assert!((deflected.x - 100.0 * 2f32.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn edge_hit_leaves_at_max_angle_with_same_speed() {
+        let velocity = Velocity { x: 150.0, y: -20.0 };
+        let speed = Vec2::new(velocity.x, velocity.y).length();
+
+        let top = deflect_off_paddle(&velocity, 1.0, Vec2::ZERO, -1.0);
+        let bottom = deflect_off_paddle(&velocity, -1.0, Vec2::ZERO, -1.0);
+
+        assert!(top.x < 0.0 && top.y > 0.0);
+        assert!(bottom.x < 0.0 && bottom.y < 0.0);
+        for deflected in [top, bottom] {
+            assert!((deflected.y.atan2(-deflected.x).abs() - MAX_BOUNCE_ANGLE).abs() < 1e-4);
+            assert!((Vec2::new(deflected.x, deflected.y).length() - speed).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn moving_paddle_steers_the_ball_within_limits() {
+        let velocity = Velocity { x: -100.0, y: 0.0 };
+
+        let still = deflect_off_paddle(&velocity, 0.5, Vec2::ZERO, 1.0);
+        let rising = deflect_off_paddle(&velocity, 0.5, Vec2::new(0.0, 500.0), 1.0);
+        let flung = deflect_off_paddle(&velocity, 1.0, Vec2::new(0.0, 5000.0), 1.0);
+
+        assert!(rising.y > still.y);
+        assert!((Vec2::new(rising.x, rising.y).length() - 100.0).abs() < 1e-3);
+        assert!(flung.y.atan2(flung.x) <= MAX_BOUNCE_ANGLE + 1e-4);
+    }
+
+    #[test]
+    fn off_center_paddle_hit_changes_angle() {
+        let face = LEFT_PADDLE.x + PADDLE_SIZE.x / 2.0 + BALL_SIZE.x / 2.0;
+        let mut transform = Transform::from_xyz(face + 10.0, 10.0, 0.0);
+        let mut velocity = Velocity { x: -INITIAL_BALL_SPEED, y: 0.0 };
+        let mut bounce_count = BounceCount(0);
+
+        step(&mut transform, &mut velocity, &mut bounce_count, 0.1);
+
+        assert_eq!(bounce_count.0, 1);
+        assert!(velocity.x > 0.0 && velocity.y > 0.0);
+    }
+
+    #[test]
+    fn ball_bounces_off_an_obstacle() {
+        let block = (Vec3::new(100.0, 0.0, 0.0), Vec2::new(20.0, 60.0));
+        let mut transform = Transform::from_xyz(60.0, 10.0, 0.0);
+        let mut velocity = Velocity { x: INITIAL_BALL_SPEED, y: 0.0 };
+        let mut bounces = Vec::new();
+
+        step_ball(
+            &mut transform,
+            &mut velocity,
+            &mut BounceCount(0),
+            &mut None,
+            &mut None,
+            &pace(&SpeedProgression::default()),
+            BALL_SIZE,
+            0.25,
+            &paddles(false),
+            &field(&[block]),
+            &mut bounces,
+        );
+
+        // The rest of the step is spent heading back the way it came
+        assert!(velocity.x < 0.0);
+        assert!(transform.translation.x < block.0.x - (block.1.x + BALL_SIZE.x) / 2.0);
+        assert_eq!(bounces, [Bounce::Wall(WallBounce { wall: Wall::Obstacle })]);
+    }
+
+    #[test]
+    fn ball_spends_the_rest_of_the_step_after_bouncing_off_a_wall() {
+        let mut transform = Transform::from_xyz(0.0, 200.0, 0.0);
+        let mut velocity = Velocity { x: 100.0, y: 400.0 };
+        let limit = HEIGHT as f32 / 2.0 - BALL_SIZE.y / 2.0;
+
+        let bounces = step_with_bounces(&mut transform, &mut velocity, &mut BounceCount(0), 0.25);
+
+        // Reaches the wall part of the way through its 100 pixels up and comes back down the rest
+        let expected = limit - (100.0 - (limit - 200.0));
+        assert!(velocity.y < 0.0);
+        assert!((transform.translation.y - expected).abs() < 1e-3, "ball at {}", transform.translation);
+        assert_eq!(bounces, [Bounce::Wall(WallBounce { wall: Wall::Top })]);
+    }
+
+    #[test]
+    fn fast_ball_does_not_tunnel_through_an_obstacle() {
+        let block = (Vec3::new(100.0, 0.0, 0.0), Vec2::new(8.0, 60.0));
+        let mut transform = Transform::from_xyz(60.0, 10.0, 0.0);
+        // Crosses the obstacle several times over in one step
+        let mut velocity = Velocity { x: 20_000.0, y: 0.0 };
+        let mut last_hit = None;
+        let mut bounces = Vec::new();
+
+        step_ball(
+            &mut transform,
+            &mut velocity,
+            &mut BounceCount(0),
+            &mut None,
+            &mut last_hit,
+            &pace(&SpeedProgression::default()),
+            BALL_SIZE,
+            1.0 / 120.0,
+            &paddles(false),
+            &field(&[block]),
+            &mut bounces,
+        );
+
+        assert!(velocity.x < 0.0);
+        assert!(transform.translation.x < block.0.x, "ball at {}", transform.translation);
+        assert_eq!(bounces, [Bounce::Wall(WallBounce { wall: Wall::Obstacle })]);
+        assert_eq!(last_hit, None);
+    }
+
+    #[test]
+    fn sticky_paddle_catches_the_ball() {
+        let face = LEFT_PADDLE.x + PADDLE_SIZE.x / 2.0 + BALL_SIZE.x / 2.0;
+        let mut transform = Transform::from_xyz(face + 10.0, 5.0, 0.0);
+        let mut velocity = Velocity { x: -INITIAL_BALL_SPEED, y: 0.0 };
+        let mut held = None;
+        let mut bounces = Vec::new();
+
+        step_ball(
+            &mut transform,
+            &mut velocity,
+            &mut BounceCount(0),
+            &mut held,
+            &mut None,
+            &pace(&SpeedProgression::default()),
+            BALL_SIZE,
+            1.0,
+            &paddles(true),
+            &field(&[]),
+            &mut bounces,
+        );
+
+        // Held against the face, ready to leave the way it was returned
+        let hold = held.expect("ball was not caught");
+        assert_eq!((hold.side, hold.remaining), (Side::Left, STICKY_HOLD));
+        assert!((hold.offset - 5.0).abs() < 1e-3);
+        assert_eq!(transform.translation.x, face);
+        assert!(velocity.x > 0.0);
+        assert_eq!(bounces.len(), 1);
+    }
+
+    #[test]
+    fn top_paddle_returns_the_ball_and_its_open_edge_is_a_goal() {
+        let top_paddle = [paddle(Side::Top, PaddleSlot::Back, Vec3::new(0.0, 200.0, 0.0), false)];
+        let field = FieldBounds { walls: [false, true], ..field(&[]) };
+        let step_up = |x: f32, velocity: &mut Velocity| {
+            let mut transform = Transform::from_xyz(x, 150.0, 0.0);
+            step_ball(
+                &mut transform,
+                velocity,
+                &mut BounceCount(0),
+                &mut None,
+                &mut None,
+                &pace(&SpeedProgression::default()),
+                BALL_SIZE,
+                2.0,
+                &top_paddle,
+                &field,
+                &mut Vec::new(),
+            );
+            transform
+        };
+
+        let mut returned = Velocity { x: 0.0, y: INITIAL_BALL_SPEED };
+        let transform = step_up(0.0, &mut returned);
+        assert!(returned.y < 0.0 && returned.x.abs() < 1e-3, "returned at {returned:?}");
+        assert!(transform.translation.y < 200.0);
+
+        let mut missed = Velocity { x: 0.0, y: INITIAL_BALL_SPEED };
+        let transform = step_up(200.0, &mut missed);
+        assert!(missed.y > 0.0);
+        assert_eq!(check_for_scoring(&transform, field.half_size), Some(Side::Top));
+    }
+
+    #[test]
+    fn ball_passes_its_own_forward_paddle_and_meets_the_others() {
+        let forward = 190.0;
+        let mut doubles = Vec::from(paddles(false));
+        doubles.push(paddle(Side::Left, PaddleSlot::Forward, Vec3::new(-forward, 0.0, 0.0), false));
+        doubles.push(paddle(Side::Right, PaddleSlot::Forward, Vec3::new(forward, 0.0, 0.0), false));
+        let mut transform = Transform::from_xyz(-300.0, 0.0, 0.0);
+        let mut velocity = Velocity { x: 1_000.0, y: 0.0 };
+        let mut last_hit = Some(Paddle::back(Side::Left));
+        let mut bounces = Vec::new();
+
+        // Reaches the right forward paddle in about half a second, passing the left one on the way
+        step_ball(
+            &mut transform,
+            &mut velocity,
+            &mut BounceCount(0),
+            &mut None,
+            &mut last_hit,
+            &pace(&SpeedProgression::default()),
+            BALL_SIZE,
+            0.6,
+            &doubles,
+            &field(&[]),
+            &mut bounces,
+        );
+
+        assert!(velocity.x < 0.0);
+        assert!(transform.translation.x < forward);
+        assert_eq!(bounces.len(), 1);
+        assert_eq!(last_hit, Some(Paddle { side: Side::Right, slot: PaddleSlot::Forward }));
+    }
+}
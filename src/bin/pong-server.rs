@@ -0,0 +1,62 @@
+/// Entry point for the Pixel Pong dedicated server
+use bevy::log::tracing_subscriber::{fmt, EnvFilter};
+use bevy::log::DEFAULT_FILTER;
+use bevy::prelude::*;
+use bevy_pong::broadcast::EventStreamConfig;
+use bevy_pong::discovery::{self, Announcer, DISCOVERY_PORT};
+use bevy_pong::scoreboard::MatchRules;
+use bevy_pong::server::{GameServer, ServerConfig};
+use bevy_pong::simulation::SimulationSettings;
+
+/// Runs the authoritative server until it is stopped
+///
+/// Listens for players and spectators on `--port <port>`, 7878 by default. The match
+/// is simulated at `--tick-rate` ticks per second with `--seed`, under the rules set
+/// by `--target-score`, `--win-by`, `--time-limit`, `--speed-curve`, `--serve`,
+/// `--serve-timeout`, `--balls`, `--milestone-balls`, `--max-balls`, `--rally-end` and
+/// the `--power-ups` options, in the arena given by `--arena`. `--events <path>` streams the
+/// match's serves, bounces and points to a file, or standard output for `-`. The
+/// server is advertised on the local network as `--lobby-name <name>`. Joins,
+/// leaves and rejected options are logged to standard error.
+fn main() {
+    // The server never builds an app with LogPlugin, so install the log output on its own
+    install_log_output();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = ServerConfig::from_args(args.clone());
+    let settings = SimulationSettings::from_args(args.clone());
+    let rules = MatchRules::from_args(args.clone());
+    let events = EventStreamConfig::from_args(args.clone());
+    let lobby_name = discovery::lobby_name_from_args(args);
+
+    let mut server = match GameServer::bind(("0.0.0.0", config.port), settings, rules) {
+        Ok(server) => server,
+        Err(err) => {
+            error!("Could not listen on port {}: {err}", config.port);
+            std::process::exit(1);
+        }
+    };
+
+    match Announcer::new(DISCOVERY_PORT) {
+        Ok(announcer) => server.advertise(announcer, lobby_name),
+        Err(err) => warn!("Could not advertise the server on the local network: {err}"),
+    }
+    if let Some(events) = events {
+        match events.open() {
+            Ok(stream) => server.stream_events(stream),
+            Err(err) => {
+                error!("Could not open {}: {err}", events.path);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    info!("Pixel Pong server listening on port {}", config.port);
+    server.run();
+}
+
+/// Sends log output to standard error, filtered by `RUST_LOG` when it is set
+fn install_log_output() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("info,{DEFAULT_FILTER}")));
+    fmt().with_env_filter(filter).with_writer(std::io::stderr).init();
+}
@@ -0,0 +1,298 @@
+/// Module for the live stream of match events that other tools can follow
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use bevy::prelude::*;
+use serde::Serialize;
+use crate::events::{PaddleHit, Serve, WallBounce};
+use crate::game::PongSet;
+use crate::paddle::Side;
+use crate::scoreboard::{self, LifeLost, MatchStatus, MatchWon, ScoreBoard, ScoreEvent};
+
+/// Plugin noting serves, bounces, points and wins as they happen
+///
+/// Does nothing unless a MatchEventLog resource has been inserted. The events are
+/// written out once per frame when an EventStream is inserted as well. Online
+/// rollback matches replay ticks and so are not logged.
+pub struct BroadcastPlugin;
+
+impl Plugin for BroadcastPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (record_ball_events, record_points.after(scoreboard::update_scoreboard))
+                .chain()
+                .in_set(PongSet::Scoring)
+                .run_if(resource_exists::<MatchEventLog>),
+        )
+        .add_systems(
+            Last,
+            write_match_events.run_if(resource_exists::<MatchEventLog>.and(resource_exists::<EventStream>)),
+        );
+    }
+}
+
+/// Something that happened in a match
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MatchEvent {
+    /// The ball was put in play after the countdown
+    Serve {
+        /// Player the ball is heading towards
+        toward: Side,
+    },
+    /// The ball came off a paddle
+    PaddleBounce {
+        /// Side of the paddle that hit the ball
+        side: Side,
+        /// Paddle hits so far in this rally
+        bounces: u32,
+    },
+    /// The ball came off the top or bottom wall
+    WallBounce,
+    /// A player won a point
+    Score {
+        /// The player who scored
+        scorer: Side,
+        /// Left and right scores after the point
+        score: [u32; 2],
+    },
+    /// A player in a four-player match let the ball past their paddle
+    LifeLost {
+        /// The player who lost a life
+        side: Side,
+        /// Lives of the left, right, top and bottom players afterwards
+        lives: [u32; 4],
+    },
+    /// A player won the match
+    MatchWon {
+        /// The player who won
+        winner: Side,
+        /// Final left and right scores
+        score: [u32; 2],
+    },
+}
+
+impl fmt::Display for MatchEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MatchEvent::Serve { toward } => write!(f, "serve towards {toward:?}"),
+            MatchEvent::PaddleBounce { side, bounces } => write!(f, "{side:?} paddle hit, {bounces} in the rally"),
+            MatchEvent::WallBounce => write!(f, "wall bounce"),
+            MatchEvent::Score { scorer, score } => write!(f, "{scorer:?} scores, {} - {}", score[0], score[1]),
+            MatchEvent::LifeLost { side, lives } => write!(f, "{side:?} loses a life, {} left", lives[side.index()]),
+            MatchEvent::MatchWon { winner, score } => write!(f, "{winner:?} wins the match {} - {}", score[0], score[1]),
+        }
+    }
+}
+
+/// A MatchEvent with the moment it happened
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MatchEventRecord {
+    /// Seconds into the match
+    pub time: f32,
+    /// What happened
+    #[serde(flatten)]
+    pub event: MatchEvent,
+}
+
+/// Events of the current match that have not been written out yet
+#[derive(Resource, Default)]
+pub struct MatchEventLog {
+    /// Events waiting to be taken
+    pending: Vec<MatchEventRecord>,
+}
+
+impl MatchEventLog {
+    /// Takes every event recorded since the last call
+    pub fn drain(&mut self) -> Vec<MatchEventRecord> {
+        std::mem::take(&mut self.pending)
+    }
+
+    /// Notes an event at `time` seconds into the match
+    fn push(&mut self, time: f32, event: MatchEvent) {
+        self.pending.push(MatchEventRecord { time, event });
+    }
+}
+
+/// How each event is written to the stream
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EventFormat {
+    /// One JSON object per line
+    #[default]
+    Json,
+    /// One readable sentence per line
+    Text,
+}
+
+/// Command line options for the event stream
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventStreamConfig {
+    /// File to append events to, or `-` for standard output
+    pub path: String,
+    /// How each event is written
+    pub format: EventFormat,
+}
+
+impl EventStreamConfig {
+    /// Reads the event stream options from command line arguments
+    ///
+    /// Returns None unless `--events <path>` is given. `--event-format text` writes
+    /// readable lines instead of JSON.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut args = args.into_iter();
+        let mut path = None;
+        let mut format = EventFormat::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--events" => path = args.next(),
+                "--event-format" => match args.next().as_deref() {
+                    Some("text") => format = EventFormat::Text,
+                    Some("json") => format = EventFormat::Json,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        path.map(|path| EventStreamConfig { path, format })
+    }
+
+    /// Opens the file or standard output the events go to
+    pub fn open(&self) -> io::Result<EventStream> {
+        let writer: Box<dyn Write + Send + Sync> = if self.path == "-" {
+            Box::new(io::stdout())
+        } else {
+            Box::new(File::options().create(true).append(true).open(&self.path)?)
+        };
+        Ok(EventStream { writer, format: self.format })
+    }
+}
+
+/// Where match events are written, one per line
+#[derive(Resource)]
+pub struct EventStream {
+    /// Destination of the lines
+    writer: Box<dyn Write + Send + Sync>,
+    /// How each event is written
+    format: EventFormat,
+}
+
+impl EventStream {
+    /// Writes events to any writer, such as a buffer in tests
+    pub fn new(writer: impl Write + Send + Sync + 'static, format: EventFormat) -> Self {
+        EventStream { writer: Box::new(writer), format }
+    }
+
+    /// Writes one event as a line
+    fn write(&mut self, record: &MatchEventRecord) -> io::Result<()> {
+        match self.format {
+            EventFormat::Json => {
+                let line = serde_json::to_string(record).map_err(io::Error::other)?;
+                writeln!(self.writer, "{line}")
+            }
+            EventFormat::Text => writeln!(self.writer, "[{:.2}s] {}", record.time, record.event),
+        }
+    }
+}
+
+/// Notes serves, paddle hits and wall bounces sent by the ball this tick
+pub fn record_ball_events(
+    mut log: ResMut<MatchEventLog>,
+    status: Res<MatchStatus>,
+    mut serves: EventReader<Serve>,
+    mut paddle_hits: EventReader<PaddleHit>,
+    mut wall_bounces: EventReader<WallBounce>,
+) {
+    let time = status.elapsed;
+    for serve in serves.read() {
+        log.push(time, MatchEvent::Serve { toward: serve.toward });
+    }
+    for hit in paddle_hits.read() {
+        log.push(time, MatchEvent::PaddleBounce { side: hit.side, bounces: hit.bounces });
+    }
+    for _ in wall_bounces.read() {
+        log.push(time, MatchEvent::WallBounce);
+    }
+}
+
+/// Notes points, lost lives and the end of the match
+pub fn record_points(
+    mut log: ResMut<MatchEventLog>,
+    status: Res<MatchStatus>,
+    scoreboard: Res<ScoreBoard>,
+    mut score_events: EventReader<ScoreEvent>,
+    mut life_lost_events: EventReader<LifeLost>,
+    mut match_won_events: EventReader<MatchWon>,
+) {
+    let time = status.elapsed;
+    let score = [scoreboard.left, scoreboard.right];
+
+    for event in score_events.read() {
+        log.push(time, MatchEvent::Score { scorer: event.scorer(), score });
+    }
+    for event in life_lost_events.read() {
+        log.push(time, MatchEvent::LifeLost { side: event.side, lives: scoreboard.lives });
+    }
+    for event in match_won_events.read() {
+        log.push(time, MatchEvent::MatchWon { winner: event.winner, score });
+    }
+}
+
+/// Writes out the events recorded this frame
+pub fn write_match_events(mut log: ResMut<MatchEventLog>, mut stream: ResMut<EventStream>) {
+    for record in log.drain() {
+        if let Err(err) = stream.write(&record) {
+            warn!("Could not write match event: {err}");
+        }
+    }
+    // Flush every frame so anyone tailing the stream sees events as they happen
+    let _ = stream.writer.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{AiPlayers, Difficulty};
+    use crate::headless::Simulation;
+    use crate::scoreboard::MatchRules;
+    use crate::simulation::SimulationSettings;
+
+    #[test]
+    fn events_are_written_as_json_lines() {
+        let record = MatchEventRecord {
+            time: 1.5,
+            event: MatchEvent::Score { scorer: Side::Left, score: [1, 0] },
+        };
+
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"time":1.5,"event":"score","scorer":"Left","score":[1,0]}"#
+        );
+        assert_eq!(record.event.to_string(), "Left scores, 1 - 0");
+    }
+
+    #[test]
+    fn rally_is_logged_from_serve_to_point() {
+        let ai_players = AiPlayers { left: Some(Difficulty::Easy), right: Some(Difficulty::Easy), ..default() };
+        let settings = SimulationSettings { seed: 3, ..SimulationSettings::default() };
+        let mut simulation = Simulation::new(settings, ai_players, MatchRules::default());
+        simulation.app.init_resource::<MatchEventLog>();
+        simulation.start_match();
+
+        let mut events = Vec::new();
+        for _ in 0..(600.0 / simulation.timestep()) as usize {
+            if events.iter().any(|record: &MatchEventRecord| matches!(record.event, MatchEvent::Score { .. })) {
+                break;
+            }
+            simulation.tick();
+            events.extend(simulation.app.world_mut().resource_mut::<MatchEventLog>().drain());
+        }
+
+        assert!(matches!(events.last().unwrap().event, MatchEvent::Score { .. }), "nobody scored");
+        assert!(matches!(events[0].event, MatchEvent::Serve { .. }));
+        assert!(events.iter().any(|record| matches!(record.event, MatchEvent::WallBounce | MatchEvent::PaddleBounce { .. })));
+        assert!(events.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    }
+}
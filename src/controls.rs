@@ -0,0 +1,228 @@
+/// Module for the controls screen where players remap their input bindings
+use bevy::prelude::*;
+use crate::input::{AxisDirection, Binding, BindingsPath, InputAction, InputBindings};
+use crate::paddle::Paddle;
+use crate::state::GameState;
+
+/// Number of rows on the controls screen, one per player and action
+const ROW_COUNT: usize = Paddle::PLAYERS.len() * InputAction::ALL.len();
+/// Number of rows shown at once, two players to a page
+const PAGE_SIZE: usize = 2 * InputAction::ALL.len();
+/// How far a gamepad axis must be pushed before it is captured as a binding
+const CAPTURE_AXIS_THRESHOLD: f32 = 0.6;
+/// Gamepad axes that can be bound to actions
+const BINDABLE_AXES: [GamepadAxis; 6] = [
+    GamepadAxis::LeftStickX,
+    GamepadAxis::LeftStickY,
+    GamepadAxis::RightStickX,
+    GamepadAxis::RightStickY,
+    GamepadAxis::LeftZ,
+    GamepadAxis::RightZ,
+];
+/// Color of the selected row
+const SELECTED_COLOR: Color = Color::srgb(1.0, 0.85, 0.2);
+
+/// Selection and capture state of the controls screen
+#[derive(Resource, Default)]
+pub struct ControlsMenu {
+    /// Index of the highlighted row
+    pub selected: usize,
+    /// Whether the next input will be bound to the highlighted action
+    pub capturing: bool,
+}
+
+/// Component for a row of the controls screen, holding the row's position on the page
+#[derive(Component)]
+pub struct ControlsRow(pub usize);
+
+/// The player and action shown on a row of the controls screen
+///
+/// The left and right players come first, then the top and bottom players of a
+/// four-player match and the forward players of a doubles match.
+fn row_action(index: usize) -> (Paddle, InputAction) {
+    (Paddle::PLAYERS[index / InputAction::ALL.len()], InputAction::ALL[index % InputAction::ALL.len()])
+}
+
+/// Run condition that is false while the controls screen waits for a new binding
+///
+/// Lets the Quit binding itself be remapped without closing the game.
+pub fn not_capturing_binding(menu: Res<ControlsMenu>) -> bool {
+    !menu.capturing
+}
+
+/// Spawns the controls screen with a page of rows, one per action of two players
+pub fn spawn_controls_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut menu: ResMut<ControlsMenu>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    *menu = ControlsMenu::default();
+
+    commands.spawn((
+        Text2d::new("Controls"),
+        TextFont {
+            font: font.clone(),
+            font_size: 40.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, 195.0, 1.0)),
+        StateScoped(GameState::Controls),
+    ));
+
+    for index in 0..PAGE_SIZE {
+        // Leave a gap between the two players' rows
+        let gap = if index < InputAction::ALL.len() { 0.0 } else { 20.0 };
+        commands.spawn((
+            Text2d::new(""),
+            TextFont {
+                font: font.clone(),
+                font_size: 18.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            TextLayout::new_with_justify(JustifyText::Center),
+            Transform::from_translation(Vec3::new(0.0, 150.0 - index as f32 * 20.0 - gap, 1.0)),
+            ControlsRow(index),
+            StateScoped(GameState::Controls),
+        ));
+    }
+
+    commands.spawn((
+        Text2d::new("Up/Down - Select    Enter - Rebind    Delete - Clear    R - Defaults    M - Menu"),
+        TextFont {
+            font: font.clone(),
+            font_size: 16.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -200.0, 1.0)),
+        StateScoped(GameState::Controls),
+    ));
+}
+
+/// Refreshes the text of every row from the current bindings
+///
+/// The rows show the page holding the selected row. The selected row is
+/// highlighted, and shows a prompt instead of its bindings while waiting for a
+/// new input.
+pub fn update_controls_text(
+    menu: Res<ControlsMenu>,
+    bindings: Res<InputBindings>,
+    mut query: Query<(&mut Text2d, &mut TextColor, &ControlsRow)>,
+) {
+    let page_start = menu.selected / PAGE_SIZE * PAGE_SIZE;
+    for (mut text, mut color, row) in query.iter_mut() {
+        let index = page_start + row.0;
+        let (paddle, action) = row_action(index);
+        let selected = index == menu.selected;
+
+        let inputs = if selected && menu.capturing {
+            "Press a key, button or stick...".to_string()
+        } else {
+            let labels: Vec<String> = bindings.player(paddle).get(action).iter().map(Binding::label).collect();
+            if labels.is_empty() { "-".to_string() } else { labels.join(", ") }
+        };
+
+        text.0 = format!("{} {}: {}", paddle.name(), action.name_for(paddle.side), inputs);
+        color.0 = if selected { SELECTED_COLOR } else { Color::WHITE };
+    }
+}
+
+/// Handles navigation on the controls screen
+///
+/// Up and Down move the selection, Enter starts waiting for a new binding,
+/// Delete clears the selected action, R restores the default bindings and M
+/// goes back to the main menu. Changes are saved straight away.
+pub fn navigate_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut menu: ResMut<ControlsMenu>,
+    mut bindings: ResMut<InputBindings>,
+    path: Res<BindingsPath>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if menu.capturing {
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::ArrowUp) {
+        menu.selected = (menu.selected + ROW_COUNT - 1) % ROW_COUNT;
+    }
+    if keyboard.just_pressed(KeyCode::ArrowDown) {
+        menu.selected = (menu.selected + 1) % ROW_COUNT;
+    }
+    if keyboard.just_pressed(KeyCode::Enter) {
+        menu.capturing = true;
+    }
+    if keyboard.just_pressed(KeyCode::Delete) {
+        let (paddle, action) = row_action(menu.selected);
+        bindings.player_mut(paddle).get_mut(action).clear();
+        save_bindings(&bindings, &path);
+    }
+    if keyboard.just_pressed(KeyCode::KeyR) {
+        *bindings = InputBindings::default();
+        save_bindings(&bindings, &path);
+    }
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+/// Binds the next key, mouse button, gamepad button or gamepad axis to the selected action
+///
+/// The new input replaces the action's existing bindings from the same kind of
+/// device and the bindings are saved straight away.
+pub fn capture_binding(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepad_query: Query<&Gamepad>,
+    mut menu: ResMut<ControlsMenu>,
+    mut bindings: ResMut<InputBindings>,
+    path: Res<BindingsPath>,
+) {
+    if !menu.capturing {
+        return;
+    }
+
+    let gamepad_binding = gamepad_query.iter().find_map(|gamepad| {
+        gamepad
+            .get_just_pressed()
+            .next()
+            .map(|button| Binding::GamepadButton(*button))
+            .or_else(|| {
+                BINDABLE_AXES.iter().find_map(|axis| {
+                    let value = gamepad.get(*axis)?;
+                    let direction = if value > 0.0 { AxisDirection::Positive } else { AxisDirection::Negative };
+                    (value.abs() > CAPTURE_AXIS_THRESHOLD).then_some(Binding::GamepadAxis(*axis, direction))
+                })
+            })
+    });
+
+    let captured = keyboard
+        .get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| mouse.get_just_pressed().next().map(|button| Binding::Mouse(*button)))
+        .or(gamepad_binding);
+
+    let Some(binding) = captured else {
+        return;
+    };
+
+    let (paddle, action) = row_action(menu.selected);
+    let action_bindings = bindings.player_mut(paddle).get_mut(action);
+    action_bindings.retain(|existing| !existing.same_device(&binding));
+    action_bindings.push(binding);
+    menu.capturing = false;
+
+    info!("{} {} bound to {}", paddle.name(), action.name_for(paddle.side), binding.label());
+    save_bindings(&bindings, &path);
+}
+
+/// Saves the bindings, reporting rather than failing if the file can't be written
+fn save_bindings(bindings: &InputBindings, path: &BindingsPath) {
+    if let Err(error) = bindings.save(&path.0) {
+        warn!("Could not save bindings to {}: {}", path.0.display(), error);
+    }
+}
@@ -0,0 +1,241 @@
+/// Module for finding matches on the local network without typing addresses
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::rollback::NetSession;
+use crate::scoreboard::MatchRules;
+
+/// Port hosts announce their lobbies to and the Find Games screen listens on
+pub const DISCOVERY_PORT: u16 = 7879;
+/// Time between a host's announcements
+pub const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+/// Time after its last announcement before a lobby is taken off the list
+pub const LOBBY_TIMEOUT: Duration = Duration::from_secs(3);
+/// Name hosts give their lobby when `--lobby-name` is not given
+pub const DEFAULT_LOBBY_NAME: &str = "Pixel Pong";
+/// Largest datagram read from the socket
+const MAX_PACKET_SIZE: usize = 1024;
+
+/// What kind of match a lobby offers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LobbyKind {
+    /// A player hosting an online match with `--host`
+    Player,
+    /// A dedicated server
+    Server,
+}
+
+/// A match being advertised on the local network
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lobby {
+    /// Name chosen by the host
+    pub name: String,
+    /// Port the match itself is played on
+    pub port: u16,
+    /// Whether to join with `--join` or `--connect`
+    pub kind: LobbyKind,
+    /// Points needed to win
+    pub target_score: u32,
+    /// Players present so far, out of two
+    pub players: u32,
+}
+
+/// Messages sent to the discovery port
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum DiscoveryMessage {
+    /// A host telling everyone about its lobby
+    Announce(Lobby),
+}
+
+/// A lobby heard on the network with the address to join it at
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoundLobby {
+    /// Address of the match, the announcing host with the lobby's port
+    pub address: SocketAddr,
+    /// The lobby as announced
+    pub lobby: Lobby,
+}
+
+/// Sends a host's lobby to everyone on the local network
+///
+/// Announcements go to the broadcast address and to loopback, so two copies of
+/// the game on one machine find each other too.
+pub struct Announcer {
+    /// Socket the announcements are sent from
+    socket: UdpSocket,
+    /// Addresses each announcement is sent to
+    targets: Vec<SocketAddr>,
+    /// When the last announcement was sent
+    last_sent: Option<Instant>,
+}
+
+impl Announcer {
+    /// Announces to anyone listening on `discovery_port`
+    pub fn new(discovery_port: u16) -> io::Result<Self> {
+        Self::with_targets(vec![
+            SocketAddr::from((Ipv4Addr::BROADCAST, discovery_port)),
+            SocketAddr::from((Ipv4Addr::LOCALHOST, discovery_port)),
+        ])
+    }
+
+    /// Announces to the given addresses only
+    pub fn with_targets(targets: Vec<SocketAddr>) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        socket.set_nonblocking(true)?;
+        Ok(Announcer { socket, targets, last_sent: None })
+    }
+
+    /// Sends `lobby` if ANNOUNCE_INTERVAL has passed since the last announcement
+    pub fn announce(&mut self, lobby: &Lobby) {
+        if self.last_sent.is_some_and(|sent| sent.elapsed() < ANNOUNCE_INTERVAL) {
+            return;
+        }
+        self.last_sent = Some(Instant::now());
+
+        let packet = ron::to_string(&DiscoveryMessage::Announce(lobby.clone()))
+            .expect("discovery messages always serialize")
+            .into_bytes();
+        for target in &self.targets {
+            // Networks without broadcast refuse the packet, which only means fewer listeners
+            let _ = self.socket.send_to(&packet, target);
+        }
+    }
+}
+
+/// Listens for lobby announcements and keeps a list of the live ones
+pub struct LobbyBrowser {
+    /// Socket bound to the discovery port
+    socket: UdpSocket,
+    /// Lobbies heard from, by address, with when they were last heard
+    lobbies: HashMap<SocketAddr, (Lobby, Instant)>,
+}
+
+impl LobbyBrowser {
+    /// Listens for announcements on `discovery_port`, or any free port for 0
+    pub fn listen(discovery_port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, discovery_port))?;
+        socket.set_nonblocking(true)?;
+        Ok(LobbyBrowser { socket, lobbies: HashMap::new() })
+    }
+
+    /// Port the browser is listening on
+    pub fn local_port(&self) -> io::Result<u16> {
+        Ok(self.socket.local_addr()?.port())
+    }
+
+    /// Reads new announcements and forgets lobbies that have gone quiet
+    pub fn poll(&mut self) {
+        let mut buffer = [0; MAX_PACKET_SIZE];
+        loop {
+            let (length, sender) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // A socket that keeps failing would never run dry, so try again next frame
+                Err(_) => break,
+            };
+            let Ok(DiscoveryMessage::Announce(lobby)) = ron::de::from_bytes(&buffer[..length]) else {
+                continue;
+            };
+            let address = SocketAddr::new(sender.ip(), lobby.port);
+            self.lobbies.insert(address, (lobby, Instant::now()));
+        }
+
+        self.lobbies.retain(|_, (_, heard)| heard.elapsed() < LOBBY_TIMEOUT);
+    }
+
+    /// Every live lobby, sorted by name and then address
+    pub fn lobbies(&self) -> Vec<FoundLobby> {
+        let mut found: Vec<FoundLobby> = self
+            .lobbies
+            .iter()
+            .map(|(address, (lobby, _))| FoundLobby { address: *address, lobby: lobby.clone() })
+            .collect();
+        found.sort_by(|a, b| a.lobby.name.cmp(&b.lobby.name).then(a.address.cmp(&b.address)));
+        found
+    }
+}
+
+/// Reads the lobby name from `--lobby-name <name>`
+pub fn lobby_name_from_args(args: impl IntoIterator<Item = String>) -> String {
+    let mut args = args.into_iter();
+    let mut name = DEFAULT_LOBBY_NAME.to_string();
+
+    while let Some(arg) = args.next() {
+        if arg == "--lobby-name" && let Some(value) = args.next() {
+            name = value;
+        }
+    }
+
+    name
+}
+
+/// Lobby of a player hosting an online match, announced until the match ends
+#[derive(Resource)]
+pub struct HostAdvertisement {
+    /// Sends the announcements
+    announcer: Announcer,
+    /// Name of the lobby
+    name: String,
+    /// Port the host listens on
+    port: u16,
+}
+
+impl HostAdvertisement {
+    /// Advertises a match hosted on `port` under `name`
+    pub fn new(announcer: Announcer, name: String, port: u16) -> Self {
+        HostAdvertisement { announcer, name, port }
+    }
+}
+
+/// Announces the hosted match, with two players present once the opponent has joined
+pub fn advertise_hosted_match(
+    mut advertisement: ResMut<HostAdvertisement>,
+    session: Res<NetSession>,
+    rules: Res<MatchRules>,
+) {
+    let lobby = Lobby {
+        name: advertisement.name.clone(),
+        port: advertisement.port,
+        kind: LobbyKind::Player,
+        target_score: rules.target_score,
+        players: if session.is_running() { 2 } else { 1 },
+    };
+    advertisement.announcer.announce(&lobby);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn announced_lobby_is_found_on_loopback() {
+        let mut browser = LobbyBrowser::listen(0).unwrap();
+        let port = browser.local_port().unwrap();
+        let mut announcer = Announcer::with_targets(vec![SocketAddr::from((Ipv4Addr::LOCALHOST, port))]).unwrap();
+        let lobby = Lobby {
+            name: "Lunch break".to_string(),
+            port: 7777,
+            kind: LobbyKind::Player,
+            target_score: 11,
+            players: 1,
+        };
+
+        announcer.announce(&lobby);
+        for _ in 0..100 {
+            browser.poll();
+            if !browser.lobbies().is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let found = browser.lobbies();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].lobby, lobby);
+        assert_eq!(found[0].address, SocketAddr::from((Ipv4Addr::LOCALHOST, 7777)));
+    }
+}
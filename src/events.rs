@@ -0,0 +1,103 @@
+/// Module for the gameplay events sent as the ball is served, hit and bounced
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::paddle::Side;
+
+/// Paddle hits between two RallyMilestone events
+pub const RALLY_MILESTONE_INTERVAL: u32 = 10;
+
+/// Sent when the ball is put in play at the end of the countdown
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct Serve {
+    /// Player the ball is heading towards
+    pub toward: Side,
+}
+
+/// Sent when a paddle returns the ball
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct PaddleHit {
+    /// Side of the paddle that hit the ball
+    pub side: Side,
+    /// Where the ball struck, from -1 at the bottom or left end of the paddle to 1 at the top or right end
+    pub contact_offset: f32,
+    /// Speed the ball leaves the paddle at, in pixels per second
+    pub speed: f32,
+    /// Paddle hits so far in this rally, including this one
+    pub bounces: u32,
+}
+
+/// One of the walls along the top and bottom of the field, or an obstacle in the arena
+///
+/// The top and bottom edges are goals in a four-player match, and only become
+/// walls once the player defending them is out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Wall {
+    /// The wall along the top of the field
+    Top,
+    /// The wall along the bottom of the field
+    Bottom,
+    /// One of the arena's obstacles
+    Obstacle,
+}
+
+/// Sent when the ball bounces off the top or bottom wall or an obstacle
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct WallBounce {
+    /// The wall the ball bounced off
+    pub wall: Wall,
+}
+
+/// Sent every RALLY_MILESTONE_INTERVAL paddle hits of a rally
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct RallyMilestone {
+    /// Paddle hits so far in this rally
+    pub bounces: u32,
+}
+
+/// Sent when the last ball in play leaves the field, ending the rally
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct RallyEnded {
+    /// Player the last ball got past
+    pub conceded: Side,
+}
+
+/// Plugin registering the gameplay events
+///
+/// The ball systems send them in PongSet::Physics, or on the transition to
+/// GameState::Playing for a Serve. Like ScoreEvent they are sent again when an
+/// online match rolls back and resimulates.
+pub struct GameplayEventsPlugin;
+
+impl Plugin for GameplayEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Serve>()
+            .add_event::<PaddleHit>()
+            .add_event::<WallBounce>()
+            .add_event::<RallyMilestone>()
+            .add_event::<RallyEnded>();
+    }
+}
+
+/// Writers for the events the ball sends while it moves
+#[derive(SystemParam)]
+pub struct BallEventWriters<'w> {
+    /// Paddle hits
+    pub paddle_hits: EventWriter<'w, PaddleHit>,
+    /// Wall bounces
+    pub wall_bounces: EventWriter<'w, WallBounce>,
+    /// Rally milestones
+    pub rally_milestones: EventWriter<'w, RallyMilestone>,
+    /// Ends of rallies
+    pub rally_ends: EventWriter<'w, RallyEnded>,
+}
+
+impl BallEventWriters<'_> {
+    /// Sends a paddle hit, along with a RallyMilestone if the rally has reached one
+    pub fn send_paddle_hit(&mut self, hit: PaddleHit) {
+        self.paddle_hits.send(hit);
+        if hit.bounces.is_multiple_of(RALLY_MILESTONE_INTERVAL) {
+            self.rally_milestones.send(RallyMilestone { bounces: hit.bounces });
+        }
+    }
+}
@@ -0,0 +1,203 @@
+/// Module for the Find Games screen listing matches on the local network
+use bevy::prelude::*;
+use crate::ai::AiPlayers;
+use crate::discovery::{FoundLobby, LobbyBrowser, LobbyKind, DISCOVERY_PORT};
+use crate::net::{UdpTransport, DEFAULT_INPUT_DELAY};
+use crate::paddle::Side;
+use crate::remote::RemoteSession;
+use crate::rollback::NetSession;
+use crate::server::ServerConnection;
+use crate::state::GameState;
+
+/// Lobbies found so far and the one selected
+#[derive(Resource, Default)]
+pub struct GameBrowser {
+    /// Listener for announcements, open while the screen is shown
+    browser: Option<LobbyBrowser>,
+    /// Why the screen can't list or join games, if something went wrong
+    error: Option<String>,
+    /// Index of the highlighted lobby
+    selected: usize,
+}
+
+/// Component for the text listing the lobbies
+#[derive(Component)]
+pub struct LobbyListText;
+
+/// Starts listening for lobbies and spawns the Find Games screen
+pub fn spawn_find_games_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut games: ResMut<GameBrowser>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    *games = match LobbyBrowser::listen(DISCOVERY_PORT) {
+        Ok(browser) => GameBrowser { browser: Some(browser), ..default() },
+        Err(err) => GameBrowser { error: Some(format!("Could not listen for games: {err}")), ..default() },
+    };
+
+    commands.spawn((
+        Text2d::new("Find Games"),
+        TextFont {
+            font: font.clone(),
+            font_size: 40.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, 195.0, 1.0)),
+        StateScoped(GameState::FindGames),
+    ));
+
+    commands.spawn((
+        Text2d::new(""),
+        TextFont {
+            font: font.clone(),
+            font_size: 20.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, 20.0, 1.0)),
+        LobbyListText,
+        StateScoped(GameState::FindGames),
+    ));
+
+    commands.spawn((
+        Text2d::new("Up/Down - Select    Enter - Join    M - Menu"),
+        TextFont {
+            font: font.clone(),
+            font_size: 16.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -200.0, 1.0)),
+        StateScoped(GameState::FindGames),
+    ));
+}
+
+/// Stops listening for lobbies once the screen is left
+pub fn close_game_browser(mut games: ResMut<GameBrowser>) {
+    *games = GameBrowser::default();
+}
+
+/// Handles the Find Games screen
+///
+/// Keeps the list of lobbies up to date. Up and Down move the selection, Enter
+/// joins the selected match and M goes back to the main menu. A dedicated server
+/// with both paddles taken is joined as a spectator.
+pub fn navigate_find_games(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut games: ResMut<GameBrowser>,
+    mut ai_players: ResMut<AiPlayers>,
+    mut text_query: Query<&mut Text2d, With<LobbyListText>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let lobbies = match games.browser.as_mut() {
+        Some(browser) => {
+            browser.poll();
+            browser.lobbies()
+        }
+        None => Vec::new(),
+    };
+
+    if !lobbies.is_empty() {
+        if keyboard.just_pressed(KeyCode::ArrowUp) {
+            games.selected = (games.selected + lobbies.len() - 1) % lobbies.len();
+        }
+        if keyboard.just_pressed(KeyCode::ArrowDown) {
+            games.selected = (games.selected + 1) % lobbies.len();
+        }
+    }
+    games.selected = games.selected.min(lobbies.len().saturating_sub(1));
+
+    if keyboard.just_pressed(KeyCode::Enter)
+        && let Some(found) = lobbies.get(games.selected)
+    {
+        match join_lobby(&mut commands, found) {
+            Ok(()) => {
+                // Both paddles belong to people in online matches
+                *ai_players = AiPlayers::default();
+                next_state.set(GameState::MainMenu);
+            }
+            Err(error) => games.error = Some(error),
+        }
+    }
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        next_state.set(GameState::MainMenu);
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.0 = lobby_list_text(&lobbies, games.selected, games.error.as_deref());
+    }
+}
+
+/// Starts the session that plays or watches a found match
+fn join_lobby(commands: &mut Commands, found: &FoundLobby) -> Result<(), String> {
+    let full = found.lobby.players >= 2;
+    match found.lobby.kind {
+        LobbyKind::Player if full => Err(format!("{} already has two players", found.lobby.name)),
+        LobbyKind::Player => {
+            let transport = UdpTransport::join(&found.address.to_string())
+                .map_err(|err| format!("Could not join {}: {err}", found.address))?;
+            commands.insert_resource(NetSession::new(transport, Side::Right, DEFAULT_INPUT_DELAY));
+            Ok(())
+        }
+        LobbyKind::Server => {
+            let connection = ServerConnection::connect(found.address, full)
+                .map_err(|err| format!("Could not connect to {}: {err}", found.address))?;
+            commands.insert_resource(RemoteSession::new(connection, 0.0));
+            Ok(())
+        }
+    }
+}
+
+/// Text listing every lobby, with the selected one marked
+fn lobby_list_text(lobbies: &[FoundLobby], selected: usize, error: Option<&str>) -> String {
+    let mut lines: Vec<String> = lobbies
+        .iter()
+        .enumerate()
+        .map(|(index, found)| {
+            let marker = if index == selected { ">" } else { " " };
+            let kind = match found.lobby.kind {
+                LobbyKind::Player => "",
+                LobbyKind::Server => " [server]",
+            };
+            format!(
+                "{marker} {}{kind} - first to {} - {}/2 players - {}",
+                found.lobby.name, found.lobby.target_score, found.lobby.players, found.address
+            )
+        })
+        .collect();
+
+    if lines.is_empty() {
+        lines.push("Looking for games on the local network...".to_string());
+    }
+    if let Some(error) = error {
+        lines.push(String::new());
+        lines.push(error.to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::Lobby;
+
+    #[test]
+    fn selected_lobby_is_marked() {
+        let lobby = |name: &str, port| FoundLobby {
+            address: ([192, 168, 1, 20], port).into(),
+            lobby: Lobby { name: name.to_string(), port, kind: LobbyKind::Server, target_score: 5, players: 1 },
+        };
+
+        let text = lobby_list_text(&[lobby("A", 7878), lobby("B", 7879)], 1, None);
+
+        assert_eq!(
+            text,
+            "  A [server] - first to 5 - 1/2 players - 192.168.1.20:7878\n\
+             > B [server] - first to 5 - 1/2 players - 192.168.1.20:7879"
+        );
+        assert!(lobby_list_text(&[], 0, None).starts_with("Looking"));
+    }
+}
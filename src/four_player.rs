@@ -0,0 +1,85 @@
+/// Module for four-player matches with a paddle guarding every edge of the field
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::paddle::Side;
+
+/// Lives each player starts a four-player match with unless `--lives` is given
+pub const DEFAULT_LIVES: u32 = 5;
+
+/// Rules for a match where the top and bottom edges are goals too
+///
+/// Every player defends their own edge and loses a life each time a ball gets
+/// past them. A player out of lives leaves the match and their edge becomes a
+/// wall, and the last player standing wins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FourPlayerRules {
+    /// Lives each player starts with
+    pub lives: u32,
+}
+
+impl Default for FourPlayerRules {
+    fn default() -> Self {
+        FourPlayerRules { lives: DEFAULT_LIVES }
+    }
+}
+
+impl FourPlayerRules {
+    /// Reads the four-player rules from command line arguments
+    ///
+    /// Gives rules only if `--four-players` is present, with the number of lives
+    /// from `--lives <count>`. Counts that fail to parse are reported and ignored.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut enabled = false;
+        let mut rules = FourPlayerRules::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--four-players" => enabled = true,
+                "--lives" => {
+                    let value = args.next().unwrap_or_default();
+                    match value.parse() {
+                        Ok(lives) if lives > 0 => rules.lives = lives,
+                        _ => warn!("Invalid value '{}' for {}", value, arg),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        enabled.then_some(rules)
+    }
+
+    /// The player serving next, after `conceded` lost a life
+    ///
+    /// The player who lost the life serves, or if that was their last one the next
+    /// player in Side::ALL order who is still in the match.
+    pub fn next_server(conceded: Side, in_play: impl Fn(Side) -> bool) -> Side {
+        let start = conceded.index();
+        (0..Side::ALL.len())
+            .map(|step| Side::ALL[(start + step) % Side::ALL.len()])
+            .find(|&side| in_play(side))
+            .unwrap_or(conceded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_are_parsed_from_args() {
+        assert_eq!(FourPlayerRules::from_args(["--lives", "3"].map(String::from)), None);
+        let args = ["--four-players", "--lives", "3"].map(String::from);
+        assert_eq!(FourPlayerRules::from_args(args), Some(FourPlayerRules { lives: 3 }));
+        let args = ["--four-players", "--lives", "0"].map(String::from);
+        assert_eq!(FourPlayerRules::from_args(args), Some(FourPlayerRules::default()));
+    }
+
+    #[test]
+    fn the_next_player_still_in_serves_for_an_eliminated_one() {
+        assert_eq!(FourPlayerRules::next_server(Side::Top, |_| true), Side::Top);
+        assert_eq!(FourPlayerRules::next_server(Side::Right, |side| side != Side::Right), Side::Top);
+        assert_eq!(FourPlayerRules::next_server(Side::Bottom, |side| side == Side::Right), Side::Right);
+    }
+}
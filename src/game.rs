@@ -0,0 +1,246 @@
+/// Module for the PongPlugin that assembles the whole game
+use bevy::{app::RunFixedMainLoopSystem, input::InputSystem, prelude::*};
+use crate::ai::{self, AiPlayers};
+use crate::arena::ArenaPlugin;
+use crate::background::BackgroundPlugin;
+use crate::ball::BallPlugin;
+use crate::broadcast::BroadcastPlugin;
+use crate::controls::{self, ControlsMenu};
+use crate::discovery::{self, HostAdvertisement};
+use crate::events::GameplayEventsPlugin;
+use crate::find_games::{self, GameBrowser};
+use crate::gamepad::{self, PaddleGamepads};
+use crate::input::{self, ActionState, BindingsPath, InputBindings};
+use crate::level_select::{self, LevelBrowser};
+use crate::menu::{self, MenuDifficulty};
+use crate::multiball::MultiBallPlugin;
+use crate::paddle::{self, PaddlePlugin};
+use crate::powerup::PowerUpPlugin;
+use crate::remote::{RemotePlugin, RemoteSession};
+use crate::replay::{self, ReplayPlayback, ReplayPlugin};
+use crate::rollback::{self, NetPlugin, NetSession};
+use crate::scoreboard::ScoreboardPlugin;
+use crate::serve::ServePlugin;
+use crate::settings::PongSettings;
+use crate::sound::SoundPlugin;
+use crate::simulation::{self, GameRng, SimulationSettings};
+use crate::state::{self, GameState, InGame, ServeCountdown};
+use crate::{game_over, window};
+
+/// Stages of a gameplay tick, run in this order in FixedUpdate
+///
+/// Hosts can order their own systems against these, for example to steer a paddle
+/// before PongSet::Movement or react to the ball after PongSet::Physics.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PongSet {
+    /// Paddle intent is decided, by the AI among others
+    Input,
+    /// Paddles move according to their PaddleInput
+    Movement,
+    /// The ball moves and bounces off paddles and walls
+    Physics,
+    /// Points are counted and the next server is chosen
+    Scoring,
+    /// The game moves between states in response to scoring
+    Flow,
+}
+
+/// Plugin running a complete game of Pixel Pong
+///
+/// Adds the game states, the ArenaPlugin, BallPlugin, MultiBallPlugin, PaddlePlugin, PowerUpPlugin,
+/// ServePlugin and ScoreboardPlugin, AI control and the fixed timestep, all sized by `settings`. Unless `headless` is set
+/// it also adds the BackgroundPlugin, camera, menus, player input and smoothing
+/// between ticks, which need the plugins from DefaultPlugins. Inserting a NetSession
+/// turns the game into an online match, with the whole tick skipped while it waits
+/// for the other player, and inserting a RemoteSession shows a match simulated by a
+/// dedicated server instead of simulating one. Inserting a MatchEventLog records
+/// serves, bounces and points as they happen. SimulationSettings,
+/// AiPlayers, MatchRules, InputBindings and BindingsPath inserted before the plugin
+/// are kept, otherwise defaults are used.
+#[derive(Default)]
+pub struct PongPlugin {
+    /// Dimensions, speeds and colors of the game
+    pub settings: PongSettings,
+    /// Runs only the gameplay, for tests and simulations without a window
+    pub headless: bool,
+}
+
+impl Plugin for PongPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .init_resource::<SimulationSettings>()
+            .init_resource::<AiPlayers>();
+        let tick_rate = app.world().resource::<SimulationSettings>().tick_rate;
+
+        app.init_state::<GameState>()
+            .add_computed_state::<InGame>()
+            .enable_state_scoped_entities::<GameState>()
+            .enable_state_scoped_entities::<InGame>()
+            .insert_resource(Time::<Fixed>::from_hz(tick_rate))
+            .init_resource::<GameRng>()
+            .init_resource::<ServeCountdown>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    PongSet::Input,
+                    PongSet::Movement,
+                    PongSet::Physics,
+                    PongSet::Scoring,
+                    PongSet::Flow,
+                )
+                    .chain(),
+            )
+            .configure_sets(
+                FixedUpdate,
+                (PongSet::Input, PongSet::Movement)
+                    .run_if(in_state(GameState::Playing).or(in_state(GameState::Countdown))),
+            )
+            .configure_sets(
+                FixedUpdate,
+                (PongSet::Physics, PongSet::Scoring).run_if(in_state(GameState::Playing)),
+            )
+            .configure_sets(
+                FixedUpdate,
+                (
+                    PongSet::Input,
+                    PongSet::Movement,
+                    PongSet::Physics,
+                    PongSet::Scoring,
+                    PongSet::Flow,
+                )
+                    .run_if(rollback::not_stalled.and(not(resource_exists::<RemoteSession>))),
+            )
+            .add_plugins((
+                GameplayEventsPlugin,
+                ArenaPlugin,
+                BallPlugin,
+                MultiBallPlugin,
+                PaddlePlugin,
+                PowerUpPlugin,
+                ServePlugin,
+                ScoreboardPlugin { headless: self.headless },
+                NetPlugin,
+                RemotePlugin,
+                BroadcastPlugin,
+                ReplayPlugin,
+            ))
+            .add_systems(
+                OnEnter(InGame),
+                (
+                    simulation::reseed_rng,
+                    ai::assign_ai_controllers.after(paddle::spawn_paddles),
+                ),
+            )
+            .add_systems(OnEnter(GameState::Countdown), state::start_serve_countdown)
+            .add_systems(
+                FixedFirst,
+                simulation::apply_state_transitions.run_if(rollback::not_stalled),
+            )
+            .add_systems(FixedUpdate, ai::control_ai_paddles.in_set(PongSet::Input))
+            .add_systems(
+                FixedUpdate,
+                state::advance_after_score
+                    .run_if(in_state(GameState::Playing))
+                    .in_set(PongSet::Flow),
+            );
+
+        if !self.headless {
+            add_presentation(app);
+        }
+    }
+}
+
+/// Run condition that is true unless the game is being played over the network or replayed
+pub fn is_local_game(
+    net: Option<Res<NetSession>>,
+    remote: Option<Res<RemoteSession>>,
+    playback: Option<Res<ReplayPlayback>>,
+) -> bool {
+    net.is_none() && remote.is_none() && playback.is_none()
+}
+
+/// Adds everything needed to show the game and play it with real input devices
+fn add_presentation(app: &mut App) {
+    app.init_resource::<BindingsPath>();
+    if !app.world().contains_resource::<InputBindings>() {
+        let bindings = InputBindings::load_or_default(&app.world().resource::<BindingsPath>().0);
+        app.insert_resource(bindings);
+    }
+
+    app.add_plugins((BackgroundPlugin, SoundPlugin))
+        .init_resource::<MenuDifficulty>()
+        .init_resource::<PaddleGamepads>()
+        .init_resource::<ActionState>()
+        .init_resource::<ControlsMenu>()
+        .init_resource::<GameBrowser>()
+        .init_resource::<LevelBrowser>()
+        .add_systems(Startup, window::setup_camera)
+        .add_systems(Startup, replay::spawn_playback_text.run_if(resource_exists::<ReplayPlayback>))
+        .add_systems(
+            PreUpdate,
+            (gamepad::assign_gamepads, input::update_action_state)
+                .chain()
+                .after(InputSystem),
+        )
+        .add_systems(OnEnter(GameState::MainMenu), menu::spawn_main_menu)
+        .add_systems(OnEnter(GameState::Controls), controls::spawn_controls_screen)
+        .add_systems(OnEnter(GameState::FindGames), find_games::spawn_find_games_screen)
+        .add_systems(OnExit(GameState::FindGames), find_games::close_game_browser)
+        .add_systems(OnEnter(GameState::LevelSelect), level_select::spawn_level_select_screen)
+        .add_systems(OnExit(GameState::LevelSelect), level_select::close_level_browser)
+        .add_systems(OnEnter(GameState::Countdown), state::spawn_countdown_text)
+        .add_systems(OnEnter(GameState::Paused), state::spawn_pause_screen)
+        .add_systems(OnEnter(GameState::GameOver), game_over::spawn_game_over_screen)
+        .add_systems(
+            RunFixedMainLoop,
+            (
+                simulation::restore_simulated_translations,
+                paddle::move_player_paddles
+                    .run_if(in_state(GameState::Playing).or(in_state(GameState::Countdown)))
+                    .run_if(is_local_game),
+                replay::control_playback
+                    .after(simulation::restore_simulated_translations)
+                    .run_if(resource_exists::<ReplayPlayback>),
+            )
+                .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+        )
+        .add_systems(
+            RunFixedMainLoop,
+            simulation::interpolate_translations.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
+        )
+        .add_systems(
+            FixedFirst,
+            simulation::store_previous_translations.after(simulation::apply_state_transitions),
+        )
+        .add_systems(
+            Update,
+            (
+                // Checked before capturing, or the key just bound to Quit would also close the game
+                window::close_on_quit
+                    .run_if(controls::not_capturing_binding)
+                    .before(controls::capture_binding),
+                menu::handle_main_menu_input
+                    .run_if(in_state(GameState::MainMenu))
+                    .run_if(is_local_game),
+                (
+                    controls::capture_binding.before(controls::navigate_controls),
+                    controls::navigate_controls,
+                    controls::update_controls_text.after(controls::navigate_controls),
+                )
+                    .run_if(in_state(GameState::Controls)),
+                find_games::navigate_find_games.run_if(in_state(GameState::FindGames)),
+                level_select::navigate_level_select.run_if(in_state(GameState::LevelSelect)),
+                discovery::advertise_hosted_match
+                    .run_if(resource_exists::<HostAdvertisement>.and(resource_exists::<NetSession>)),
+                state::update_countdown_text.run_if(in_state(GameState::Countdown)),
+                replay::update_playback_text.run_if(resource_exists::<ReplayPlayback>),
+                // Network matches can neither be paused nor restarted by one player alone
+                state::toggle_pause
+                    .run_if(in_state(GameState::Playing).or(in_state(GameState::Paused)))
+                    .run_if(is_local_game),
+                game_over::handle_game_over_input
+                    .run_if(in_state(GameState::GameOver))
+                    .run_if(is_local_game),
+            ),
+        );
+}
@@ -0,0 +1,109 @@
+/// Module for the screen shown once a player has won the match
+use bevy::prelude::*;
+use crate::ball::{self, Ball, RallyBall, Velocity};
+use crate::input::{Binding, InputAction, InputBindings};
+use crate::paddle::{Paddle, Side};
+use crate::scoreboard::{MatchRules, MatchStatus, ScoreBoard};
+use crate::serve::ServingSide;
+use crate::settings::PongSettings;
+use crate::state::GameState;
+
+/// Spawns the game over screen when the match has been won
+///
+/// Shows a dark panel over the middle of the field announcing the winner,
+/// with a line explaining how to start a rematch, go back to the menu or quit.
+/// The quit hint shows the left player's first Quit input.
+pub fn spawn_game_over_screen(
+    mut commands: Commands,
+    status: Res<MatchStatus>,
+    asset_server: Res<AssetServer>,
+    bindings: Res<InputBindings>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let title = match status.winner {
+        Some(Side::Left) => "Left player wins!",
+        Some(Side::Right) => "Right player wins!",
+        Some(Side::Top) => "Top player wins!",
+        Some(Side::Bottom) => "Bottom player wins!",
+        None => "Game over",
+    };
+    let quit = bindings
+        .left
+        .quit
+        .first()
+        .map(Binding::label)
+        .unwrap_or_else(|| InputAction::Quit.name().to_string());
+
+    commands.spawn((
+        Sprite {
+            color: Color::srgba(0.0, 0.0, 0.0, 0.8),
+            custom_size: Some(Vec2::new(380.0, 150.0)),
+            ..default()
+        },
+        Transform::from_translation(Vec3::new(0.0, 0.0, 2.0)),
+        StateScoped(GameState::GameOver),
+    ));
+
+    commands.spawn((
+        Text2d::new(title),
+        TextFont {
+            font: font.clone(),
+            font_size: 40.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, 25.0, 3.0)),
+        StateScoped(GameState::GameOver),
+    ));
+
+    commands.spawn((
+        Text2d::new(format!("R - Rematch    M - Menu    {} - Quit", quit)),
+        TextFont {
+            font: font.clone(),
+            font_size: 20.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -35.0, 3.0)),
+        StateScoped(GameState::GameOver),
+    ));
+}
+
+/// Handles key presses on the game over screen
+///
+/// R starts a rematch: the ScoreBoard and match clock are reset, the balls are
+/// gathered up as after a rally, the paddles go back to their places, and the
+/// left player serves first. The PowerUpPlugin clears the power-ups.
+/// M returns to the main menu. Quitting is handled by the Quit action as usual.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_game_over_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut ball_query: Query<RallyBall, With<Ball>>,
+    mut paddle_query: Query<(&mut Transform, &mut Velocity, &Paddle), Without<Ball>>,
+    settings: Res<PongSettings>,
+    rules: Res<MatchRules>,
+    mut scoreboard: ResMut<ScoreBoard>,
+    mut status: ResMut<MatchStatus>,
+    mut serving: ResMut<ServingSide>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        next_state.set(GameState::MainMenu);
+        return;
+    }
+    if !keyboard.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+
+    ball::reset_balls(&mut ball_query);
+    for (mut transform, mut velocity, paddle) in paddle_query.iter_mut() {
+        transform.translation = settings.paddle_position(*paddle).extend(transform.translation.z);
+        *velocity = Velocity { x: 0.0, y: 0.0 };
+    }
+
+    *scoreboard = ScoreBoard::new(&rules);
+    *status = MatchStatus::default();
+    *serving = ServingSide::default();
+    next_state.set(GameState::Countdown);
+    info!("Rematch started");
+}
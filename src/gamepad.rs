@@ -0,0 +1,126 @@
+/// Module for assigning connected gamepads to the paddles
+use bevy::input::gamepad::GamepadConnectionEvent;
+use bevy::prelude::*;
+use crate::paddle::{Paddle, PaddleSlot, Side};
+use crate::scoreboard::MatchRules;
+
+/// Stick deflection below which the left stick is treated as centered
+pub const STICK_DEADZONE: f32 = 0.15;
+
+/// Which connected gamepad, if any, controls each paddle
+#[derive(Resource, Default)]
+pub struct PaddleGamepads {
+    /// Gamepad entity driving the left paddle
+    pub left: Option<Entity>,
+    /// Gamepad entity driving the right paddle
+    pub right: Option<Entity>,
+    /// Gamepad entity driving the top paddle in a four-player match
+    pub top: Option<Entity>,
+    /// Gamepad entity driving the bottom paddle in a four-player match
+    pub bottom: Option<Entity>,
+    /// Gamepad entity driving the left forward paddle in a doubles match
+    pub left_forward: Option<Entity>,
+    /// Gamepad entity driving the right forward paddle in a doubles match
+    pub right_forward: Option<Entity>,
+}
+
+impl PaddleGamepads {
+    /// Gamepad entity driving `paddle`
+    pub fn get(&self, paddle: Paddle) -> Option<Entity> {
+        *self.slot(paddle)
+    }
+
+    /// The slot holding the gamepad for `paddle`
+    ///
+    /// Four-player matches have no forward paddles, so the top and bottom
+    /// paddles are always the back ones.
+    fn slot(&self, paddle: Paddle) -> &Option<Entity> {
+        match (paddle.side, paddle.slot) {
+            (Side::Left, PaddleSlot::Back) => &self.left,
+            (Side::Right, PaddleSlot::Back) => &self.right,
+            (Side::Left, PaddleSlot::Forward) => &self.left_forward,
+            (Side::Right, PaddleSlot::Forward) => &self.right_forward,
+            (Side::Top, _) => &self.top,
+            (Side::Bottom, _) => &self.bottom,
+        }
+    }
+
+    /// The mutable slot holding the gamepad for `paddle`
+    fn slot_mut(&mut self, paddle: Paddle) -> &mut Option<Entity> {
+        match (paddle.side, paddle.slot) {
+            (Side::Left, PaddleSlot::Back) => &mut self.left,
+            (Side::Right, PaddleSlot::Back) => &mut self.right,
+            (Side::Left, PaddleSlot::Forward) => &mut self.left_forward,
+            (Side::Right, PaddleSlot::Forward) => &mut self.right_forward,
+            (Side::Top, _) => &mut self.top,
+            (Side::Bottom, _) => &mut self.bottom,
+        }
+    }
+}
+
+/// Keeps the PaddleGamepads assignment in step with the connected gamepads
+///
+/// Gamepads that have been unplugged give up their paddle, as do paddles the
+/// match rules leave off the field. Any paddle in the match without a gamepad
+/// is given the first connected one not already in use, the left paddle first,
+/// then the right, then the top and bottom or the forward paddles. This hands
+/// the first gamepads to the players in that order and lets a replacement take
+/// over when one is plugged back in mid-match.
+pub fn assign_gamepads(
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    gamepad_query: Query<Entity, With<Gamepad>>,
+    rules: Res<MatchRules>,
+    mut paddle_gamepads: ResMut<PaddleGamepads>,
+) {
+    for event in connection_events.read() {
+        if event.connected() {
+            info!("Gamepad {} connected", event.gamepad);
+        } else {
+            info!("Gamepad {} disconnected", event.gamepad);
+        }
+    }
+
+    let paddles = rules.paddles();
+    for paddle in Paddle::PLAYERS {
+        let slot = paddle_gamepads.slot_mut(paddle);
+        if slot.is_some_and(|gamepad| !gamepad_query.contains(gamepad) || !paddles.contains(&paddle)) {
+            *slot = None;
+        }
+    }
+
+    for gamepad in gamepad_query.iter() {
+        if paddles.iter().any(|&paddle| paddle_gamepads.get(paddle) == Some(gamepad)) {
+            continue;
+        }
+        if let Some(&paddle) = paddles.iter().find(|&&paddle| paddle_gamepads.get(paddle).is_none()) {
+            *paddle_gamepads.slot_mut(paddle) = Some(gamepad);
+        }
+    }
+}
+
+/// Zeroes stick values inside STICK_DEADZONE and rescales the rest to the full range
+pub fn apply_deadzone(value: f32) -> f32 {
+    if value.abs() < STICK_DEADZONE {
+        return 0.0;
+    }
+    value.signum() * ((value.abs() - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_centers_small_deflections() {
+        assert_eq!(apply_deadzone(0.1), 0.0);
+        assert_eq!(apply_deadzone(-0.14), 0.0);
+    }
+
+    #[test]
+    fn deadzone_rescales_to_full_range() {
+        assert_eq!(apply_deadzone(1.0), 1.0);
+        assert_eq!(apply_deadzone(-1.0), -1.0);
+        assert!(apply_deadzone(STICK_DEADZONE + 0.01) < 0.05);
+        assert!((apply_deadzone(0.575) - 0.5).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,427 @@
+/// Module for running the gameplay without a window, for tests and batch simulations
+use std::time::Duration;
+use bevy::{ecs::event::EventCursor, prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+use crate::ai::{AiPlayers, Difficulty};
+use crate::game::PongPlugin;
+use crate::scoreboard::{MatchRules, MatchStatus, ScoreBoard, ScoreEvent};
+use crate::simulation::SimulationSettings;
+use crate::state::GameState;
+
+/// Longest a headless match may run, in simulated seconds, before it is abandoned
+const MAX_MATCH_SECONDS: f64 = 1800.0;
+
+/// A gameplay App stepped one fixed tick at a time
+///
+/// Built on MinimalPlugins with a headless PongPlugin, so it needs no
+/// window, renderer or input devices. Each call to `tick` advances the clock by
+/// exactly one fixed timestep, which makes runs with the same seed identical.
+pub struct Simulation {
+    /// The underlying app, for anything the helpers below don't cover
+    pub app: App,
+    /// Position in the ScoreEvent queue up to which events have been collected
+    score_cursor: EventCursor<ScoreEvent>,
+    /// Every ScoreEvent sent since the simulation was created
+    pub scores: Vec<ScoreEvent>,
+}
+
+impl Simulation {
+    /// Builds a simulation with the given settings, sitting on the main menu
+    pub fn new(settings: SimulationSettings, ai_players: AiPlayers, rules: MatchRules) -> Self {
+        Simulation::from_app(Simulation::build_app(settings, ai_players, rules))
+    }
+
+    /// Builds the app a simulation runs, for adding to it before it starts
+    pub fn build_app(settings: SimulationSettings, ai_players: AiPlayers, rules: MatchRules) -> App {
+        let mut app = App::new();
+        let timestep = Duration::from_secs_f64(1.0 / settings.tick_rate);
+
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+            .insert_resource(settings)
+            .insert_resource(ai_players)
+            .insert_resource(rules)
+            .add_plugins(PongPlugin { headless: true, ..default() });
+        app
+    }
+
+    /// Starts a simulation from an app made with `build_app`
+    pub fn from_app(mut app: App) -> Self {
+        app.finish();
+        app.cleanup();
+
+        // The first update only starts the clocks and enters the main menu
+        app.update();
+
+        Simulation {
+            app,
+            score_cursor: EventCursor::default(),
+            scores: Vec::new(),
+        }
+    }
+
+    /// Leaves the current state and starts a fresh match with the serve countdown
+    pub fn start_match(&mut self) {
+        self.set_state(GameState::MainMenu);
+        self.tick();
+        self.set_state(GameState::Countdown);
+        self.tick();
+    }
+
+    /// Advances the simulation by a single fixed tick
+    pub fn tick(&mut self) {
+        self.app.update();
+
+        let events = self.app.world().resource::<Events<ScoreEvent>>();
+        self.scores.extend(self.score_cursor.read(events).copied());
+    }
+
+    /// Advances the simulation by `ticks` fixed ticks
+    pub fn run(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    /// Runs the serve countdown until the ball is in play
+    pub fn skip_countdown(&mut self) {
+        while self.state() == GameState::Countdown {
+            self.tick();
+        }
+    }
+
+    /// Queues a change of game state, applied on the next tick
+    pub fn set_state(&mut self, state: GameState) {
+        self.app.world_mut().resource_mut::<NextState<GameState>>().set(state);
+    }
+
+    /// Current game state
+    pub fn state(&self) -> GameState {
+        *self.app.world().resource::<State<GameState>>().get()
+    }
+
+    /// Current score
+    pub fn scoreboard(&self) -> &ScoreBoard {
+        self.app.world().resource::<ScoreBoard>()
+    }
+
+    /// Elapsed time and winner of the current match
+    pub fn status(&self) -> &MatchStatus {
+        self.app.world().resource::<MatchStatus>()
+    }
+
+    /// Simulated seconds per tick
+    pub fn timestep(&self) -> f64 {
+        1.0 / self.app.world().resource::<SimulationSettings>().tick_rate
+    }
+}
+
+/// Settings for a `--headless` run
+pub struct HeadlessSettings {
+    /// Number of matches to simulate
+    pub matches: u32,
+}
+
+impl HeadlessSettings {
+    /// Reads the headless settings from command line arguments
+    ///
+    /// Returns None unless `--headless` is given. `--matches <count>` sets how
+    /// many matches are played, one by default.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut args = args.into_iter();
+        let mut headless = false;
+        let mut settings = HeadlessSettings { matches: 1 };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => headless = true,
+                "--matches" => {
+                    if let Some(count) = args.next().and_then(|value| value.parse().ok()) {
+                        settings.matches = count;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        headless.then_some(settings)
+    }
+}
+
+/// Simulates AI-vs-AI matches as fast as possible and prints the results
+///
+/// Every paddle is controlled by the computer, at Easy difficulty unless the
+/// `--ai-<paddle>` arguments read by AiPlayers say otherwise, and the usual match rules and
+/// simulation arguments apply. Match `n` is played with the seed plus `n`, so any
+/// single match can be watched again with `--seed`.
+pub fn run_headless_matches(headless: HeadlessSettings, args: Vec<String>) {
+    let settings = SimulationSettings::from_args(args.clone());
+    let requested = AiPlayers::from_args(args.clone());
+    let ai_players = AiPlayers {
+        left: requested.left.or(Some(Difficulty::Easy)),
+        right: requested.right.or(Some(Difficulty::Easy)),
+        top: requested.top.or(Some(Difficulty::Easy)),
+        bottom: requested.bottom.or(Some(Difficulty::Easy)),
+        left_forward: requested.left_forward.or(Some(Difficulty::Easy)),
+        right_forward: requested.right_forward.or(Some(Difficulty::Easy)),
+    };
+    let base_seed = settings.seed;
+    let rules = MatchRules::from_args(args);
+    let sides = rules.sides();
+    let four_player = rules.four_player.is_some();
+    let mut simulation = Simulation::new(settings, ai_players, rules);
+    let max_ticks = (MAX_MATCH_SECONDS / simulation.timestep()) as u64;
+
+    let (mut wins, mut unfinished) = ([0; 4], 0);
+    for index in 0..headless.matches {
+        let seed = base_seed.wrapping_add(index as u64);
+        simulation.app.world_mut().resource_mut::<SimulationSettings>().seed = seed;
+        simulation.start_match();
+
+        let mut ticks = 0;
+        while simulation.state() != GameState::GameOver && ticks < max_ticks {
+            simulation.tick();
+            ticks += 1;
+        }
+
+        let scoreboard = simulation.scoreboard();
+        let status = simulation.status();
+        let result = match status.winner {
+            Some(winner) => format!("{:?} wins", winner),
+            None => "unfinished".to_string(),
+        };
+        let score = if four_player {
+            let lives: Vec<String> = scoreboard.lives.iter().map(u32::to_string).collect();
+            format!("with lives {}", lives.join("-"))
+        } else {
+            format!("{}-{}", scoreboard.left, scoreboard.right)
+        };
+        println!("Match {} (seed {}): {} {} after {:.1}s", index + 1, seed, result, score, status.elapsed);
+
+        match status.winner {
+            Some(winner) => wins[winner.index()] += 1,
+            None => unfinished += 1,
+        }
+    }
+
+    let tally: Vec<String> =
+        sides.iter().map(|side| format!("{} won {}", format!("{side:?}").to_lowercase(), wins[side.index()])).collect();
+    println!("{} matches: {}, {} unfinished", headless.matches, tally.join(", "), unfinished);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ball::{Ball, Velocity};
+    use crate::four_player::FourPlayerRules;
+    use crate::movement::PaddleMovement;
+    use crate::paddle::{find_paddle, Paddle, PaddleInput, PaddleSlot, Side, PADDLE_SPEED};
+    use crate::settings::PongSettings;
+
+    fn two_players(seed: u64) -> Simulation {
+        let settings = SimulationSettings { seed, ..default() };
+        Simulation::new(settings, AiPlayers::default(), MatchRules::default())
+    }
+
+    fn ai_match(seed: u64, rules: MatchRules) -> Simulation {
+        let settings = SimulationSettings { seed, ..default() };
+        let ai_players = AiPlayers {
+            left: Some(Difficulty::Easy),
+            right: Some(Difficulty::Easy),
+            top: Some(Difficulty::Easy),
+            bottom: Some(Difficulty::Easy),
+            left_forward: Some(Difficulty::Easy),
+            right_forward: Some(Difficulty::Easy),
+        };
+        Simulation::new(settings, ai_players, rules)
+    }
+
+    fn ball_position(simulation: &mut Simulation) -> Vec3 {
+        let world = simulation.app.world_mut();
+        world.query_filtered::<&Transform, With<Ball>>().single(world).translation
+    }
+
+    fn left_paddle_input(simulation: &mut Simulation) -> Mut<'_, PaddleInput> {
+        let world = simulation.app.world_mut();
+        let paddle = find_paddle(world, Paddle::back(Side::Left)).unwrap();
+        world.get_mut::<PaddleInput>(paddle).unwrap()
+    }
+
+    fn left_paddle_position(simulation: &mut Simulation) -> Vec3 {
+        let world = simulation.app.world_mut();
+        let paddle = find_paddle(world, Paddle::back(Side::Left)).unwrap();
+        world.get::<Transform>(paddle).unwrap().translation
+    }
+
+    fn set_left_paddle_input(simulation: &mut Simulation, axis: f32) {
+        left_paddle_input(simulation).axis = axis;
+    }
+
+    #[test]
+    fn ball_waits_on_the_servers_paddle_then_moves() {
+        let mut simulation = two_players(1);
+        simulation.start_match();
+        simulation.run(60);
+        assert_eq!(simulation.state(), GameState::Countdown);
+        let parked = ball_position(&mut simulation);
+        let offset = parked.x - left_paddle_position(&mut simulation).x;
+        assert!(offset > 0.0 && offset < 20.0 && parked.y == 0.0, "ball at {parked}");
+
+        simulation.skip_countdown();
+        simulation.run(60);
+        let position = ball_position(&mut simulation);
+        assert!(position.x > parked.x + 50.0, "ball at {position}");
+    }
+
+    #[test]
+    fn server_launches_the_ball_aimed_by_their_paddle() {
+        let mut simulation = two_players(1);
+        simulation.start_match();
+        simulation.run(60);
+
+        *left_paddle_input(&mut simulation) = PaddleInput { axis: -1.0, serve: true, ..default() };
+        simulation.run(2);
+
+        assert_eq!(simulation.state(), GameState::Playing);
+        let world = simulation.app.world_mut();
+        let velocity = *world.query_filtered::<&Velocity, With<Ball>>().single(world);
+        assert!(velocity.x > 0.0 && velocity.y < 0.0, "served at {velocity:?}");
+    }
+
+    #[test]
+    fn scripted_input_moves_the_paddle() {
+        let mut simulation = two_players(1);
+        simulation.start_match();
+        set_left_paddle_input(&mut simulation, 1.0);
+        simulation.run(12);
+
+        // Twelve ticks at 120Hz is a tenth of a second at full speed
+        let expected = PADDLE_SPEED * 0.1;
+        assert!((left_paddle_position(&mut simulation).y - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn paddles_with_inertia_ramp_up_and_stay_in_their_zone() {
+        let movement = PaddleMovement { acceleration: Some(2_500.0), zone_depth: Some(60.0), ..default() };
+        let rules = MatchRules { movement, ..default() };
+        let mut simulation = Simulation::new(SimulationSettings::default(), AiPlayers::default(), rules);
+        simulation.start_match();
+        let line = left_paddle_position(&mut simulation).x;
+
+        *left_paddle_input(&mut simulation) = PaddleInput { axis: 1.0, depth: 1.0, serve: false };
+        simulation.run(12);
+        // From a standstill the paddle covers much less than a tenth of a second at full speed
+        let ramped = left_paddle_position(&mut simulation);
+        assert!(ramped.y > 0.0 && ramped.y < PADDLE_SPEED * 0.05, "paddle at {ramped}");
+
+        simulation.run(60);
+        let pushed = left_paddle_position(&mut simulation);
+        assert!((pushed.x - (line + 60.0)).abs() < 0.01, "paddle at {pushed}");
+
+        *left_paddle_input(&mut simulation) = PaddleInput::default();
+        simulation.run(60);
+        let stopped = left_paddle_position(&mut simulation);
+        assert!(stopped.y > pushed.y + 5.0 && stopped.x == pushed.x, "paddle at {stopped}");
+    }
+
+    #[test]
+    fn missed_ball_scores_a_point() {
+        let mut simulation = two_players(1);
+        simulation.start_match();
+        simulation.skip_countdown();
+
+        let mut ticks = 0;
+        while simulation.scores.is_empty() && ticks < 120 * 60 {
+            simulation.tick();
+            ticks += 1;
+        }
+
+        // The state change queued by the score lands on the next tick
+        simulation.tick();
+        let scoreboard = simulation.scoreboard();
+        match simulation.scores[..] {
+            [ScoreEvent::LeftScored] => assert_eq!((scoreboard.left, scoreboard.right), (1, 0)),
+            [ScoreEvent::RightScored] => assert_eq!((scoreboard.left, scoreboard.right), (0, 1)),
+            ref scores => panic!("expected a single score, got {scores:?}"),
+        }
+        assert_eq!(simulation.state(), GameState::Countdown);
+        // The point's winner serves the next one from their own paddle
+        let parked = ball_position(&mut simulation);
+        assert_eq!(parked.x < 0.0, simulation.scores[0] == ScoreEvent::LeftScored, "ball at {parked}");
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_match() {
+        let mut first = ai_match(42, MatchRules::default());
+        let mut second = ai_match(42, MatchRules::default());
+        for simulation in [&mut first, &mut second] {
+            simulation.start_match();
+            simulation.run(120 * 30);
+        }
+
+        assert_eq!(ball_position(&mut first), ball_position(&mut second));
+        assert_eq!(first.scores, second.scores);
+    }
+
+    #[test]
+    fn ai_match_runs_to_a_winner() {
+        let rules = MatchRules { target_score: 3, win_by: 1, ..default() };
+        let mut simulation = ai_match(7, rules);
+        simulation.start_match();
+
+        let mut ticks = 0;
+        while simulation.state() != GameState::GameOver && ticks < 120 * 600 {
+            simulation.tick();
+            ticks += 1;
+        }
+
+        let scoreboard = simulation.scoreboard();
+        let (left, right) = (scoreboard.left, scoreboard.right);
+        assert_eq!(simulation.state(), GameState::GameOver);
+        assert_eq!(simulation.status().winner, Some(if left > right { Side::Left } else { Side::Right }));
+        assert_eq!(left.max(right), 3);
+        assert_eq!(simulation.scores.len() as u32, left + right);
+    }
+
+    #[test]
+    fn doubles_ai_match_puts_two_paddles_on_each_side() {
+        let rules = MatchRules { doubles: true, target_score: 2, win_by: 1, ..default() };
+        let mut simulation = ai_match(3, rules);
+        simulation.start_match();
+
+        let world = simulation.app.world_mut();
+        let mut xs: Vec<f32> =
+            world.query_filtered::<&Transform, With<Paddle>>().iter(world).map(|transform| transform.translation.x).collect();
+        xs.sort_by(f32::total_cmp);
+        let settings = PongSettings::default();
+        let back = settings.paddle_position(Paddle::back(Side::Right)).x;
+        let forward = settings.paddle_position(Paddle { side: Side::Right, slot: PaddleSlot::Forward }).x;
+        assert_eq!(xs, [-back, -forward, forward, back]);
+
+        let mut ticks = 0;
+        while simulation.state() != GameState::GameOver && ticks < 120 * 600 {
+            simulation.tick();
+            ticks += 1;
+        }
+        assert_eq!(simulation.state(), GameState::GameOver);
+    }
+
+    #[test]
+    fn four_player_ai_match_runs_to_a_winner() {
+        let rules = MatchRules { four_player: Some(FourPlayerRules { lives: 2 }), ..default() };
+        let mut simulation = ai_match(7, rules);
+        simulation.start_match();
+
+        let mut ticks = 0;
+        while simulation.state() != GameState::GameOver && ticks < 120 * 600 {
+            simulation.tick();
+            ticks += 1;
+        }
+
+        let lives = simulation.scoreboard().lives;
+        let winner = simulation.status().winner.expect("a winner");
+        assert_eq!(simulation.state(), GameState::GameOver);
+        assert!(lives[winner.index()] > 0);
+        assert_eq!(lives.iter().filter(|lives| **lives > 0).count(), 1, "lives {lives:?}");
+        assert!(simulation.scores.is_empty());
+    }
+}
@@ -0,0 +1,639 @@
+/// Module for mapping keyboard, gamepad and mouse input to game actions
+use std::{fmt, fs, io, path::{Path, PathBuf}};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::gamepad::{apply_deadzone, PaddleGamepads};
+use crate::paddle::{Paddle, PaddleSlot, Side};
+
+/// File the input bindings are read from and saved to unless `--bindings` is given
+pub const DEFAULT_BINDINGS_PATH: &str = "bindings.ron";
+/// Value above which an action counts as pressed
+const PRESS_THRESHOLD: f32 = 0.5;
+
+/// Something a player can do, independent of the device they do it with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    /// Move the paddle up, or right for the top and bottom paddles
+    PaddleUp,
+    /// Move the paddle down, or left for the top and bottom paddles
+    PaddleDown,
+    /// Move the paddle away from its goal, when the match gives paddles room to
+    PaddleForward,
+    /// Move the paddle back towards its goal
+    PaddleBack,
+    /// Launch the ball when it is your serve
+    Serve,
+    /// Pause or resume the game
+    Pause,
+    /// Close the game
+    Quit,
+    /// Turn the sound off or back on
+    Mute,
+}
+
+impl InputAction {
+    /// Every action, in the order they are listed on the controls screen
+    pub const ALL: [InputAction; 8] = [
+        InputAction::PaddleUp,
+        InputAction::PaddleDown,
+        InputAction::PaddleForward,
+        InputAction::PaddleBack,
+        InputAction::Serve,
+        InputAction::Pause,
+        InputAction::Quit,
+        InputAction::Mute,
+    ];
+
+    /// Name of the action as shown on the controls screen
+    pub fn name(self) -> &'static str {
+        match self {
+            InputAction::PaddleUp => "Paddle Up",
+            InputAction::PaddleDown => "Paddle Down",
+            InputAction::PaddleForward => "Paddle Forward",
+            InputAction::PaddleBack => "Paddle Back",
+            InputAction::Serve => "Serve",
+            InputAction::Pause => "Pause",
+            InputAction::Quit => "Quit",
+            InputAction::Mute => "Mute",
+        }
+    }
+
+    /// Name of the action as shown on the controls screen for the player on `side`
+    ///
+    /// The top and bottom paddles move sideways, so their movement is labelled left and right.
+    pub fn name_for(self, side: Side) -> &'static str {
+        match self {
+            InputAction::PaddleUp if side.horizontal() => "Paddle Right",
+            InputAction::PaddleDown if side.horizontal() => "Paddle Left",
+            _ => self.name(),
+        }
+    }
+
+    /// Position of the action in InputAction::ALL
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Direction a gamepad axis has to be pushed to trigger a binding
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AxisDirection {
+    /// Up or right
+    Positive,
+    /// Down or left
+    Negative,
+}
+
+/// A single physical input that triggers an action
+///
+/// Gamepad bindings refer to whichever gamepad is assigned to the player in
+/// PaddleGamepads.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    /// A key on the keyboard
+    Key(KeyCode),
+    /// A button on the player's gamepad
+    GamepadButton(GamepadButton),
+    /// An axis on the player's gamepad, pushed in one direction
+    ///
+    /// Gives a proportional value, so a stick half way up moves a paddle at half speed.
+    GamepadAxis(GamepadAxis, AxisDirection),
+    /// A mouse button
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    /// Short description of the binding for the controls screen
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::GamepadButton(button) => format!("Pad {:?}", button),
+            Binding::GamepadAxis(axis, AxisDirection::Positive) => format!("Pad {:?}+", axis),
+            Binding::GamepadAxis(axis, AxisDirection::Negative) => format!("Pad {:?}-", axis),
+            Binding::Mouse(button) => format!("Mouse {:?}", button),
+        }
+    }
+
+    /// Whether two bindings come from the same kind of device
+    ///
+    /// Rebinding an action replaces its bindings for that kind of device only,
+    /// so remapping a key keeps the gamepad controls working.
+    pub fn same_device(&self, other: &Binding) -> bool {
+        matches!(
+            (self, other),
+            (Binding::Key(_), Binding::Key(_))
+                | (Binding::GamepadButton(_) | Binding::GamepadAxis(..), Binding::GamepadButton(_) | Binding::GamepadAxis(..))
+                | (Binding::Mouse(_), Binding::Mouse(_))
+        )
+    }
+
+    /// How strongly the binding is currently held, from 0 to 1
+    fn value(&self, keyboard: &ButtonInput<KeyCode>, mouse: &ButtonInput<MouseButton>, gamepad: Option<&Gamepad>) -> f32 {
+        let held = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+        match (self, gamepad) {
+            (Binding::Key(key), _) => held(keyboard.pressed(*key)),
+            (Binding::Mouse(button), _) => held(mouse.pressed(*button)),
+            (Binding::GamepadButton(button), Some(gamepad)) => held(gamepad.pressed(*button)),
+            (Binding::GamepadAxis(axis, direction), Some(gamepad)) => {
+                let value = apply_deadzone(gamepad.get(*axis).unwrap_or(0.0));
+                match direction {
+                    AxisDirection::Positive => value.max(0.0),
+                    AxisDirection::Negative => (-value).max(0.0),
+                }
+            }
+            (Binding::GamepadButton(_) | Binding::GamepadAxis(..), None) => 0.0,
+        }
+    }
+}
+
+/// The bindings for each of one player's actions
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerBindings {
+    /// Inputs that move the paddle up
+    pub paddle_up: Vec<Binding>,
+    /// Inputs that move the paddle down
+    pub paddle_down: Vec<Binding>,
+    /// Inputs that move the paddle away from its goal, missing from bindings saved before paddles could
+    #[serde(default)]
+    pub paddle_forward: Vec<Binding>,
+    /// Inputs that move the paddle back towards its goal, missing from bindings saved before paddles could
+    #[serde(default)]
+    pub paddle_back: Vec<Binding>,
+    /// Inputs that launch the ball, missing from bindings saved before serving existed
+    #[serde(default = "default_serve_bindings")]
+    pub serve: Vec<Binding>,
+    /// Inputs that pause and resume the game
+    pub pause: Vec<Binding>,
+    /// Inputs that close the game
+    pub quit: Vec<Binding>,
+    /// Inputs that turn the sound off and on, missing from bindings saved before sound existed
+    #[serde(default = "default_mute_bindings")]
+    pub mute: Vec<Binding>,
+}
+
+impl PlayerBindings {
+    /// Bindings for one action
+    pub fn get(&self, action: InputAction) -> &[Binding] {
+        match action {
+            InputAction::PaddleUp => &self.paddle_up,
+            InputAction::PaddleDown => &self.paddle_down,
+            InputAction::PaddleForward => &self.paddle_forward,
+            InputAction::PaddleBack => &self.paddle_back,
+            InputAction::Serve => &self.serve,
+            InputAction::Pause => &self.pause,
+            InputAction::Quit => &self.quit,
+            InputAction::Mute => &self.mute,
+        }
+    }
+
+    /// Mutable bindings for one action
+    pub fn get_mut(&mut self, action: InputAction) -> &mut Vec<Binding> {
+        match action {
+            InputAction::PaddleUp => &mut self.paddle_up,
+            InputAction::PaddleDown => &mut self.paddle_down,
+            InputAction::PaddleForward => &mut self.paddle_forward,
+            InputAction::PaddleBack => &mut self.paddle_back,
+            InputAction::Serve => &mut self.serve,
+            InputAction::Pause => &mut self.pause,
+            InputAction::Quit => &mut self.quit,
+            InputAction::Mute => &mut self.mute,
+        }
+    }
+
+    /// Default bindings shared by every player apart from the movement and serve keys
+    ///
+    /// The top and bottom paddles move sideways, so they take the gamepad's
+    /// horizontal stick and directions. Moving forward and back takes the other
+    /// stick axis, pushed towards the middle of the field for forward.
+    fn with_keys(side: Side, [up, down, forward, back]: [KeyCode; 4], serve: KeyCode) -> Self {
+        let (stick, dpad_up, dpad_down) = if side.horizontal() {
+            (GamepadAxis::LeftStickX, GamepadButton::DPadRight, GamepadButton::DPadLeft)
+        } else {
+            (GamepadAxis::LeftStickY, GamepadButton::DPadUp, GamepadButton::DPadDown)
+        };
+        let (depth_stick, toward_middle) = if side.horizontal() {
+            (GamepadAxis::LeftStickY, side.inward().y)
+        } else {
+            (GamepadAxis::LeftStickX, side.inward().x)
+        };
+        let (stick_forward, stick_back) = if toward_middle > 0.0 {
+            (AxisDirection::Positive, AxisDirection::Negative)
+        } else {
+            (AxisDirection::Negative, AxisDirection::Positive)
+        };
+        let (dpad_forward, dpad_back) = match side {
+            Side::Left => (GamepadButton::DPadRight, GamepadButton::DPadLeft),
+            Side::Right => (GamepadButton::DPadLeft, GamepadButton::DPadRight),
+            Side::Top => (GamepadButton::DPadDown, GamepadButton::DPadUp),
+            Side::Bottom => (GamepadButton::DPadUp, GamepadButton::DPadDown),
+        };
+        PlayerBindings {
+            paddle_up: vec![
+                Binding::Key(up),
+                Binding::GamepadAxis(stick, AxisDirection::Positive),
+                Binding::GamepadButton(dpad_up),
+            ],
+            paddle_down: vec![
+                Binding::Key(down),
+                Binding::GamepadAxis(stick, AxisDirection::Negative),
+                Binding::GamepadButton(dpad_down),
+            ],
+            paddle_forward: vec![
+                Binding::Key(forward),
+                Binding::GamepadAxis(depth_stick, stick_forward),
+                Binding::GamepadButton(dpad_forward),
+            ],
+            paddle_back: vec![
+                Binding::Key(back),
+                Binding::GamepadAxis(depth_stick, stick_back),
+                Binding::GamepadButton(dpad_back),
+            ],
+            serve: vec![Binding::Key(serve), Binding::GamepadButton(GamepadButton::South)],
+            pause: vec![Binding::Key(KeyCode::KeyP), Binding::GamepadButton(GamepadButton::Start)],
+            quit: vec![Binding::Key(KeyCode::Escape)],
+            mute: default_mute_bindings(),
+        }
+    }
+}
+
+/// Default inputs for InputAction::Serve in bindings that have none saved
+///
+/// The serve key differs between the players, so only the gamepad button can be
+/// filled in. The ball is still served when the countdown runs out.
+fn default_serve_bindings() -> Vec<Binding> {
+    vec![Binding::GamepadButton(GamepadButton::South)]
+}
+
+/// Default inputs for InputAction::Mute
+fn default_mute_bindings() -> Vec<Binding> {
+    vec![Binding::Key(KeyCode::KeyN), Binding::GamepadButton(GamepadButton::Select)]
+}
+
+/// Default bindings for the top player, missing from bindings saved before four-player matches existed
+fn default_top_bindings() -> PlayerBindings {
+    PlayerBindings::with_keys(Side::Top, [KeyCode::KeyL, KeyCode::KeyJ, KeyCode::Comma, KeyCode::KeyI], KeyCode::KeyK)
+}
+
+/// Default bindings for the bottom player, missing from bindings saved before four-player matches existed
+fn default_bottom_bindings() -> PlayerBindings {
+    PlayerBindings::with_keys(
+        Side::Bottom,
+        [KeyCode::Numpad6, KeyCode::Numpad4, KeyCode::NumpadAdd, KeyCode::NumpadEnter],
+        KeyCode::Numpad5,
+    )
+}
+
+/// Default bindings for the left forward player, missing from bindings saved before doubles matches existed
+fn default_left_forward_bindings() -> PlayerBindings {
+    PlayerBindings::with_keys(Side::Left, [KeyCode::KeyT, KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyR], KeyCode::KeyF)
+}
+
+/// Default bindings for the right forward player, missing from bindings saved before doubles matches existed
+fn default_right_forward_bindings() -> PlayerBindings {
+    PlayerBindings::with_keys(
+        Side::Right,
+        [KeyCode::Numpad8, KeyCode::Numpad2, KeyCode::Numpad1, KeyCode::Numpad3],
+        KeyCode::Numpad0,
+    )
+}
+
+/// Input bindings for every player, saved to and loaded from a RON file
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputBindings {
+    /// Bindings for the player on the left
+    pub left: PlayerBindings,
+    /// Bindings for the player on the right
+    pub right: PlayerBindings,
+    /// Bindings for the player along the top in a four-player match
+    #[serde(default = "default_top_bindings")]
+    pub top: PlayerBindings,
+    /// Bindings for the player along the bottom in a four-player match
+    #[serde(default = "default_bottom_bindings")]
+    pub bottom: PlayerBindings,
+    /// Bindings for the player on the left forward paddle in a doubles match
+    #[serde(default = "default_left_forward_bindings")]
+    pub left_forward: PlayerBindings,
+    /// Bindings for the player on the right forward paddle in a doubles match
+    #[serde(default = "default_right_forward_bindings")]
+    pub right_forward: PlayerBindings,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings {
+            left: PlayerBindings::with_keys(Side::Left, [KeyCode::KeyW, KeyCode::KeyS, KeyCode::KeyE, KeyCode::KeyQ], KeyCode::KeyD),
+            right: PlayerBindings::with_keys(
+                Side::Right,
+                [KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ShiftRight, KeyCode::ControlRight],
+                KeyCode::ArrowLeft,
+            ),
+            top: default_top_bindings(),
+            bottom: default_bottom_bindings(),
+            left_forward: default_left_forward_bindings(),
+            right_forward: default_right_forward_bindings(),
+        }
+    }
+}
+
+impl InputBindings {
+    /// Bindings for the player on `paddle`
+    ///
+    /// Four-player matches have no forward paddles, so the top and bottom
+    /// players are always on the back ones.
+    pub fn player(&self, paddle: Paddle) -> &PlayerBindings {
+        match (paddle.side, paddle.slot) {
+            (Side::Left, PaddleSlot::Back) => &self.left,
+            (Side::Right, PaddleSlot::Back) => &self.right,
+            (Side::Left, PaddleSlot::Forward) => &self.left_forward,
+            (Side::Right, PaddleSlot::Forward) => &self.right_forward,
+            (Side::Top, _) => &self.top,
+            (Side::Bottom, _) => &self.bottom,
+        }
+    }
+
+    /// Mutable bindings for the player on `paddle`
+    pub fn player_mut(&mut self, paddle: Paddle) -> &mut PlayerBindings {
+        match (paddle.side, paddle.slot) {
+            (Side::Left, PaddleSlot::Back) => &mut self.left,
+            (Side::Right, PaddleSlot::Back) => &mut self.right,
+            (Side::Left, PaddleSlot::Forward) => &mut self.left_forward,
+            (Side::Right, PaddleSlot::Forward) => &mut self.right_forward,
+            (Side::Top, _) => &mut self.top,
+            (Side::Bottom, _) => &mut self.bottom,
+        }
+    }
+
+    /// Reads bindings from a RON file
+    pub fn load(path: &Path) -> Result<Self, BindingsError> {
+        let text = fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    /// Reads bindings from a RON file, falling back to the defaults
+    ///
+    /// A missing file is expected on first run and silently gives the defaults.
+    /// Any other problem is reported before falling back.
+    pub fn load_or_default(path: &Path) -> Self {
+        match InputBindings::load(path) {
+            Ok(bindings) => bindings,
+            Err(BindingsError::Io(error)) if error.kind() == io::ErrorKind::NotFound => InputBindings::default(),
+            Err(error) => {
+                warn!("Could not load bindings from {}: {}", path.display(), error);
+                InputBindings::default()
+            }
+        }
+    }
+
+    /// Writes the bindings to a RON file
+    pub fn save(&self, path: &Path) -> Result<(), BindingsError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Errors that can happen while loading or saving input bindings
+#[derive(Debug)]
+pub enum BindingsError {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file is not valid RON for InputBindings
+    Parse(ron::error::SpannedError),
+    /// The bindings could not be turned into RON
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingsError::Io(error) => write!(f, "{}", error),
+            BindingsError::Parse(error) => write!(f, "invalid bindings file: {}", error),
+            BindingsError::Serialize(error) => write!(f, "could not write bindings: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+impl From<io::Error> for BindingsError {
+    fn from(error: io::Error) -> Self {
+        BindingsError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for BindingsError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        BindingsError::Parse(error)
+    }
+}
+
+impl From<ron::Error> for BindingsError {
+    fn from(error: ron::Error) -> Self {
+        BindingsError::Serialize(error)
+    }
+}
+
+/// Location of the input bindings file
+#[derive(Resource)]
+pub struct BindingsPath(pub PathBuf);
+
+impl Default for BindingsPath {
+    fn default() -> Self {
+        BindingsPath(PathBuf::from(DEFAULT_BINDINGS_PATH))
+    }
+}
+
+impl BindingsPath {
+    /// Reads the bindings file location from `--bindings <path>` on the command line
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        let mut path = BindingsPath::default();
+
+        while let Some(arg) = args.next() {
+            if arg == "--bindings" && let Some(value) = args.next() {
+                path = BindingsPath(PathBuf::from(value));
+            }
+        }
+
+        path
+    }
+}
+
+/// Current and previous values of one player's actions
+#[derive(Default)]
+pub struct PlayerActions {
+    /// Value of each action this frame, indexed like InputAction::ALL
+    values: [f32; InputAction::ALL.len()],
+    /// Value of each action last frame
+    previous: [f32; InputAction::ALL.len()],
+}
+
+impl PlayerActions {
+    /// How strongly the action is held, from 0 to 1
+    pub fn value(&self, action: InputAction) -> f32 {
+        self.values[action.index()]
+    }
+
+    /// Whether the action is held
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.value(action) > PRESS_THRESHOLD
+    }
+
+    /// Whether the action started being held this frame
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.pressed(action) && self.previous[action.index()] <= PRESS_THRESHOLD
+    }
+
+    /// Paddle movement requested by the player, from -1 (down or left) to 1 (up or right)
+    pub fn paddle_axis(&self) -> f32 {
+        self.value(InputAction::PaddleUp) - self.value(InputAction::PaddleDown)
+    }
+
+    /// Movement away from the goal requested by the player, from -1 (back) to 1 (forward)
+    pub fn depth_axis(&self) -> f32 {
+        self.value(InputAction::PaddleForward) - self.value(InputAction::PaddleBack)
+    }
+}
+
+/// The state of every player's actions, refreshed from the bindings each frame
+#[derive(Resource, Default)]
+pub struct ActionState {
+    /// Actions of the player on the left
+    pub left: PlayerActions,
+    /// Actions of the player on the right
+    pub right: PlayerActions,
+    /// Actions of the player along the top
+    pub top: PlayerActions,
+    /// Actions of the player along the bottom
+    pub bottom: PlayerActions,
+    /// Actions of the player on the left forward paddle
+    pub left_forward: PlayerActions,
+    /// Actions of the player on the right forward paddle
+    pub right_forward: PlayerActions,
+}
+
+impl ActionState {
+    /// Actions of the player on `paddle`
+    pub fn player(&self, paddle: Paddle) -> &PlayerActions {
+        match (paddle.side, paddle.slot) {
+            (Side::Left, PaddleSlot::Back) => &self.left,
+            (Side::Right, PaddleSlot::Back) => &self.right,
+            (Side::Left, PaddleSlot::Forward) => &self.left_forward,
+            (Side::Right, PaddleSlot::Forward) => &self.right_forward,
+            (Side::Top, _) => &self.top,
+            (Side::Bottom, _) => &self.bottom,
+        }
+    }
+
+    /// Mutable actions of the player on `paddle`
+    fn player_mut(&mut self, paddle: Paddle) -> &mut PlayerActions {
+        match (paddle.side, paddle.slot) {
+            (Side::Left, PaddleSlot::Back) => &mut self.left,
+            (Side::Right, PaddleSlot::Back) => &mut self.right,
+            (Side::Left, PaddleSlot::Forward) => &mut self.left_forward,
+            (Side::Right, PaddleSlot::Forward) => &mut self.right_forward,
+            (Side::Top, _) => &mut self.top,
+            (Side::Bottom, _) => &mut self.bottom,
+        }
+    }
+
+    /// Whether any player started the action this frame
+    pub fn any_just_pressed(&self, action: InputAction) -> bool {
+        Paddle::PLAYERS.into_iter().any(|paddle| self.player(paddle).just_pressed(action))
+    }
+}
+
+/// Refreshes the ActionState from the keyboard, mouse and assigned gamepads
+///
+/// Each action takes the strongest value of any of its bindings, so a key
+/// press and a stick push can be used interchangeably.
+pub fn update_action_state(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    paddle_gamepads: Res<PaddleGamepads>,
+    gamepad_query: Query<&Gamepad>,
+    bindings: Res<InputBindings>,
+    mut action_state: ResMut<ActionState>,
+) {
+    for paddle in Paddle::PLAYERS {
+        let gamepad = paddle_gamepads.get(paddle).and_then(|gamepad| gamepad_query.get(gamepad).ok());
+
+        let player_bindings = bindings.player(paddle);
+        let actions = action_state.player_mut(paddle);
+
+        actions.previous = actions.values;
+        for action in InputAction::ALL {
+            actions.values[action.index()] = player_bindings
+                .get(action)
+                .iter()
+                .map(|binding| binding.value(&keyboard, &mouse, gamepad))
+                .fold(0.0, f32::max);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_round_trip_through_ron() {
+        let bindings = InputBindings::default();
+
+        let text = ron::ser::to_string_pretty(&bindings, ron::ser::PrettyConfig::default()).unwrap();
+        let loaded: InputBindings = ron::from_str(&text).unwrap();
+
+        assert_eq!(loaded, bindings);
+    }
+
+    #[test]
+    fn bindings_saved_before_newer_actions_get_the_defaults() {
+        let text = "(paddle_up: [Key(KeyW)], paddle_down: [Key(KeyS)], pause: [], quit: [Key(Escape)])";
+
+        let loaded: PlayerBindings = ron::from_str(text).unwrap();
+
+        assert_eq!(loaded.mute, default_mute_bindings());
+        assert_eq!(loaded.serve, default_serve_bindings());
+        assert!(loaded.paddle_forward.is_empty() && loaded.paddle_back.is_empty());
+    }
+
+    #[test]
+    fn bindings_saved_before_more_players_get_the_defaults() {
+        let mut bindings = InputBindings::default();
+        bindings.left.paddle_up = vec![Binding::Key(KeyCode::KeyQ)];
+        let text = ron::ser::to_string(&bindings).unwrap();
+        let two_players = text.split(",top:").next().unwrap().to_string() + ")";
+
+        let loaded: InputBindings = ron::from_str(&two_players).unwrap();
+
+        assert_eq!(loaded.left, bindings.left);
+        assert_eq!(loaded.top, default_top_bindings());
+        assert_eq!(loaded.bottom, default_bottom_bindings());
+        assert_eq!(loaded.right_forward, default_right_forward_bindings());
+    }
+
+    #[test]
+    fn axis_bindings_give_proportional_values() {
+        let keyboard = ButtonInput::default();
+        let mouse = ButtonInput::default();
+        let mut gamepad = Gamepad::default();
+        gamepad.analog_mut().set(GamepadAxis::LeftStickY, -1.0);
+
+        let down = Binding::GamepadAxis(GamepadAxis::LeftStickY, AxisDirection::Negative);
+        let up = Binding::GamepadAxis(GamepadAxis::LeftStickY, AxisDirection::Positive);
+
+        assert_eq!(down.value(&keyboard, &mouse, Some(&gamepad)), 1.0);
+        assert_eq!(up.value(&keyboard, &mouse, Some(&gamepad)), 0.0);
+        assert_eq!(down.value(&keyboard, &mouse, None), 0.0);
+    }
+
+    #[test]
+    fn rebinding_keeps_other_devices() {
+        let key = Binding::Key(KeyCode::KeyQ);
+
+        assert!(key.same_device(&Binding::Key(KeyCode::KeyW)));
+        assert!(!key.same_device(&Binding::GamepadButton(GamepadButton::DPadUp)));
+        assert!(Binding::GamepadButton(GamepadButton::South)
+            .same_device(&Binding::GamepadAxis(GamepadAxis::LeftStickX, AxisDirection::Positive)));
+    }
+}
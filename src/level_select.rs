@@ -0,0 +1,175 @@
+/// Module for the Level Select screen choosing the arena matches are played in
+use std::path::Path;
+use bevy::prelude::*;
+use crate::arena::{Arena, ArenaError, LEVEL_DIR};
+use crate::scoreboard::MatchRules;
+use crate::settings::PongSettings;
+use crate::state::GameState;
+
+/// An arena offered on the Level Select screen
+pub struct Level {
+    /// Name shown in the list, the file name for arenas that failed to load
+    pub name: String,
+    /// The arena, or why its file could not be used
+    pub arena: Result<Arena, ArenaError>,
+}
+
+/// Arenas found for the Level Select screen and the one selected
+#[derive(Resource, Default)]
+pub struct LevelBrowser {
+    /// Built-in arenas followed by the files in LEVEL_DIR
+    levels: Vec<Level>,
+    /// Index of the highlighted arena
+    selected: usize,
+}
+
+/// Component for the text listing the arenas
+#[derive(Component)]
+pub struct LevelListText;
+
+/// Finds the arenas and spawns the Level Select screen
+///
+/// The arena files are read again every time the screen opens, so edits show up
+/// without restarting the game. The arena already chosen starts out selected.
+pub fn spawn_level_select_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<PongSettings>,
+    rules: Res<MatchRules>,
+    mut browser: ResMut<LevelBrowser>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let built_in = Arena::built_in().into_iter().map(|arena| Level { name: arena.name.clone(), arena: Ok(arena) });
+    let files = Arena::load_dir(Path::new(LEVEL_DIR), &settings).into_iter().map(|(path, arena)| {
+        let file_name = path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        let name = arena.as_ref().map_or(file_name, |arena| arena.name.clone());
+        Level { name, arena }
+    });
+    let levels: Vec<Level> = built_in.chain(files).collect();
+    let selected = levels
+        .iter()
+        .position(|level| level.arena.as_ref().is_ok_and(|arena| *arena == rules.arena))
+        .unwrap_or(0);
+    *browser = LevelBrowser { levels, selected };
+
+    commands.spawn((
+        Text2d::new("Level Select"),
+        TextFont {
+            font: font.clone(),
+            font_size: 40.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, 195.0, 1.0)),
+        StateScoped(GameState::LevelSelect),
+    ));
+
+    commands.spawn((
+        Text2d::new(level_list_text(&browser.levels, browser.selected)),
+        TextFont {
+            font: font.clone(),
+            font_size: 20.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, 10.0, 1.0)),
+        LevelListText,
+        StateScoped(GameState::LevelSelect),
+    ));
+
+    commands.spawn((
+        Text2d::new(format!("Up/Down - Select    Enter - Play Here    M - Menu\nMore arenas are read from {LEVEL_DIR}/")),
+        TextFont {
+            font: font.clone(),
+            font_size: 16.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -200.0, 1.0)),
+        StateScoped(GameState::LevelSelect),
+    ));
+}
+
+/// Forgets the arenas found once the screen is left
+pub fn close_level_browser(mut browser: ResMut<LevelBrowser>) {
+    *browser = LevelBrowser::default();
+}
+
+/// Handles the Level Select screen
+///
+/// Up and Down move the selection and Enter plays the next matches in the
+/// selected arena, going back to the main menu. Arenas whose files are malformed
+/// can't be chosen, and the problems found in them are listed when they are
+/// selected. M goes back without changing the arena.
+pub fn navigate_level_select(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut browser: ResMut<LevelBrowser>,
+    mut rules: ResMut<MatchRules>,
+    mut text_query: Query<&mut Text2d, With<LevelListText>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let count = browser.levels.len();
+    if count == 0 {
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::ArrowUp) {
+        browser.selected = (browser.selected + count - 1) % count;
+    }
+    if keyboard.just_pressed(KeyCode::ArrowDown) {
+        browser.selected = (browser.selected + 1) % count;
+    }
+
+    if keyboard.just_pressed(KeyCode::Enter)
+        && let Ok(arena) = &browser.levels[browser.selected].arena
+    {
+        rules.arena = arena.clone();
+        next_state.set(GameState::MainMenu);
+    }
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        next_state.set(GameState::MainMenu);
+    }
+
+    if browser.is_changed() {
+        for mut text in text_query.iter_mut() {
+            text.0 = level_list_text(&browser.levels, browser.selected);
+        }
+    }
+}
+
+/// Text listing every arena, with the selected one marked and its problems if it can't be used
+fn level_list_text(levels: &[Level], selected: usize) -> String {
+    let mut lines: Vec<String> = levels
+        .iter()
+        .enumerate()
+        .map(|(index, level)| {
+            let marker = if index == selected { ">" } else { " " };
+            let status = if level.arena.is_err() { " [invalid]" } else { "" };
+            format!("{marker} {}{status}", level.name)
+        })
+        .collect();
+
+    if let Some(Level { arena: Err(error), .. }) = levels.get(selected) {
+        lines.push(String::new());
+        lines.push(error.to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problems_with_the_selected_level_are_listed() {
+        let levels = vec![
+            Level { name: "Classic".to_string(), arena: Ok(Arena::default()) },
+            Level { name: "broken".to_string(), arena: Err(ArenaError::Invalid(vec!["portal 1 has no size".to_string()])) },
+        ];
+
+        assert_eq!(level_list_text(&levels, 0), "> Classic\n  broken [invalid]");
+        assert_eq!(
+            level_list_text(&levels, 1),
+            "  Classic\n> broken [invalid]\n\narena has 1 problem(s):\n- portal 1 has no size"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+// Bevy system parameters routinely trip this lint
+#![allow(clippy::type_complexity)]
+
+/// Library entry point for embedding Pixel Pong in a Bevy app or reusing its pieces
+pub use background::BackgroundPlugin;
+pub use ball::BallPlugin;
+pub use game::{PongPlugin, PongSet};
+pub use paddle::PaddlePlugin;
+pub use scoreboard::ScoreboardPlugin;
+pub use settings::PongSettings;
+
+/// AI module for computer-controlled paddles
+pub mod ai;
+/// Arena module with the obstacles, portals and goals laid out on the field
+pub mod arena;
+/// Background module responsible for creating the play area and visual elements
+pub mod background;
+/// Ball module with ball physics, movement and collision detection
+pub mod ball;
+/// Broadcast module with the live stream of match events
+pub mod broadcast;
+/// Controls module for the screen where input bindings are remapped
+pub mod controls;
+/// Discovery module for finding matches on the local network
+pub mod discovery;
+/// Events module with the gameplay events other systems can react to
+pub mod events;
+/// Find games module for the screen listing matches on the local network
+pub mod find_games;
+/// Four player module with the rules for matches with a paddle on every edge
+pub mod four_player;
+/// Game module with the PongPlugin that assembles everything else
+pub mod game;
+/// Game over module showing the winner and offering a rematch
+pub mod game_over;
+/// Gamepad module for assigning controllers to paddles
+pub mod gamepad;
+/// Headless module for running matches without a window
+pub mod headless;
+/// Input module mapping keys, gamepads and mouse to game actions
+pub mod input;
+/// Level select module for the screen where the arena is chosen
+pub mod level_select;
+/// Menu module for the title screen
+pub mod menu;
+/// Movement module with the acceleration, friction and zone paddles move with
+pub mod movement;
+/// Multiball module for rallies with several balls in play at once
+pub mod multiball;
+/// Net module with the transports and messages used for online play
+pub mod net;
+/// Paddle module handling player input and paddle positioning
+pub mod paddle;
+/// Powerup module with the power-ups players claim by sending the ball through them
+pub mod powerup;
+/// Remote module for playing or watching a match on a dedicated server
+pub mod remote;
+/// Replay module for recording matches and playing them back
+pub mod replay;
+/// Rollback module keeping online players in step
+pub mod rollback;
+/// Scoreboard module for tracking and displaying player scores
+pub mod scoreboard;
+/// Serve module for putting the ball in play from the serving player's paddle
+pub mod serve;
+/// Server module with the authoritative dedicated server
+pub mod server;
+/// Settings module describing the sizes, speeds and colors of the game
+pub mod settings;
+/// Simulation module running gameplay on a fixed, reproducible timestep
+pub mod simulation;
+/// Sound module with the generated beeps for bounces and points
+pub mod sound;
+/// Speed module with the curves the ball speeds up along during a rally
+pub mod speed;
+/// State module defining the game flow between menu, play, pause and game over
+pub mod state;
+/// Window module for handling window settings and camera configuration
+pub mod window;
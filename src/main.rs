@@ -1,54 +1,146 @@
 /// Main entry point for the Pixel Pong game application
-use bevy::{prelude::*, render::pipelined_rendering::PipelinedRenderingPlugin};
-
-/// Background module responsible for creating the play area and visual elements
-mod background;
-/// Ball module with ball physics, movement and collision detection
-mod ball;
-/// Paddle module handling player input and paddle positioning
-mod paddle;
-/// Scoreboard module for tracking and displaying player scores
-mod scoreboard;
-/// Window module for handling window settings and camera configuration
-mod window;
-
-use scoreboard::{ScoreBoard, ScoreEvent};
+use bevy::{
+    log::{
+        tracing_subscriber::{fmt, EnvFilter},
+        LogPlugin, DEFAULT_FILTER,
+    },
+    prelude::*,
+    render::pipelined_rendering::PipelinedRenderingPlugin,
+};
+use bevy_pong::ai::AiPlayers;
+use bevy_pong::broadcast::{EventStreamConfig, MatchEventLog};
+use bevy_pong::discovery::{self, Announcer, HostAdvertisement, DISCOVERY_PORT};
+use bevy_pong::headless::{self, HeadlessSettings};
+use bevy_pong::input::{BindingsPath, InputBindings};
+use bevy_pong::net::{NetConfig, NetRole};
+use bevy_pong::paddle::Side;
+use bevy_pong::remote::{RemoteConfig, RemoteSession};
+use bevy_pong::replay::{Replay, ReplayConfig, ReplayPlayback, ReplayRecorder};
+use bevy_pong::rollback::NetSession;
+use bevy_pong::scoreboard::MatchRules;
+use bevy_pong::server::ServerConnection;
+use bevy_pong::simulation::SimulationSettings;
+use bevy_pong::sound::SoundSettings;
+use bevy_pong::{window, PongPlugin, PongSettings};
 
 /// Main function that configures and runs the game
 ///
-/// Sets up the Bevy app with all necessary plugins, resources, events, and systems.
-/// Organizes systems into appropriate startup and update schedules.
+/// Sets up the Bevy app with the default plugins and the PongPlugin, configured
+/// from the command line, or simulates AI-vs-AI matches without a window when
+/// `--headless` is given. See the README for every option.
 fn main() {
-    App::new()
+    // Installed before anything is parsed so invalid options are reported, in
+    // windowed and headless runs alike, which is why the app leaves out LogPlugin
+    install_log_output();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(headless) = HeadlessSettings::from_args(args.clone()) {
+        headless::run_headless_matches(headless, args);
+        return;
+    }
+
+    let bindings_path = BindingsPath::from_args(args.clone());
+    let bindings = InputBindings::load_or_default(&bindings_path.0);
+
+    let replay_config = ReplayConfig::from_args(args.clone());
+    let replay = match replay_config.play.as_deref().map(Replay::load) {
+        Some(Ok(replay)) => Some(replay),
+        Some(Err(err)) => {
+            eprintln!("Could not load the replay: {err}");
+            return;
+        }
+        None => None,
+    };
+
+    let settings = replay.as_ref().map_or_else(PongSettings::default, |replay| replay.settings.clone());
+    let net_config = NetConfig::from_args(args.clone());
+    let remote_config = RemoteConfig::from_args(args.clone());
+    let ai_players = if net_config.is_some() || remote_config.is_some() || replay.is_some() {
+        AiPlayers::default()
+    } else {
+        AiPlayers::from_args(args.clone())
+    };
+    let (simulation_settings, mut rules) = match &replay {
+        Some(replay) => (
+            SimulationSettings { tick_rate: replay.tick_rate, seed: replay.seed },
+            replay.rules.clone(),
+        ),
+        None => (SimulationSettings::from_args(args.clone()), MatchRules::from_args(args.clone())),
+    };
+    if (net_config.is_some() || remote_config.is_some()) && rules.strip_local_modes() {
+        eprintln!("Four-player and doubles matches can only be played on one computer, playing one against one");
+    }
+
+    let mut app = App::new();
+    app
         .add_plugins(
             DefaultPlugins
-                .set(window::get_window_settings())
-                .disable::<PipelinedRenderingPlugin>(),
-        )
-        .insert_resource(ScoreBoard::default())
-        .add_event::<ScoreEvent>()
-        .add_systems(
-            Startup,
-            (
-                window::setup_camera,
-                paddle::spawn_left_paddle,
-                paddle::spawn_right_paddle,
-                background::spawn_background,
-                ball::spawn_ball,
-                scoreboard::spawn_scoreboard,
-            ),
+                .set(window::get_window_settings(&settings))
+                .disable::<PipelinedRenderingPlugin>()
+                .disable::<LogPlugin>(),
         )
-        .add_systems(
-            Update,
-            (
-                window::close_on_escape,
-                paddle::move_left_paddle,
-                paddle::move_right_paddle,
-                ball::update_ball,
-                scoreboard::update_scoreboard,
-                scoreboard::update_scoreboard_text,
-                ball::reset_ball_system,
-            ),
-        )
-        .run();
+        .insert_resource(simulation_settings)
+        .insert_resource(ai_players)
+        .insert_resource(rules)
+        .insert_resource(bindings)
+        .insert_resource(bindings_path)
+        .insert_resource(SoundSettings::from_args(args.clone()))
+        .add_plugins(PongPlugin { settings, headless: false });
+
+    if let Some(replay) = replay {
+        app.insert_resource(ReplayPlayback::new(replay));
+        app.run();
+        return;
+    }
+
+    if let Some(config) = net_config {
+        let transport = match config.open_transport() {
+            Ok(transport) => transport,
+            Err(err) => {
+                eprintln!("Could not open a network socket: {err}");
+                return;
+            }
+        };
+        let side = match config.role {
+            NetRole::Host(_) => Side::Left,
+            NetRole::Join(_) => Side::Right,
+        };
+        if let NetRole::Host(port) = config.role {
+            match Announcer::new(DISCOVERY_PORT) {
+                Ok(announcer) => {
+                    let name = discovery::lobby_name_from_args(args.clone());
+                    app.insert_resource(HostAdvertisement::new(announcer, name, port));
+                }
+                Err(err) => warn!("Could not advertise the match on the local network: {err}"),
+            }
+        }
+        app.insert_resource(NetSession::new(transport, side, config.input_delay));
+    } else if let Some(config) = remote_config {
+        match ServerConnection::connect(config.address.as_str(), config.spectate) {
+            Ok(connection) => app.insert_resource(RemoteSession::new(connection, config.delay)),
+            Err(err) => {
+                eprintln!("Could not connect to {}: {err}", config.address);
+                return;
+            }
+        };
+    } else {
+        if let Some(config) = EventStreamConfig::from_args(args) {
+            match config.open() {
+                Ok(stream) => app.insert_resource(stream).init_resource::<MatchEventLog>(),
+                Err(err) => {
+                    eprintln!("Could not open {}: {err}", config.path);
+                    return;
+                }
+            };
+        }
+        app.insert_resource(ReplayRecorder::new(replay_config.record_dir));
+    }
+
+    app.run();
+}
+
+/// Sends log output to standard error, filtered by `RUST_LOG` when it is set
+fn install_log_output() {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("info,{DEFAULT_FILTER}")));
+    fmt().with_env_filter(filter).with_writer(std::io::stderr).init();
 }
@@ -0,0 +1,187 @@
+/// Module for the title menu shown when the game starts
+use bevy::prelude::*;
+use crate::ai::{AiPlayers, Difficulty};
+use crate::input::{Binding, InputAction, InputBindings};
+use crate::scoreboard::MatchRules;
+use crate::settings::PongSettings;
+use crate::speed::{SpeedMode, SpeedProgression};
+use crate::state::GameState;
+
+/// Difficulty the computer opponent will have in a one player game
+#[derive(Resource)]
+pub struct MenuDifficulty(pub Difficulty);
+
+impl Default for MenuDifficulty {
+    fn default() -> Self {
+        MenuDifficulty(Difficulty::Normal)
+    }
+}
+
+/// Component for the menu line showing the selected difficulty
+#[derive(Component)]
+pub struct DifficultyText;
+
+/// Component for the menu line showing the ball's speed progression
+#[derive(Component)]
+pub struct SpeedText;
+
+/// Spawns the title and the list of menu options
+///
+/// The quit option shows the left player's first Quit input.
+pub fn spawn_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    difficulty: Res<MenuDifficulty>,
+    rules: Res<MatchRules>,
+    bindings: Res<InputBindings>,
+) {
+    let font = asset_server.load("fonts/FiraSans-Bold.ttf");
+    let quit = bindings
+        .left
+        .quit
+        .first()
+        .map(Binding::label)
+        .unwrap_or_else(|| InputAction::Quit.name().to_string());
+
+    commands.spawn((
+        Text2d::new("PIXEL PONG"),
+        TextFont {
+            font: font.clone(),
+            font_size: 70.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, 110.0, 1.0)),
+        StateScoped(GameState::MainMenu),
+    ));
+
+    commands.spawn((
+        Text2d::new(format!(
+            "1 - One Player\n2 - Two Players    3 - Doubles    4 - Four Players\nEnter - Start\nF - Find Games\nC - Controls\n{} - Quit",
+            quit
+        )),
+        TextFont {
+            font: font.clone(),
+            font_size: 24.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -10.0, 1.0)),
+        StateScoped(GameState::MainMenu),
+    ));
+
+    commands.spawn((
+        Text2d::new(difficulty_label(difficulty.0)),
+        TextFont {
+            font: font.clone(),
+            font_size: 24.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -110.0, 1.0)),
+        DifficultyText,
+        StateScoped(GameState::MainMenu),
+    ));
+
+    commands.spawn((
+        Text2d::new(speed_label(&rules.speed)),
+        TextFont {
+            font: font.clone(),
+            font_size: 24.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -140.0, 1.0)),
+        SpeedText,
+        StateScoped(GameState::MainMenu),
+    ));
+
+    commands.spawn((
+        Text2d::new(format!("L - Arena: {}", rules.arena.name)),
+        TextFont {
+            font: font.clone(),
+            font_size: 24.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -170.0, 1.0)),
+        StateScoped(GameState::MainMenu),
+    ));
+}
+
+/// Handles key presses on the main menu
+///
+/// 1 starts a game against the computer on the right paddle, 2 starts a game
+/// between two players, 3 starts a doubles game with two players a side, 4
+/// starts a four-player game with a player on every edge, and Enter starts with
+/// the paddles and players as set up on the command line. Doubles and
+/// four-player games go to the Classic field if the arena is in the way of their
+/// extra paddles. D cycles through the computer's difficulty levels, B through
+/// the ball speed modes, L opens the level select screen to pick the arena, F
+/// looks for matches on the local network and C opens the controls screen.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_main_menu_input(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut ai_players: ResMut<AiPlayers>,
+    mut difficulty: ResMut<MenuDifficulty>,
+    mut rules: ResMut<MatchRules>,
+    settings: Res<PongSettings>,
+    mut text_query: Query<&mut Text2d, (With<DifficultyText>, Without<SpeedText>)>,
+    mut speed_text_query: Query<&mut Text2d, With<SpeedText>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyD) {
+        difficulty.0 = difficulty.0.next();
+        for mut text in text_query.iter_mut() {
+            text.0 = difficulty_label(difficulty.0);
+        }
+    }
+    if keyboard.just_pressed(KeyCode::KeyB) {
+        // A custom curve from the command line is followed by Classic
+        rules.speed = SpeedMode::of(&rules.speed).map_or(SpeedMode::Classic, SpeedMode::next).progression();
+        for mut text in speed_text_query.iter_mut() {
+            text.0 = speed_label(&rules.speed);
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::Digit1) {
+        *ai_players = AiPlayers { right: Some(difficulty.0), ..default() };
+        rules.strip_local_modes();
+        next_state.set(GameState::Countdown);
+    } else if keyboard.just_pressed(KeyCode::Digit2) {
+        *ai_players = AiPlayers::default();
+        rules.strip_local_modes();
+        next_state.set(GameState::Countdown);
+    } else if keyboard.just_pressed(KeyCode::Digit3) {
+        *ai_players = AiPlayers::default();
+        rules.four_player = None;
+        rules.doubles = true;
+        rules.fit_arena(&settings);
+        next_state.set(GameState::Countdown);
+    } else if keyboard.just_pressed(KeyCode::Digit4) {
+        *ai_players = AiPlayers::default();
+        rules.doubles = false;
+        rules.four_player.get_or_insert_default();
+        rules.fit_arena(&settings);
+        next_state.set(GameState::Countdown);
+    } else if keyboard.just_pressed(KeyCode::Enter) {
+        next_state.set(GameState::Countdown);
+    } else if keyboard.just_pressed(KeyCode::KeyL) {
+        next_state.set(GameState::LevelSelect);
+    } else if keyboard.just_pressed(KeyCode::KeyF) {
+        next_state.set(GameState::FindGames);
+    } else if keyboard.just_pressed(KeyCode::KeyC) {
+        next_state.set(GameState::Controls);
+    }
+}
+
+/// Text for the difficulty line of the menu
+fn difficulty_label(difficulty: Difficulty) -> String {
+    format!("D - Difficulty: {}", difficulty.name())
+}
+
+/// Text for the ball speed line of the menu
+fn speed_label(progression: &SpeedProgression) -> String {
+    let name = SpeedMode::of(progression).map_or("Custom", SpeedMode::name);
+    format!("B - Ball Speed: {}", name)
+}
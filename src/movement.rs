@@ -0,0 +1,143 @@
+/// Module for how paddles speed up, slow down and move about in front of their goal
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Rate a paddle left alone slows down at unless `--paddle-friction` is given, in pixels per second squared
+pub const DEFAULT_FRICTION: f32 = 3000.0;
+/// Deepest zone paddles may be given in pixels, which still keeps back paddles well out of the middle
+pub const MAX_ZONE_DEPTH: f32 = 200.0;
+
+/// How paddles move when their players push them around
+///
+/// By default a paddle moves at the requested fraction of the paddle speed the
+/// moment it is asked to and stops dead when let go. With an acceleration it
+/// instead gathers speed up to the paddle speed and slides to a stop under
+/// friction. With a zone depth paddles may also leave their starting line and
+/// move towards the middle of the field, up to that far in front of it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaddleMovement {
+    /// Rate a paddle gathers speed at in pixels per second squared, or None to reach full speed at once
+    pub acceleration: Option<f32>,
+    /// Rate a paddle slows down at when it is pushed less hard than it is moving, in pixels per second squared
+    pub friction: f32,
+    /// How far in front of its starting line a paddle may move in pixels, or None to keep it on the line
+    pub zone_depth: Option<f32>,
+}
+
+impl Default for PaddleMovement {
+    fn default() -> Self {
+        PaddleMovement {
+            acceleration: None,
+            friction: DEFAULT_FRICTION,
+            zone_depth: None,
+        }
+    }
+}
+
+impl PaddleMovement {
+    /// Reads the paddle movement from command line arguments
+    ///
+    /// Accepts `--paddle-acceleration <pixels per second squared>`,
+    /// `--paddle-friction <pixels per second squared>` and `--paddle-zone <pixels>`,
+    /// keeping the default for anything missing. Values that fail to parse or
+    /// aren't positive are reported and ignored, and zones deeper than
+    /// MAX_ZONE_DEPTH are trimmed to it.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut movement = PaddleMovement::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if !matches!(arg.as_str(), "--paddle-acceleration" | "--paddle-friction" | "--paddle-zone") {
+                continue;
+            }
+            let value = args.next().unwrap_or_default();
+            let amount = match value.parse::<f32>() {
+                Ok(amount) if amount > 0.0 && amount.is_finite() => amount,
+                _ => {
+                    warn!("Invalid value '{}' for {}", value, arg);
+                    continue;
+                }
+            };
+            match arg.as_str() {
+                "--paddle-acceleration" => movement.acceleration = Some(amount),
+                "--paddle-friction" => movement.friction = amount,
+                _ if amount > MAX_ZONE_DEPTH => {
+                    warn!("{} {} is deeper than the {} allowed, using {}", arg, value, MAX_ZONE_DEPTH, MAX_ZONE_DEPTH);
+                    movement.zone_depth = Some(MAX_ZONE_DEPTH);
+                }
+                _ => movement.zone_depth = Some(amount),
+            }
+        }
+
+        movement
+    }
+
+    /// Speed along one axis after `delta` seconds of a paddle moving at `speed` being pushed towards `wanted`
+    ///
+    /// Without an acceleration the paddle takes the wanted speed straight away.
+    /// Otherwise it speeds up at the acceleration, slows down at the friction,
+    /// and turns around with both working together.
+    pub fn step_speed(&self, speed: f32, wanted: f32, delta: f32) -> f32 {
+        let Some(acceleration) = self.acceleration else {
+            return wanted;
+        };
+        let rate = if wanted * speed < 0.0 {
+            acceleration + self.friction
+        } else if wanted.abs() > speed.abs() {
+            acceleration
+        } else {
+            self.friction
+        };
+        let change = rate * delta;
+        speed + (wanted - speed).clamp(-change, change)
+    }
+
+    /// How far a paddle moving at `speed` slides after it is let go, signed like `speed`
+    pub fn stopping_distance(&self, speed: f32) -> f32 {
+        match self.acceleration {
+            Some(_) => speed * speed.abs() / (2.0 * self.friction),
+            None => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movement_is_parsed_from_args() {
+        assert_eq!(PaddleMovement::from_args(Vec::new()), PaddleMovement::default());
+        let args = ["--paddle-acceleration", "2000", "--paddle-friction", "500", "--paddle-zone", "60"].map(String::from);
+        assert_eq!(
+            PaddleMovement::from_args(args),
+            PaddleMovement { acceleration: Some(2000.0), friction: 500.0, zone_depth: Some(60.0) },
+        );
+        let args = ["--paddle-zone", "-5", "--paddle-friction", "fast"].map(String::from);
+        assert_eq!(PaddleMovement::from_args(args), PaddleMovement::default());
+        let args = ["--paddle-zone", "1000"].map(String::from);
+        assert_eq!(PaddleMovement::from_args(args).zone_depth, Some(MAX_ZONE_DEPTH));
+    }
+
+    #[test]
+    fn instant_movement_takes_the_wanted_speed() {
+        let movement = PaddleMovement::default();
+
+        assert_eq!(movement.step_speed(0.0, 500.0, 0.01), 500.0);
+        assert_eq!(movement.step_speed(500.0, 0.0, 0.01), 0.0);
+    }
+
+    #[test]
+    fn paddles_ramp_up_and_slide_to_a_stop() {
+        let movement = PaddleMovement { acceleration: Some(1000.0), friction: 400.0, zone_depth: None };
+
+        assert_eq!(movement.step_speed(0.0, 500.0, 0.1), 100.0);
+        assert_eq!(movement.step_speed(450.0, 500.0, 0.1), 500.0);
+        assert_eq!(movement.step_speed(300.0, 0.0, 0.1), 260.0);
+        assert_eq!(movement.step_speed(20.0, 0.0, 0.1), 0.0);
+        assert_eq!(movement.step_speed(300.0, -500.0, 0.1), 160.0);
+        assert_eq!(movement.stopping_distance(-400.0), -200.0);
+        assert_eq!(PaddleMovement::default().stopping_distance(400.0), 0.0);
+    }
+}
@@ -0,0 +1,253 @@
+/// Module for rallies played with more than one ball at a time
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::ball::{self, Ball, BounceCount, InPlay, LastHit, RallyTime, Velocity};
+use crate::events::RallyMilestone;
+use crate::game::PongSet;
+use crate::scoreboard::MatchRules;
+use crate::settings::PongSettings;
+use crate::simulation::GameRng;
+use crate::state::GameState;
+
+/// Most balls `--balls` and `--max-balls` can put in play at once
+pub const MAX_BALLS: u32 = 16;
+
+/// When a rally with several balls is over, and which balls score
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RallyEnd {
+    /// The rally goes on until every ball is out, and only the last one scores
+    #[default]
+    LastBallOut,
+    /// Every ball scores a point as it leaves, and the rally ends with the last one
+    EveryBallScores,
+}
+
+impl RallyEnd {
+    /// Parses `last` or `every`
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("last") {
+            Some(RallyEnd::LastBallOut)
+        } else if name.eq_ignore_ascii_case("every") {
+            Some(RallyEnd::EveryBallScores)
+        } else {
+            None
+        }
+    }
+}
+
+/// How many balls are in play during a rally
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultiBallRules {
+    /// Balls served at the start of every rally
+    pub balls: u32,
+    /// Whether every RallyMilestone puts another ball in play
+    pub milestone_balls: bool,
+    /// Most balls milestones can bring into play at once
+    pub max_balls: u32,
+    /// When the rally ends and which balls score
+    pub rally_end: RallyEnd,
+}
+
+impl Default for MultiBallRules {
+    fn default() -> Self {
+        MultiBallRules {
+            balls: 1,
+            milestone_balls: false,
+            max_balls: 4,
+            rally_end: RallyEnd::default(),
+        }
+    }
+}
+
+impl MultiBallRules {
+    /// Reads the multi-ball rules from command line arguments
+    ///
+    /// Accepts `--balls <count>`, `--milestone-balls`, `--max-balls <count>` and
+    /// `--rally-end last|every`, keeping the default for anything missing. Values
+    /// that fail to parse and ball counts outside 1 to MAX_BALLS are reported and
+    /// ignored.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut rules = MultiBallRules::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let value = match arg.as_str() {
+                "--milestone-balls" => {
+                    rules.milestone_balls = true;
+                    continue;
+                }
+                "--balls" | "--max-balls" | "--rally-end" => args.next().unwrap_or_default(),
+                _ => continue,
+            };
+            let parsed = match arg.as_str() {
+                "--balls" => value.parse().ok().filter(valid_ball_count).map(|balls| rules.balls = balls).is_some(),
+                "--max-balls" => value.parse().ok().filter(valid_ball_count).map(|balls| rules.max_balls = balls).is_some(),
+                _ => RallyEnd::from_name(&value).map(|rally_end| rules.rally_end = rally_end).is_some(),
+            };
+            if !parsed {
+                warn!("Invalid value '{}' for {}", value, arg);
+            }
+        }
+
+        rules
+    }
+
+    /// Checks that both ball counts are between 1 and MAX_BALLS
+    pub fn validate(&self) -> Result<(), String> {
+        if !valid_ball_count(&self.balls) || !valid_ball_count(&self.max_balls) {
+            return Err(format!("ball counts have to be between 1 and {}", MAX_BALLS));
+        }
+        Ok(())
+    }
+
+    /// Number of balls a match played by these rules needs
+    pub fn pool_size(&self) -> u32 {
+        let balls = if self.milestone_balls { self.max_balls.max(self.balls) } else { self.balls };
+        balls.max(1)
+    }
+}
+
+/// Whether a ball count can be played with
+fn valid_ball_count(balls: &u32) -> bool {
+    (1..=MAX_BALLS).contains(balls)
+}
+
+/// Plugin putting the extra balls of a multi-ball rally in play
+///
+/// The BallPlugin spawns every ball a match needs and serves the first one from
+/// the serving player's paddle. This plugin sends the other balls of the
+/// MatchRules' MultiBallRules off from the middle of the field as the serve is
+/// made, and adds one more at every RallyMilestone when milestone balls are on.
+pub struct MultiBallPlugin;
+
+impl Plugin for MultiBallPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnTransition { exited: GameState::Countdown, entered: GameState::Playing },
+            serve_extra_balls.before(ball::serve_ball),
+        )
+        .add_systems(
+            FixedUpdate,
+            add_milestone_ball.in_set(PongSet::Physics).after(ball::update_ball),
+        );
+    }
+}
+
+/// Puts the extra balls of the rally in play as the first one is served
+pub fn serve_extra_balls(
+    rules: Res<MatchRules>,
+    settings: Res<PongSettings>,
+    mut rng: ResMut<GameRng>,
+    mut ball_query: Query<
+        (&mut Transform, &mut Velocity, &mut BounceCount, &mut RallyTime, &mut LastHit, &mut InPlay),
+        With<Ball>,
+    >,
+) {
+    let extra = rules.multi_ball.balls.saturating_sub(1) as usize;
+    for ball in ball_query.iter_mut().filter(|(.., in_play)| !in_play.0).take(extra) {
+        launch_from_center(ball, settings.ball_speed, &mut rng);
+    }
+}
+
+/// Puts another ball in play whenever the rally reaches a milestone
+///
+/// Nothing happens unless milestone balls are on, the rally is still going and
+/// there are fewer than `max_balls` in play.
+pub fn add_milestone_ball(
+    mut milestones: EventReader<RallyMilestone>,
+    rules: Res<MatchRules>,
+    settings: Res<PongSettings>,
+    mut rng: ResMut<GameRng>,
+    mut ball_query: Query<
+        (&mut Transform, &mut Velocity, &mut BounceCount, &mut RallyTime, &mut LastHit, &mut InPlay),
+        With<Ball>,
+    >,
+) {
+    for _ in milestones.read() {
+        let in_play = ball_query.iter().filter(|(.., in_play)| in_play.0).count() as u32;
+        if !rules.multi_ball.milestone_balls || in_play == 0 || in_play >= rules.multi_ball.max_balls {
+            continue;
+        }
+        if let Some(ball) = ball_query.iter_mut().find(|(.., in_play)| !in_play.0) {
+            launch_from_center(ball, settings.ball_speed, &mut rng);
+        }
+    }
+}
+
+/// Sends a waiting ball off from the middle of the field in a direction drawn from the GameRng
+///
+/// No paddle has touched the ball yet, so it starts without a last hit.
+fn launch_from_center(
+    (mut transform, mut velocity, mut bounce_count, mut rally_time, mut last_hit, mut in_play): (
+        Mut<Transform>,
+        Mut<Velocity>,
+        Mut<BounceCount>,
+        Mut<RallyTime>,
+        Mut<LastHit>,
+        Mut<InPlay>,
+    ),
+    speed: f32,
+    rng: &mut GameRng,
+) {
+    let mut direction = || if rng.0.random_bool(0.5) { 1.0 } else { -1.0 };
+    transform.translation = Vec3::ZERO;
+    *velocity = Velocity { x: speed * direction(), y: speed * direction() };
+    bounce_count.0 = 0;
+    rally_time.0 = 0.0;
+    last_hit.0 = None;
+    in_play.0 = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AiPlayers;
+    use crate::headless::Simulation;
+    use crate::scoreboard::ScoreEvent;
+    use crate::simulation::SimulationSettings;
+
+    #[test]
+    fn multi_ball_rules_are_read_from_args() {
+        let args = ["--balls", "3", "--milestone-balls", "--max-balls", "2", "--rally-end", "every"].map(String::from);
+        let rules = MultiBallRules::from_args(args);
+
+        assert_eq!(
+            rules,
+            MultiBallRules { balls: 3, milestone_balls: true, max_balls: 2, rally_end: RallyEnd::EveryBallScores }
+        );
+        assert_eq!(rules.pool_size(), 3);
+        for balls in ["0", "17", "4294967295"] {
+            let args = ["--balls", balls, "--max-balls", balls].map(String::from);
+            assert_eq!(MultiBallRules::from_args(args), MultiBallRules::default(), "accepted {balls}");
+        }
+        let args = ["--balls", "0", "--rally-end", "sometimes"].map(String::from);
+        assert_eq!(MultiBallRules::from_args(args), MultiBallRules::default());
+        assert_eq!(MultiBallRules::from_args(["--milestone-balls"].map(String::from)).pool_size(), 4);
+    }
+
+    /// Points scored in the first rally of a three ball match between players who never move
+    fn first_rally(rally_end: RallyEnd) -> Vec<ScoreEvent> {
+        let rules = MatchRules {
+            multi_ball: MultiBallRules { balls: 3, rally_end, ..default() },
+            ..default()
+        };
+        let mut simulation = Simulation::new(SimulationSettings::default(), AiPlayers::default(), rules);
+        simulation.start_match();
+        simulation.skip_countdown();
+
+        let mut ticks = 0;
+        while simulation.state() == GameState::Playing && ticks < 120 * 60 {
+            simulation.tick();
+            ticks += 1;
+        }
+        assert_eq!(simulation.state(), GameState::Countdown);
+        simulation.scores.clone()
+    }
+
+    #[test]
+    fn rally_ends_once_every_ball_is_out() {
+        assert_eq!(first_rally(RallyEnd::LastBallOut).len(), 1);
+        assert_eq!(first_rally(RallyEnd::EveryBallScores).len(), 3);
+    }
+}
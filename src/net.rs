@@ -0,0 +1,350 @@
+/// Module for the network transports and messages used by online play
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::paddle::PaddleInput;
+use crate::scoreboard::MatchRules;
+
+/// Frames of input delay used when `--input-delay` is not given
+pub const DEFAULT_INPUT_DELAY: u32 = 2;
+/// Largest datagram read from the socket, big enough for a Welcome carrying the match rules
+const MAX_PACKET_SIZE: usize = 8192;
+
+/// Sends and receives unreliable datagrams to and from the other player
+///
+/// Packets may be lost, duplicated or arrive out of order; the rollback session
+/// copes with all three.
+pub trait Transport: Send + Sync + 'static {
+    /// Sends a packet to the other player, dropping it if they are not known yet
+    fn send(&mut self, packet: &[u8]);
+    /// Returns every packet that has arrived since the last call
+    fn receive(&mut self) -> Vec<Vec<u8>>;
+}
+
+/// Transport over a non-blocking UDP socket
+pub struct UdpTransport {
+    /// Socket bound to the local port
+    socket: UdpSocket,
+    /// Address of the other player, learnt from their first Hello when hosting
+    peer: Option<SocketAddr>,
+}
+
+impl UdpTransport {
+    /// Listens on `port` for a player to join
+    pub fn host(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpTransport { socket, peer: None })
+    }
+
+    /// Connects to a host at `address`, such as 192.168.1.20:7777
+    pub fn join(address: &str) -> io::Result<Self> {
+        let peer: SocketAddr = address
+            .parse()
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, format!("invalid address {address}")))?;
+        UdpTransport::connect(peer)
+    }
+
+    /// Talks to whoever is listening at `peer` from a free local port
+    pub fn connect(peer: SocketAddr) -> io::Result<Self> {
+        let local: SocketAddr = if peer.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().unwrap();
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpTransport { socket, peer: Some(peer) })
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) {
+        if let Some(peer) = self.peer {
+            // Losing a packet is expected, the next one repeats everything unacknowledged
+            let _ = self.socket.send_to(packet, peer);
+        }
+    }
+
+    fn receive(&mut self) -> Vec<Vec<u8>> {
+        let mut packets = Vec::new();
+        let mut buffer = [0; MAX_PACKET_SIZE];
+
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((length, sender)) => {
+                    let packet = &buffer[..length];
+                    // The first player to say hello to a host becomes its opponent
+                    if self.peer.is_none() && matches!(NetMessage::decode(packet), Some(NetMessage::Hello)) {
+                        self.peer = Some(sender);
+                    }
+                    if self.peer == Some(sender) {
+                        packets.push(packet.to_vec());
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // ICMP errors from a peer that is not listening yet surface here on some platforms
+                Err(_) => break,
+            }
+        }
+
+        packets
+    }
+}
+
+/// Latency and packet loss applied by a FakeTransport
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinkConditions {
+    /// Number of receive calls a packet waits before it can be read
+    pub latency: u32,
+    /// Chance of each packet being dropped, from 0 to 1
+    pub loss: f64,
+    /// Seed for choosing which packets are dropped
+    pub seed: u64,
+}
+
+/// Packets travelling in one direction between two FakeTransports
+#[derive(Default)]
+struct Link {
+    /// Packets in flight with the receive call at which they arrive
+    in_flight: VecDeque<(u64, Vec<u8>)>,
+    /// Number of times the receiving end has called receive
+    receiver_clock: u64,
+}
+
+/// In-process transport for tests, with injected latency and packet loss
+pub struct FakeTransport {
+    /// Packets on their way to the other end
+    outgoing: Arc<Mutex<Link>>,
+    /// Packets on their way to this end
+    incoming: Arc<Mutex<Link>>,
+    /// Conditions applied to packets sent from this end
+    conditions: LinkConditions,
+    /// Source of packet loss
+    rng: StdRng,
+}
+
+impl FakeTransport {
+    /// Creates two connected ends, each sending under the same conditions
+    pub fn pair(conditions: LinkConditions) -> (Self, Self) {
+        let first = Arc::new(Mutex::new(Link::default()));
+        let second = Arc::new(Mutex::new(Link::default()));
+
+        (
+            FakeTransport {
+                outgoing: first.clone(),
+                incoming: second.clone(),
+                conditions,
+                rng: StdRng::seed_from_u64(conditions.seed),
+            },
+            FakeTransport {
+                outgoing: second,
+                incoming: first,
+                conditions,
+                rng: StdRng::seed_from_u64(conditions.seed.wrapping_add(1)),
+            },
+        )
+    }
+}
+
+impl Transport for FakeTransport {
+    fn send(&mut self, packet: &[u8]) {
+        if self.rng.random_bool(self.conditions.loss) {
+            return;
+        }
+        let mut link = self.outgoing.lock().unwrap();
+        let arrival = link.receiver_clock + self.conditions.latency as u64;
+        link.in_flight.push_back((arrival, packet.to_vec()));
+    }
+
+    fn receive(&mut self) -> Vec<Vec<u8>> {
+        let mut link = self.incoming.lock().unwrap();
+        link.receiver_clock += 1;
+
+        let mut packets = Vec::new();
+        while link.in_flight.front().is_some_and(|(arrival, _)| *arrival < link.receiver_clock) {
+            packets.push(link.in_flight.pop_front().unwrap().1);
+        }
+        packets
+    }
+}
+
+/// Messages exchanged between the two players
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum NetMessage {
+    /// Sent by the joining player until the host answers
+    Hello,
+    /// The host's answer, fixing the seed and rules of the match
+    Welcome {
+        /// Seed both players simulate the match with
+        seed: u64,
+        /// Rules of the match, boxed as they dwarf every other message
+        rules: Box<MatchRules>,
+    },
+    /// The sender's paddle inputs, starting at `first_frame`
+    Inputs {
+        /// Frame of the first input in `inputs`
+        first_frame: u32,
+        /// One quantized paddle input per frame
+        inputs: Vec<QuantizedInput>,
+        /// Number of the receiver's frames the sender has inputs for
+        ack: u32,
+    },
+}
+
+impl NetMessage {
+    /// Encodes the message for sending
+    pub fn encode(&self) -> Vec<u8> {
+        ron::to_string(self).expect("net messages always serialize").into_bytes()
+    }
+
+    /// Decodes a received packet, returning None for anything malformed
+    pub fn decode(packet: &[u8]) -> Option<Self> {
+        ron::de::from_bytes(packet).ok()
+    }
+}
+
+/// Whether this player hosts the match or joins one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetRole {
+    /// Listen on a port and play the left paddle
+    Host(u16),
+    /// Connect to a host's address and play the right paddle
+    Join(String),
+}
+
+/// Command line options for online play
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetConfig {
+    /// Whether to host or join
+    pub role: NetRole,
+    /// Frames between pressing a key and the paddle responding
+    pub input_delay: u32,
+}
+
+impl NetConfig {
+    /// Reads the online options from command line arguments
+    ///
+    /// Returns None unless `--host <port>` or `--join <address>` is given.
+    /// `--input-delay <frames>` trades responsiveness for fewer rollbacks.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut args = args.into_iter();
+        let mut role = None;
+        let mut input_delay = DEFAULT_INPUT_DELAY;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--host" => {
+                    if let Some(port) = args.next().and_then(|value| value.parse().ok()) {
+                        role = Some(NetRole::Host(port));
+                    }
+                }
+                "--join" => {
+                    if let Some(address) = args.next() {
+                        role = Some(NetRole::Join(address));
+                    }
+                }
+                "--input-delay" => {
+                    if let Some(frames) = args.next().and_then(|value| value.parse().ok()) {
+                        input_delay = frames;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        role.map(|role| NetConfig { role, input_delay })
+    }
+
+    /// Opens the UDP socket for this role
+    pub fn open_transport(&self) -> io::Result<UdpTransport> {
+        match &self.role {
+            NetRole::Host(port) => UdpTransport::host(*port),
+            NetRole::Join(address) => UdpTransport::join(address),
+        }
+    }
+}
+
+/// Converts a paddle axis to the form sent over the network
+///
+/// Both players simulate with the quantized value, so the local player's own
+/// paddle moves exactly as the other player sees it.
+pub fn quantize_axis(axis: f32) -> i8 {
+    (axis.clamp(-1.0, 1.0) * i8::MAX as f32).round() as i8
+}
+
+/// Converts a quantized paddle axis back to the -1 to 1 range
+pub fn dequantize_axis(value: i8) -> f32 {
+    (value as f32 / i8::MAX as f32).max(-1.0)
+}
+
+/// A PaddleInput in the compact form sent over the network and stored in replays
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuantizedInput {
+    /// The paddle axis, as given by quantize_axis
+    pub axis: i8,
+    /// The paddle's forward and back axis, as given by quantize_axis
+    #[serde(default)]
+    pub depth: i8,
+    /// Whether the player is asking to serve
+    pub serve: bool,
+}
+
+impl QuantizedInput {
+    /// Quantizes a paddle's input
+    pub fn new(input: &PaddleInput) -> Self {
+        QuantizedInput {
+            axis: quantize_axis(input.axis),
+            depth: quantize_axis(input.depth),
+            serve: input.serve,
+        }
+    }
+
+    /// The paddle input this stands for
+    pub fn to_paddle_input(self) -> PaddleInput {
+        PaddleInput {
+            axis: dequantize_axis(self.axis),
+            depth: dequantize_axis(self.depth),
+            serve: self.serve,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_are_parsed_from_args() {
+        let args = ["--join", "127.0.0.1:7777", "--input-delay", "4"].map(String::from);
+        let config = NetConfig::from_args(args).unwrap();
+
+        assert_eq!(config.role, NetRole::Join("127.0.0.1:7777".to_string()));
+        assert_eq!(config.input_delay, 4);
+        assert_eq!(NetConfig::from_args(["--input-delay", "4"].map(String::from)), None);
+    }
+
+    #[test]
+    fn fake_transport_delays_packets() {
+        let (mut left, mut right) = FakeTransport::pair(LinkConditions { latency: 2, ..LinkConditions::default() });
+        left.send(b"ping");
+
+        assert!(right.receive().is_empty());
+        assert!(right.receive().is_empty());
+        assert_eq!(right.receive(), vec![b"ping".to_vec()]);
+    }
+
+    #[test]
+    fn messages_survive_a_round_trip() {
+        let inputs = vec![
+            QuantizedInput { axis: -127, depth: 0, serve: false },
+            QuantizedInput { axis: 0, depth: 127, serve: true },
+            QuantizedInput { axis: 127, depth: -64, serve: false },
+        ];
+        let message = NetMessage::Inputs { first_frame: 7, inputs: inputs.clone(), ack: 3 };
+        let decoded = NetMessage::decode(&message.encode()).unwrap();
+
+        assert!(matches!(decoded, NetMessage::Inputs { first_frame: 7, ack: 3, inputs: ref decoded } if decoded == &inputs));
+        assert_eq!(dequantize_axis(quantize_axis(-1.0)), -1.0);
+    }
+}
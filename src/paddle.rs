@@ -1,110 +1,295 @@
 /// Module for handling the paddles that players control to hit the ball
 use bevy::prelude::*;
-use crate::window::{HEIGHT, WIDTH};
+use serde::{Deserialize, Serialize};
+use crate::ai::AiController;
+use crate::ball::Velocity;
+use crate::game::PongSet;
+use crate::input::{ActionState, InputAction};
+use crate::powerup::PowerUpState;
+use crate::scoreboard::{MatchRules, ScoreBoard};
+use crate::settings::PongSettings;
+use crate::simulation::Interpolated;
+use crate::state::InGame;
 
-/// Height of the paddle sprite in pixels
+/// Default height of the paddle sprite in pixels
 pub const PADDLE_HEIGHT: f32 = 28.0;
-/// Width of the paddle sprite in pixels
+/// Default width of the paddle sprite in pixels
 pub const PADDLE_WIDTH: f32 = 9.0;
-/// Movement speed of the paddle in pixels per second
+/// Default movement speed of the paddle in pixels per second
 pub const PADDLE_SPEED: f32 = 500.0;
-/// Distance from the edge of the screen in pixels
+/// Default distance from the edge of the screen in pixels
 pub const PADDLE_OFFSET: f32 = 40.0;
+/// Default distance of a forward paddle from the edge of the screen in pixels
+pub const FORWARD_PADDLE_OFFSET: f32 = 180.0;
 
-/// Component that marks an entity as the left paddle
-#[derive(Component)]
-pub struct LeftPaddle;
+/// Identifies a player by the side of the field they defend
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    /// The player on the left
+    Left,
+    /// The player on the right
+    Right,
+    /// The player along the top, in four-player matches
+    Top,
+    /// The player along the bottom, in four-player matches
+    Bottom,
+}
+
+impl Side {
+    /// Every side, the two of a two-player match first
+    pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Top, Side::Bottom];
+
+    /// The player across the field
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+            Side::Top => Side::Bottom,
+            Side::Bottom => Side::Top,
+        }
+    }
+
+    /// Position of the side in Side::ALL
+    pub fn index(self) -> usize {
+        self as usize
+    }
 
-/// Component that marks an entity as the right paddle
-#[derive(Component)]
-pub struct RightPaddle;
+    /// Whether the paddle on this side lies flat along the top or bottom of the field
+    pub fn horizontal(self) -> bool {
+        matches!(self, Side::Top | Side::Bottom)
+    }
+
+    /// Direction from this side's edge of the field towards the middle
+    pub fn inward(self) -> Vec2 {
+        match self {
+            Side::Left => Vec2::X,
+            Side::Right => Vec2::NEG_X,
+            Side::Top => Vec2::NEG_Y,
+            Side::Bottom => Vec2::Y,
+        }
+    }
 
-/// Spawns the left paddle at the starting position
+    /// Swaps the axes of `value` for the top and bottom sides
+    ///
+    /// Turns a size or position laid out for a left or right paddle into the same
+    /// thing for this side, and back again, so the width of a paddle is always
+    /// `orient(size).x` and its movement runs along `orient(position).y`.
+    pub fn orient(self, value: Vec2) -> Vec2 {
+        if self.horizontal() { value.yx() } else { value }
+    }
+
+    /// Which of `sides` a ball moving with `velocity` is heading towards
+    pub fn toward(velocity: Vec2, sides: impl IntoIterator<Item = Side>) -> Option<Side> {
+        sides.into_iter().max_by(|a, b| (-a.inward().dot(velocity)).total_cmp(&-b.inward().dot(velocity)))
+    }
+}
+
+/// Which of a side's paddles a paddle is
 ///
-/// Creates a white rectangular sprite with the specified dimensions
-/// positioned near the left edge of the screen. Marks it with the
-/// LeftPaddle component to identify it for movement and collision systems.
-pub fn spawn_left_paddle(mut commands: Commands) {
-    let half_width = WIDTH as f32 / 2.0;
-    
-    commands.spawn((
-        Sprite {
-            color: Color::WHITE,
-            custom_size: Some(Vec2::new(PADDLE_WIDTH, PADDLE_HEIGHT)),
-            ..default()
-        },
-        Transform {
-            translation: Vec3::new(-half_width + PADDLE_WIDTH + PADDLE_OFFSET, 0.0, 0.0),
-            ..default()
-        },
-        LeftPaddle,
-    ));
+/// Every side has a back paddle near its edge. In a doubles match each side
+/// also has a forward paddle further up the field, played by a second player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum PaddleSlot {
+    /// The paddle near the edge of the field
+    #[default]
+    Back,
+    /// The paddle further up the field in a doubles match
+    Forward,
+}
+
+/// Component for a paddle, holding the side of the field it defends and its place on that side
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Paddle {
+    /// Side the paddle guards
+    pub side: Side,
+    /// Whether this is the side's back or forward paddle
+    pub slot: PaddleSlot,
+}
+
+impl Paddle {
+    /// Every paddle that can have its own player, in the order they are listed on the controls screen
+    pub const PLAYERS: [Paddle; 6] = [
+        Paddle::back(Side::Left),
+        Paddle::back(Side::Right),
+        Paddle::back(Side::Top),
+        Paddle::back(Side::Bottom),
+        Paddle { side: Side::Left, slot: PaddleSlot::Forward },
+        Paddle { side: Side::Right, slot: PaddleSlot::Forward },
+    ];
+
+    /// The back paddle of `side`, the only one outside doubles matches
+    pub const fn back(side: Side) -> Self {
+        Paddle { side, slot: PaddleSlot::Back }
+    }
+
+    /// Name of the paddle's player, such as "Left" or "Left Forward"
+    pub fn name(self) -> String {
+        match self.slot {
+            PaddleSlot::Back => format!("{:?}", self.side),
+            PaddleSlot::Forward => format!("{:?} Forward", self.side),
+        }
+    }
+}
+
+/// Movement requested for a paddle this frame, filled in by player or AI control
+#[derive(Component, Default)]
+pub struct PaddleInput {
+    /// Movement along the paddle's edge as a fraction of the paddle speed, from -1
+    /// (down, or left for the top and bottom paddles) to 1 (up, or right)
+    pub axis: f32,
+    /// Movement away from the paddle's goal as a fraction of the paddle speed, from
+    /// -1 (back towards the goal) to 1 (towards the middle of the field)
+    ///
+    /// Only used when the MatchRules' PaddleMovement gives paddles a zone to move in.
+    pub depth: f32,
+    /// Whether the player is asking to serve the ball
+    pub serve: bool,
 }
 
-/// Spawns the right paddle at the starting position
+/// Plugin spawning the paddles and moving them according to their PaddleInput
 ///
-/// Creates a white rectangular sprite with the specified dimensions
-/// positioned near the right edge of the screen. Marks it with the
-/// RightPaddle component to identify it for movement and collision systems.
-pub fn spawn_right_paddle(mut commands: Commands) {
-    let half_width = WIDTH as f32 / 2.0;
-    
-    commands.spawn((
-        Sprite {
-            color: Color::WHITE,
-            custom_size: Some(Vec2::new(PADDLE_WIDTH, PADDLE_HEIGHT)),
-            ..default()
-        },
-        Transform {
-            translation: Vec3::new(half_width - PADDLE_WIDTH - PADDLE_OFFSET, 0.0, 0.0),
-            ..default()
-        },
-        RightPaddle,
-    ));
+/// Paddles are spawned on entering InGame and moved in PongSet::Movement. Filling in
+/// PaddleInput is left to player input or the AI.
+pub struct PaddlePlugin;
+
+impl Plugin for PaddlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(InGame), spawn_paddles)
+            .add_systems(FixedUpdate, move_paddles.in_set(PongSet::Movement))
+            .add_systems(Update, show_paddles_in_play);
+    }
 }
 
-/// Handles movement of the left paddle using W and S keys
+/// Spawns every paddle taking part in the match
 ///
-/// Updates the left paddle's position based on keyboard input.
-/// W key moves the paddle up, S key moves it down.
-/// Prevents the paddle from moving beyond the screen boundaries.
-pub fn move_left_paddle(
-    mut query: Query<&mut Transform, With<LeftPaddle>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
+/// Creates a rectangular sprite with the size and color from PongSettings at
+/// the paddle's starting position, turned to lie flat for the top and bottom
+/// paddles. Marks it with a Paddle component to identify it for movement and
+/// collision systems.
+pub fn spawn_paddles(mut commands: Commands, settings: Res<PongSettings>, rules: Res<MatchRules>) {
+    for paddle in rules.paddles() {
+        let side = paddle.side;
+        let translation = settings.paddle_position(paddle).extend(0.0);
+
+        commands.spawn((
+            Sprite {
+                color: settings.paddle_color,
+                custom_size: Some(side.orient(settings.paddle_size)),
+                ..default()
+            },
+            Transform {
+                translation,
+                ..default()
+            },
+            Interpolated::at(translation),
+            paddle,
+            Velocity { x: 0.0, y: 0.0 },
+            PaddleInput::default(),
+            StateScoped(InGame),
+        ));
+    }
+}
+
+/// The entity of `paddle`, if it is on the field
+pub fn find_paddle(world: &mut World, paddle: Paddle) -> Option<Entity> {
+    world
+        .query::<(Entity, &Paddle)>()
+        .iter(world)
+        .find_map(|(entity, found)| (*found == paddle).then_some(entity))
+}
+
+/// Hides the paddles of players who have run out of lives
+pub fn show_paddles_in_play(
+    scoreboard: Res<ScoreBoard>,
+    rules: Res<MatchRules>,
+    mut paddle_query: Query<(&Paddle, &mut Visibility)>,
 ) {
-    let paddle_boundary = (HEIGHT as f32 - PADDLE_HEIGHT) / 2.0;
-    
-    let mut transform = query.single_mut();
-    let move_amount = PADDLE_SPEED * time.delta_secs();
-    
-    if keyboard.pressed(KeyCode::KeyW) {
-        transform.translation.y = (transform.translation.y + move_amount).min(paddle_boundary);
+    if !scoreboard.is_changed() {
+        return;
     }
-    if keyboard.pressed(KeyCode::KeyS) {
-        transform.translation.y = (transform.translation.y - move_amount).max(-paddle_boundary);
+    for (paddle, mut visibility) in paddle_query.iter_mut() {
+        let shown = if scoreboard.in_play(&rules, paddle.side) { Visibility::Inherited } else { Visibility::Hidden };
+        visibility.set_if_neq(shown);
     }
 }
 
-/// Handles movement of the right paddle using arrow keys
+/// Handles input for the paddles of the players at this computer
 ///
-/// Updates the right paddle's position based on keyboard input.
-/// Up arrow key moves the paddle up, Down arrow key moves it down.
-/// Prevents the paddle from moving beyond the screen boundaries.
-pub fn move_right_paddle(
-    mut query: Query<&mut Transform, With<RightPaddle>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
+/// Sets each paddle's PaddleInput from its player's PaddleUp, PaddleDown,
+/// PaddleForward, PaddleBack and Serve actions, swapping the directions while a
+/// Reverse power-up acts on their side. Paddles driven by the computer are left alone.
+pub fn move_player_paddles(
+    mut query: Query<(&Paddle, &mut PaddleInput), Without<AiController>>,
+    actions: Res<ActionState>,
+    power_ups: Res<PowerUpState>,
 ) {
-    let paddle_boundary = (HEIGHT as f32 - PADDLE_HEIGHT) / 2.0;
-    
-    let mut transform = query.single_mut();
-    let move_amount = PADDLE_SPEED * time.delta_secs();
-    
-    if keyboard.pressed(KeyCode::ArrowUp) {
-        transform.translation.y = (transform.translation.y + move_amount).min(paddle_boundary);
+    for (paddle, mut input) in query.iter_mut() {
+        let actions = actions.player(*paddle);
+        let direction = control_direction(&power_ups, paddle.side);
+        input.axis = actions.paddle_axis() * direction;
+        input.depth = actions.depth_axis() * direction;
+        input.serve = actions.pressed(InputAction::Serve);
     }
-    if keyboard.pressed(KeyCode::ArrowDown) {
-        transform.translation.y = (transform.translation.y - move_amount).max(-paddle_boundary);
+}
+
+/// Multiplier for the paddle axis of the player on `side`, -1 while their controls are reversed
+pub fn control_direction(power_ups: &PowerUpState, side: Side) -> f32 {
+    if power_ups.reversed(side) { -1.0 } else { 1.0 }
+}
+
+/// Moves every paddle according to its PaddleInput
+///
+/// Pushes each paddle along its edge of the field, and towards or away from the
+/// middle when the MatchRules' PaddleMovement gives it a zone, at up to the
+/// paddle speed. The paddle's Velocity carries over between ticks, so with an
+/// acceleration it picks up speed and slides to a stop as PaddleMovement says.
+/// The paddle is kept within the screen boundaries and its zone, which ends
+/// before the middle of the field and short of the arena's obstacles and
+/// portals, and the velocity it actually moved with is recorded so the ball
+/// can pick up spin from it. A paddle that runs into a boundary stops there.
+pub fn move_paddles(
+    mut query: Query<(&mut Transform, &mut Velocity, &PaddleInput, &Sprite, &Paddle)>,
+    settings: Res<PongSettings>,
+    rules: Res<MatchRules>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+    let movement = &rules.movement;
+
+    for (mut transform, mut velocity, input, sprite, paddle) in query.iter_mut() {
+        let side = paddle.side;
+        // Power-ups can make a paddle longer or shorter than the settings say
+        let length = sprite.custom_size.map_or(settings.paddle_size.y, |size| side.orient(size).y);
+        let paddle_boundary = ((side.orient(settings.field_size).y - length) / 2.0).max(0.0);
+        let inward = side.orient(side.inward()).x;
+        let start = side.orient(transform.translation.truncate());
+        let speed = side.orient(Vec2::new(velocity.x, velocity.y));
+
+        let depth = if movement.zone_depth.is_some() { input.depth.clamp(-1.0, 1.0) } else { 0.0 };
+        let wanted = (Vec2::new(depth * inward, input.axis.clamp(-1.0, 1.0)) * settings.paddle_speed)
+            .clamp_length_max(settings.paddle_speed);
+        let speed = Vec2::new(
+            movement.step_speed(speed.x, wanted.x, delta),
+            movement.step_speed(speed.y, wanted.y, delta),
+        );
+
+        let mut position = start + speed * delta;
+        position.y = position.y.clamp(-paddle_boundary, paddle_boundary);
+        position.x = match movement.zone_depth {
+            Some(zone_depth) => {
+                let line = side.orient(settings.paddle_position(*paddle)).x;
+                // Stay in your own half and out of the way of the arena
+                let own_half = -inward * line - settings.paddle_size.x / 2.0;
+                let depth = zone_depth.min(own_half).min(rules.arena.room_in_front(&settings, *paddle)).max(0.0);
+                let front = line + inward * depth;
+                position.x.clamp(line.min(front), line.max(front))
+            }
+            None => start.x,
+        };
+        transform.translation = side.orient(position).extend(transform.translation.z);
+
+        // Remember how fast the paddle actually moved so the ball can pick up spin from it
+        let moved = if delta > 0.0 { side.orient((position - start) / delta) } else { Vec2::ZERO };
+        *velocity = Velocity { x: moved.x, y: moved.y };
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,729 @@
+/// Module for the power-ups that appear on the field and are claimed by sending the ball through them
+use std::fs;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::ball::{self, Ball, BounceCount, Held, InPlay, LastHit, RallyTime, Velocity};
+use crate::game::PongSet;
+use crate::multiball;
+use crate::paddle::{self, Paddle, Side};
+use crate::remote::RemoteSession;
+use crate::scoreboard::{MatchRules, ScoreBoard};
+use crate::settings::PongSettings;
+use crate::simulation::GameRng;
+use crate::state::{GameState, InGame};
+
+/// Width and height of a power-up on the field in pixels
+pub const POWER_UP_SIZE: f32 = 16.0;
+/// Paddle height multiplier for every stack of Grow
+pub const GROW_FACTOR: f32 = 1.5;
+/// Paddle height multiplier for every stack of Shrink
+pub const SHRINK_FACTOR: f32 = 0.6;
+/// Ball speed multiplier for every stack of SpeedBurst
+pub const BURST_FACTOR: f32 = 1.4;
+/// Extra balls kept ready for split balls when they can appear
+const SPLIT_BALLS: u32 = 2;
+
+/// The kinds of power-up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    /// Makes the claiming player's paddle taller
+    Grow,
+    /// Makes the other player's paddle shorter
+    Shrink,
+    /// Speeds up balls heading towards the other player
+    SpeedBurst,
+    /// Splits the ball in two straight away
+    SplitBall,
+    /// The claiming player's paddle catches the ball and holds it until they serve it
+    Sticky,
+    /// Swaps the other player's up and down controls
+    Reverse,
+    /// Puts a wall behind the claiming player's goal that sends balls back
+    Shield,
+}
+
+impl PowerUpKind {
+    /// Every kind of power-up
+    pub const ALL: [PowerUpKind; 7] = [
+        PowerUpKind::Grow,
+        PowerUpKind::Shrink,
+        PowerUpKind::SpeedBurst,
+        PowerUpKind::SplitBall,
+        PowerUpKind::Sticky,
+        PowerUpKind::Reverse,
+        PowerUpKind::Shield,
+    ];
+
+    /// Parses a kind from its name on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        PowerUpKind::ALL.into_iter().find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Name of the kind on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUpKind::Grow => "grow",
+            PowerUpKind::Shrink => "shrink",
+            PowerUpKind::SpeedBurst => "burst",
+            PowerUpKind::SplitBall => "split",
+            PowerUpKind::Sticky => "sticky",
+            PowerUpKind::Reverse => "reverse",
+            PowerUpKind::Shield => "shield",
+        }
+    }
+
+    /// Color the power-up is drawn in
+    pub fn color(self) -> Color {
+        match self {
+            PowerUpKind::Grow => Color::srgb(0.3, 0.9, 0.3),
+            PowerUpKind::Shrink => Color::srgb(0.9, 0.3, 0.3),
+            PowerUpKind::SpeedBurst => Color::srgb(1.0, 0.6, 0.1),
+            PowerUpKind::SplitBall => Color::srgb(0.9, 0.9, 0.9),
+            PowerUpKind::Sticky => Color::srgb(0.9, 0.3, 0.9),
+            PowerUpKind::Reverse => Color::srgb(0.6, 0.3, 0.9),
+            PowerUpKind::Shield => Color::srgb(0.3, 0.6, 1.0),
+        }
+    }
+
+    /// The player an effect claimed by `claimer` acts on
+    pub fn target(self, claimer: Side) -> Side {
+        match self {
+            PowerUpKind::Grow | PowerUpKind::SplitBall | PowerUpKind::Sticky | PowerUpKind::Shield => claimer,
+            PowerUpKind::Shrink | PowerUpKind::SpeedBurst | PowerUpKind::Reverse => claimer.opposite(),
+        }
+    }
+}
+
+/// What happens when a player claims an effect they already have running
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stacking {
+    /// The effect starts its full duration again
+    Refresh,
+    /// The duration is added on to the time left
+    Extend,
+    /// The effect grows stronger, up to `max` times over, and starts its full duration again
+    Stack {
+        /// Most times the effect can apply at once
+        max: u32,
+    },
+}
+
+/// How one kind of power-up behaves
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpConfig {
+    /// The kind of power-up
+    pub kind: PowerUpKind,
+    /// Chance of this kind being picked, relative to the other weights
+    pub weight: u32,
+    /// Seconds of play the effect lasts, ignored by SplitBall which happens at once
+    pub duration: f32,
+    /// What claiming the effect again does
+    pub stacking: Stacking,
+}
+
+/// Rules for power-ups
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerUpRules {
+    /// Whether power-ups appear at all
+    pub enabled: bool,
+    /// Seconds of play between power-ups appearing
+    pub spawn_interval: f32,
+    /// Most power-ups waiting on the field at once
+    pub max_on_field: u32,
+    /// Seconds a power-up waits on the field before it disappears
+    pub lifetime: f32,
+    /// Weight, duration and stacking of every kind
+    pub kinds: Vec<PowerUpConfig>,
+}
+
+impl Default for PowerUpRules {
+    fn default() -> Self {
+        let config = |kind, weight, duration, stacking| PowerUpConfig { kind, weight, duration, stacking };
+        PowerUpRules {
+            enabled: false,
+            spawn_interval: 8.0,
+            max_on_field: 2,
+            lifetime: 10.0,
+            kinds: vec![
+                config(PowerUpKind::Grow, 3, 10.0, Stacking::Stack { max: 2 }),
+                config(PowerUpKind::Shrink, 3, 10.0, Stacking::Stack { max: 2 }),
+                config(PowerUpKind::SpeedBurst, 2, 5.0, Stacking::Refresh),
+                config(PowerUpKind::SplitBall, 2, 0.0, Stacking::Refresh),
+                config(PowerUpKind::Sticky, 2, 8.0, Stacking::Extend),
+                config(PowerUpKind::Reverse, 1, 5.0, Stacking::Refresh),
+                config(PowerUpKind::Shield, 1, 6.0, Stacking::Extend),
+            ],
+        }
+    }
+}
+
+impl PowerUpRules {
+    /// Reads the power-up rules from command line arguments
+    ///
+    /// `--power-ups` turns power-ups on with the default rules, and
+    /// `--power-up-rules <path>` loads a RON file of PowerUpRules instead.
+    /// `--power-up-interval <seconds>` and `--power-up-weights <kind>=<weight>,...`
+    /// then change how often they appear and which kinds are picked, where the kinds
+    /// are grow, shrink, burst, split, sticky, reverse and shield. Values that fail
+    /// to parse and rules files that fail validate are reported and ignored.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut rules = PowerUpRules::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let value = match arg.as_str() {
+                "--power-ups" => {
+                    rules.enabled = true;
+                    continue;
+                }
+                "--power-up-rules" | "--power-up-interval" | "--power-up-weights" => args.next().unwrap_or_default(),
+                _ => continue,
+            };
+            let parsed = match arg.as_str() {
+                "--power-up-rules" => match fs::read_to_string(&value)
+                    .map_err(|err| err.to_string())
+                    .and_then(|text| ron::from_str::<PowerUpRules>(&text).map_err(|err| err.to_string()))
+                    .and_then(|loaded| loaded.validate().map(|()| loaded))
+                {
+                    Ok(loaded) => {
+                        rules = PowerUpRules { enabled: true, ..loaded };
+                        true
+                    }
+                    Err(err) => {
+                        warn!("Could not load power-up rules from '{}': {}", value, err);
+                        continue;
+                    }
+                },
+                "--power-up-interval" => value
+                    .parse()
+                    .ok()
+                    .filter(|&seconds: &f32| seconds > 0.0)
+                    .map(|seconds| rules.spawn_interval = seconds)
+                    .is_some(),
+                _ => rules.set_weights(&value),
+            };
+            if !parsed {
+                warn!("Invalid value '{}' for {}", value, arg);
+            }
+        }
+
+        rules
+    }
+
+    /// Checks that power-ups appear, last and stack sensibly
+    ///
+    /// The spawn interval and lifetime have to be finite and above zero, every
+    /// duration finite and not negative, and stacking effects able to apply at
+    /// least once.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.spawn_interval.is_finite() && self.spawn_interval > 0.0) {
+            return Err("spawn_interval has to be above zero".to_string());
+        }
+        if !(self.lifetime.is_finite() && self.lifetime > 0.0) {
+            return Err("lifetime has to be above zero".to_string());
+        }
+        for config in &self.kinds {
+            if !(config.duration.is_finite() && config.duration >= 0.0) {
+                return Err(format!("the duration of {} can't be negative", config.kind.name()));
+            }
+            if config.stacking == (Stacking::Stack { max: 0 }) {
+                return Err(format!("{} has to stack at least once", config.kind.name()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets weights from a list like `grow=3,split=0`, returning false and changing
+    /// nothing if any entry is invalid
+    fn set_weights(&mut self, list: &str) -> bool {
+        let weights: Option<Vec<(PowerUpKind, u32)>> = list
+            .split(',')
+            .map(|entry| {
+                let (name, weight) = entry.split_once('=')?;
+                Some((PowerUpKind::from_name(name.trim())?, weight.trim().parse().ok()?))
+            })
+            .collect();
+        let Some(weights) = weights else {
+            return false;
+        };
+
+        for (kind, weight) in weights {
+            match self.kinds.iter_mut().find(|config| config.kind == kind) {
+                Some(config) => config.weight = weight,
+                None => self.kinds.push(PowerUpConfig { weight, ..*PowerUpRules::default().config(kind).unwrap() }),
+            }
+        }
+        true
+    }
+
+    /// How `kind` behaves, if it can appear at all
+    pub fn config(&self, kind: PowerUpKind) -> Option<&PowerUpConfig> {
+        self.kinds.iter().find(|config| config.kind == kind)
+    }
+
+    /// Balls a match needs on top of its multi-ball ones for split balls
+    pub fn extra_balls(&self) -> u32 {
+        let splits = self.config(PowerUpKind::SplitBall).is_some_and(|config| config.weight > 0);
+        if self.enabled && splits { SPLIT_BALLS } else { 0 }
+    }
+
+    /// Picks a kind of power-up by weight, or None if every weight is zero
+    pub fn choose(&self, rng: &mut impl Rng) -> Option<PowerUpKind> {
+        // Summed wider than the weights so large ones can't overflow
+        let total: u64 = self.kinds.iter().map(|config| config.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.random_range(0..total);
+        self.kinds.iter().find_map(|config| {
+            if pick < config.weight as u64 {
+                Some(config.kind)
+            } else {
+                pick -= config.weight as u64;
+                None
+            }
+        })
+    }
+}
+
+/// A power-up waiting on the field to be claimed
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldPowerUp {
+    /// Number telling this power-up apart from the others in the match
+    pub id: u32,
+    /// The kind of power-up
+    pub kind: PowerUpKind,
+    /// Center of the power-up
+    pub position: Vec2,
+    /// Seconds left before it disappears
+    pub remaining: f32,
+}
+
+/// An effect a player has claimed that is still running
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActiveEffect {
+    /// The kind of power-up the effect came from
+    pub kind: PowerUpKind,
+    /// Player who claimed it
+    pub claimer: Side,
+    /// Seconds of play left
+    pub remaining: f32,
+    /// Times over the effect applies
+    pub stacks: u32,
+}
+
+/// Every power-up on the field and every effect running, as part of the simulation
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PowerUpState {
+    /// Power-ups waiting on the field
+    pub field: Vec<FieldPowerUp>,
+    /// Effects running
+    pub effects: Vec<ActiveEffect>,
+    /// Seconds of play before the next power-up appears
+    pub next_spawn: f32,
+    /// Id for the next power-up to appear
+    pub next_id: u32,
+}
+
+impl PowerUpState {
+    /// Starts the effect described by `config` for `claimer`, following its stacking rule
+    pub fn apply(&mut self, config: &PowerUpConfig, claimer: Side) {
+        let running = self
+            .effects
+            .iter_mut()
+            .find(|effect| effect.kind == config.kind && effect.claimer == claimer);
+        let Some(effect) = running else {
+            self.effects.push(ActiveEffect { kind: config.kind, claimer, remaining: config.duration, stacks: 1 });
+            return;
+        };
+        match config.stacking {
+            Stacking::Refresh => effect.remaining = config.duration,
+            Stacking::Extend => effect.remaining += config.duration,
+            Stacking::Stack { max } => {
+                effect.stacks = (effect.stacks + 1).min(max.max(1));
+                effect.remaining = config.duration;
+            }
+        }
+    }
+
+    /// Times over effects of `kind` act on `side`
+    pub fn stacks(&self, kind: PowerUpKind, side: Side) -> u32 {
+        self.effects
+            .iter()
+            .filter(|effect| effect.kind == kind && kind.target(effect.claimer) == side)
+            .map(|effect| effect.stacks)
+            .sum()
+    }
+
+    /// Height multiplier of the paddle on `side`
+    ///
+    /// Grow and Shrink multiply together, so one of each roughly cancels out.
+    pub fn paddle_scale(&self, side: Side) -> f32 {
+        GROW_FACTOR.powi(self.stacks(PowerUpKind::Grow, side) as i32)
+            * SHRINK_FACTOR.powi(self.stacks(PowerUpKind::Shrink, side) as i32)
+    }
+
+    /// Speed multiplier of balls heading towards `side`
+    pub fn burst(&self, side: Side) -> f32 {
+        BURST_FACTOR.powi(self.stacks(PowerUpKind::SpeedBurst, side) as i32)
+    }
+
+    /// Whether the paddle on `side` catches the ball
+    pub fn sticky(&self, side: Side) -> bool {
+        self.stacks(PowerUpKind::Sticky, side) > 0
+    }
+
+    /// Whether the player on `side` has their controls swapped
+    pub fn reversed(&self, side: Side) -> bool {
+        self.stacks(PowerUpKind::Reverse, side) > 0
+    }
+
+    /// Whether a shield stands behind the goal on `side`
+    pub fn shielded(&self, side: Side) -> bool {
+        self.stacks(PowerUpKind::Shield, side) > 0
+    }
+}
+
+/// Sent when a player claims a power-up
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct PowerUpClaimed {
+    /// The kind of power-up
+    pub kind: PowerUpKind,
+    /// Player who claimed it
+    pub claimer: Side,
+}
+
+/// Component for the sprite showing a power-up on the field
+#[derive(Component)]
+pub struct PowerUpSprite(pub u32);
+
+/// Plugin for power-ups on the field
+///
+/// When the MatchRules' PowerUpRules turn them on, power-ups appear in the middle
+/// of the field every so often during play. A ball passing through one gives it
+/// to the player who sent the ball that way, which is the player who last hit it.
+/// Effects count down only while the ball is in play. Everything is kept in the
+/// PowerUpState resource so online matches and replays can save and restore it.
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerUpState>()
+            .add_event::<PowerUpClaimed>()
+            .add_systems(OnEnter(InGame), reset_power_ups)
+            .add_systems(OnTransition { exited: GameState::GameOver, entered: GameState::Countdown }, reset_power_ups)
+            .add_systems(FixedUpdate, size_paddles.in_set(PongSet::Movement).before(paddle::move_paddles))
+            .add_systems(FixedUpdate, tick_power_ups.in_set(PongSet::Physics).before(ball::update_ball))
+            .add_systems(
+                FixedUpdate,
+                claim_power_ups
+                    .in_set(PongSet::Physics)
+                    .after(ball::update_ball)
+                    .after(multiball::add_milestone_ball),
+            )
+            // A remote match isn't simulated, but its paddles still change size
+            .add_systems(Update, size_paddles.run_if(resource_exists::<RemoteSession>))
+            .add_systems(Update, show_power_ups.run_if(in_state(InGame)));
+    }
+}
+
+/// Clears the field and every effect ready for a new match
+pub fn reset_power_ups(mut state: ResMut<PowerUpState>, rules: Res<MatchRules>) {
+    *state = PowerUpState { next_spawn: rules.power_ups.spawn_interval, ..default() };
+}
+
+/// Counts down the running effects and the power-ups on the field, and puts new ones on the field
+pub fn tick_power_ups(
+    mut state: ResMut<PowerUpState>,
+    rules: Res<MatchRules>,
+    settings: Res<PongSettings>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    let delta = time.delta_secs();
+    let four_player = rules.four_player.is_some();
+    let rules = &rules.power_ups;
+
+    for effect in state.effects.iter_mut() {
+        effect.remaining -= delta;
+    }
+    state.effects.retain(|effect| effect.remaining > 0.0);
+    for power_up in state.field.iter_mut() {
+        power_up.remaining -= delta;
+    }
+    state.field.retain(|power_up| power_up.remaining > 0.0);
+
+    if !rules.enabled {
+        return;
+    }
+    state.next_spawn -= delta;
+    if state.next_spawn > 0.0 {
+        return;
+    }
+    state.next_spawn += rules.spawn_interval.max(delta);
+    if state.field.len() >= rules.max_on_field as usize {
+        return;
+    }
+    let Some(kind) = rules.choose(&mut rng.0) else {
+        return;
+    };
+
+    // Keep to the middle half of the field, clear of the paddles
+    let reach_y = if four_player { settings.field_size.y / 4.0 } else { (settings.field_size.y - POWER_UP_SIZE) / 2.0 };
+    let reach = Vec2::new(settings.field_size.x / 4.0, reach_y);
+    let position = Vec2::new(rng.0.random_range(-reach.x..=reach.x), rng.0.random_range(-reach.y..=reach.y));
+    let id = state.next_id;
+    state.next_id += 1;
+    state.field.push(FieldPowerUp { id, kind, position, remaining: rules.lifetime });
+}
+
+/// Gives power-ups to the players whose balls pass through them
+///
+/// A power-up goes to the player whose paddle last hit the ball, so a ball no
+/// paddle has touched yet, or last hit by a player who is out of a four-player
+/// match, passes through without claiming anything. A split ball sends a resting
+/// ball off from the same spot at a mirrored angle, last hit by the same paddle.
+pub fn claim_power_ups(
+    mut state: ResMut<PowerUpState>,
+    rules: Res<MatchRules>,
+    scoreboard: Res<ScoreBoard>,
+    mut ball_query: Query<
+        (&mut Transform, &mut Velocity, &mut BounceCount, &mut RallyTime, &mut LastHit, &mut InPlay, &Held, &Sprite),
+        With<Ball>,
+    >,
+    mut claimed_writer: EventWriter<PowerUpClaimed>,
+) {
+    if state.field.is_empty() {
+        return;
+    }
+
+    let mut splits = Vec::new();
+    for (transform, velocity, bounce_count, rally_time, last_hit, in_play, held, sprite) in ball_query.iter() {
+        if !in_play.0 || held.0.is_some() {
+            continue;
+        }
+        let Some(claimer) = last_hit.0.map(|paddle| paddle.side).filter(|side| scoreboard.in_play(&rules, *side))
+        else {
+            continue;
+        };
+        let reach = (sprite.custom_size.unwrap_or_default() + Vec2::splat(POWER_UP_SIZE)) / 2.0;
+        let ball = transform.translation.truncate();
+
+        while let Some(index) = state.field.iter().position(|power_up| {
+            let gap = (power_up.position - ball).abs();
+            gap.x <= reach.x && gap.y <= reach.y
+        }) {
+            let power_up = state.field.remove(index);
+            claimed_writer.send(PowerUpClaimed { kind: power_up.kind, claimer });
+            if power_up.kind == PowerUpKind::SplitBall {
+                splits.push((transform.translation, *velocity, *bounce_count, *rally_time, *last_hit));
+            } else if let Some(config) = rules.power_ups.config(power_up.kind) {
+                state.apply(config, claimer);
+            }
+        }
+    }
+
+    let mut resting = ball_query.iter_mut().filter(|(.., in_play, _, _)| !in_play.0);
+    for (translation, velocity, bounces, time, hit) in splits {
+        let Some((mut transform, mut split_velocity, mut bounce_count, mut rally_time, mut last_hit, mut in_play, _, _)) =
+            resting.next()
+        else {
+            break;
+        };
+        // A flat ball would split into two balls on top of each other
+        let y = if velocity.y.abs() < 1.0 { velocity.x.abs() / 2.0 } else { -velocity.y };
+        transform.translation = translation;
+        *split_velocity = Velocity { x: velocity.x, y };
+        *bounce_count = bounces;
+        *rally_time = time;
+        *last_hit = hit;
+        in_play.0 = true;
+    }
+}
+
+/// Sizes each paddle for the Grow and Shrink effects acting on it
+pub fn size_paddles(
+    state: Res<PowerUpState>,
+    settings: Res<PongSettings>,
+    mut paddle_query: Query<(&mut Sprite, &Paddle)>,
+) {
+    for (mut sprite, paddle) in paddle_query.iter_mut() {
+        let side = paddle.side;
+        let length = (settings.paddle_size.y * state.paddle_scale(side)).min(side.orient(settings.field_size).y);
+        let size = Some(side.orient(Vec2::new(settings.paddle_size.x, length)));
+        if sprite.custom_size != size {
+            sprite.custom_size = size;
+        }
+    }
+}
+
+/// Keeps a sprite on the field for every power-up waiting there
+pub fn show_power_ups(
+    mut commands: Commands,
+    state: Res<PowerUpState>,
+    sprite_query: Query<(Entity, &PowerUpSprite)>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+
+    for (entity, sprite) in sprite_query.iter() {
+        if !state.field.iter().any(|power_up| power_up.id == sprite.0) {
+            commands.entity(entity).despawn();
+        }
+    }
+    for power_up in state.field.iter() {
+        if sprite_query.iter().any(|(_, sprite)| sprite.0 == power_up.id) {
+            continue;
+        }
+        commands.spawn((
+            Sprite {
+                color: power_up.kind.color(),
+                custom_size: Some(Vec2::splat(POWER_UP_SIZE)),
+                ..default()
+            },
+            Transform::from_translation(power_up.position.extend(0.5)),
+            PowerUpSprite(power_up.id),
+            StateScoped(InGame),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha12Rng;
+    use crate::ai::AiPlayers;
+    use crate::headless::Simulation;
+    use crate::simulation::SimulationSettings;
+
+    #[test]
+    fn stacking_rules_decide_what_claiming_again_does() {
+        let rules = PowerUpRules::default();
+        let mut state = PowerUpState::default();
+
+        for _ in 0..3 {
+            state.apply(rules.config(PowerUpKind::Grow).unwrap(), Side::Left);
+            state.apply(rules.config(PowerUpKind::Sticky).unwrap(), Side::Left);
+        }
+        state.apply(rules.config(PowerUpKind::Shrink).unwrap(), Side::Right);
+
+        assert_eq!(state.stacks(PowerUpKind::Grow, Side::Left), 2);
+        assert!((state.paddle_scale(Side::Left) - GROW_FACTOR * GROW_FACTOR * SHRINK_FACTOR).abs() < 1e-5);
+        assert_eq!(state.paddle_scale(Side::Right), 1.0);
+        let sticky = state.effects.iter().find(|effect| effect.kind == PowerUpKind::Sticky).unwrap();
+        assert_eq!((sticky.stacks, sticky.remaining), (1, 24.0));
+        assert!(state.sticky(Side::Left) && !state.sticky(Side::Right));
+    }
+
+    #[test]
+    fn ball_claims_a_power_up_for_the_player_who_sent_it() {
+        let mut simulation = Simulation::new(SimulationSettings::default(), AiPlayers::default(), MatchRules::default());
+        simulation.start_match();
+        simulation.skip_countdown();
+
+        let world = simulation.app.world_mut();
+        let (transform, velocity, last_hit) =
+            world.query_filtered::<(&Transform, &Velocity, &LastHit), With<Ball>>().single(world);
+        let ahead = transform.translation.truncate() + Vec2::new(velocity.x, velocity.y).normalize() * 20.0;
+        // The left player serves first
+        assert_eq!(last_hit.0, Some(Paddle::back(Side::Left)));
+        let claimer = Side::Left;
+        world.resource_mut::<PowerUpState>().field.push(FieldPowerUp {
+            id: 0,
+            kind: PowerUpKind::Grow,
+            position: ahead,
+            remaining: 10.0,
+        });
+        simulation.run(30);
+
+        let world = simulation.app.world_mut();
+        assert!(world.resource::<PowerUpState>().field.is_empty());
+        let heights: Vec<(f32, Side)> = world
+            .query::<(&Sprite, &Paddle)>()
+            .iter(world)
+            .map(|(sprite, paddle)| (sprite.custom_size.unwrap().y, paddle.side))
+            .collect();
+        let paddle_height = PongSettings::default().paddle_size.y;
+        for (height, side) in heights {
+            let grown = side == claimer;
+            let expected = if grown { paddle_height * GROW_FACTOR } else { paddle_height };
+            assert!((height - expected).abs() < 1e-3, "paddle height {height}");
+        }
+    }
+
+    #[test]
+    fn ball_no_paddle_has_hit_claims_nothing() {
+        let mut simulation = Simulation::new(SimulationSettings::default(), AiPlayers::default(), MatchRules::default());
+        simulation.start_match();
+        simulation.skip_countdown();
+
+        let world = simulation.app.world_mut();
+        let (transform, velocity, mut last_hit) =
+            world.query_filtered::<(&Transform, &Velocity, &mut LastHit), With<Ball>>().single_mut(world);
+        let ahead = transform.translation.truncate() + Vec2::new(velocity.x, velocity.y).normalize() * 20.0;
+        last_hit.0 = None;
+        world.resource_mut::<PowerUpState>().field.push(FieldPowerUp {
+            id: 0,
+            kind: PowerUpKind::Grow,
+            position: ahead,
+            remaining: 10.0,
+        });
+        simulation.run(30);
+
+        assert_eq!(simulation.app.world().resource::<PowerUpState>().field.len(), 1);
+    }
+
+    #[test]
+    fn weights_pick_the_kinds() {
+        let weights = "grow=0,shrink=0,burst=0,split=0,sticky=0,reverse=0,shield=5";
+        let args = ["--power-ups", "--power-up-interval", "3", "--power-up-weights", weights].map(String::from);
+        let rules = PowerUpRules::from_args(args);
+        let mut rng = ChaCha12Rng::seed_from_u64(7);
+
+        assert!(rules.enabled);
+        assert_eq!(rules.spawn_interval, 3.0);
+        assert!((0..20).all(|_| rules.choose(&mut rng) == Some(PowerUpKind::Shield)));
+        assert_eq!(rules.extra_balls(), 0);
+        assert!(!PowerUpRules::default().set_weights("grow=lots"));
+
+        // A list with a bad entry changes nothing
+        let mut rules = PowerUpRules::default();
+        assert!(!rules.set_weights("grow=7,bogus"));
+        assert_eq!(rules, PowerUpRules::default());
+
+        // Weights too big to add up in 32 bits still pick a kind
+        let mut rules = PowerUpRules::default();
+        assert!(rules.set_weights("grow=4294967295,shrink=1"));
+        assert!(rules.choose(&mut rng).is_some());
+    }
+
+    #[test]
+    fn rules_load_from_ron() {
+        let text = "(spawn_interval: 5.0, kinds: [(kind: Grow, weight: 2, duration: 12.0, stacking: Stack(max: 3))])";
+
+        let rules: PowerUpRules = ron::from_str(text).unwrap();
+
+        assert_eq!(rules.spawn_interval, 5.0);
+        assert_eq!(rules.lifetime, PowerUpRules::default().lifetime);
+        assert_eq!(rules.config(PowerUpKind::Grow).unwrap().stacking, Stacking::Stack { max: 3 });
+        assert_eq!(rules.config(PowerUpKind::Shield), None);
+        assert_eq!(rules.validate(), Ok(()));
+    }
+
+    #[test]
+    fn unusable_rules_are_refused() {
+        let never = "(spawn_interval: -1.0)";
+        let forever = "(lifetime: NaN)";
+        let backwards = "(kinds: [(kind: Grow, weight: 1, duration: -5.0, stacking: Refresh)])";
+        let unstackable = "(kinds: [(kind: Grow, weight: 1, duration: 5.0, stacking: Stack(max: 0))])";
+        for text in [never, forever, backwards, unstackable] {
+            let rules: PowerUpRules = ron::from_str(text).unwrap();
+            assert!(rules.validate().is_err(), "accepted {text}");
+        }
+        assert_eq!(PowerUpRules::default().validate(), Ok(()));
+    }
+}
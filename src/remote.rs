@@ -0,0 +1,413 @@
+/// Module for playing or watching a match run by a dedicated server
+use std::collections::VecDeque;
+use std::time::Duration;
+use bevy::prelude::*;
+use crate::ball::{self, Ball, InPlay, Velocity};
+use crate::input::{ActionState, InputAction};
+use crate::paddle::{self, Paddle, Side};
+use crate::powerup::PowerUpState;
+use crate::scoreboard::{MatchRules, MatchStatus, ScoreBoard};
+use crate::serve::ServingSide;
+use crate::server::{ClientRole, ServerConnection, ServerSnapshot};
+use crate::settings::PongSettings;
+use crate::state::{GameState, InGame, ServeCountdown};
+
+/// How far behind the latest snapshot the match is shown, in seconds
+///
+/// Showing the match slightly in the past means there is almost always a
+/// snapshot on either side of the moment drawn to blend between.
+pub const INTERPOLATION_DELAY: f64 = 0.1;
+/// Longest stretch of snapshots kept for interpolation, in seconds
+const SNAPSHOT_HISTORY: f64 = 1.0;
+/// Distance between two snapshots beyond which a ball is taken to have been moved rather than to have travelled
+const TELEPORT_DISTANCE: f32 = 100.0;
+
+/// Plugin showing a match from a dedicated server in place of simulating one
+///
+/// Does nothing unless a RemoteSession resource has been inserted.
+pub struct RemotePlugin;
+
+impl Plugin for RemotePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                receive_snapshots,
+                mirror_server_state,
+                predict_local_paddle,
+                show_remote_match,
+            )
+                .chain()
+                .run_if(resource_exists::<RemoteSession>),
+        )
+        .add_systems(OnEnter(InGame), reset_local_paddle.run_if(resource_exists::<RemoteSession>))
+        .add_systems(
+            OnEnter(GameState::MainMenu),
+            spawn_waiting_text
+                .run_if(resource_exists::<RemoteSession>.and(resource_exists::<AssetServer>)),
+        )
+        .add_systems(Last, leave_server_on_exit.run_if(resource_exists::<RemoteSession>));
+    }
+}
+
+/// Command line options for connecting to a dedicated server
+pub struct RemoteConfig {
+    /// Address of the server
+    pub address: String,
+    /// Whether to watch rather than play
+    pub spectate: bool,
+    /// Seconds a spectator watches behind the live match
+    pub delay: f64,
+}
+
+impl RemoteConfig {
+    /// Reads the server options from command line arguments
+    ///
+    /// Returns None unless `--connect <address>` is given. `--spectate` watches the
+    /// match instead of taking a paddle, `--delay <seconds>` behind the live match.
+    /// Delays that aren't a positive number of seconds are reported and ignored.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut args = args.into_iter();
+        let mut address = None;
+        let mut spectate = false;
+        let mut delay = 0.0;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--connect" => address = args.next(),
+                "--spectate" => spectate = true,
+                "--delay" => {
+                    let value = args.next().unwrap_or_default();
+                    match value.parse::<f64>() {
+                        Ok(seconds) if seconds.is_finite() && seconds > 0.0 => delay = seconds,
+                        _ => warn!("Invalid value '{}' for --delay", value),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Players see the match as it happens
+        let delay = if spectate { delay } else { 0.0 };
+        address.map(|address| RemoteConfig { address, spectate, delay })
+    }
+}
+
+/// A connection to a dedicated server and the snapshots received from it
+#[derive(Resource)]
+pub struct RemoteSession {
+    /// Connection to the server
+    connection: ServerConnection,
+    /// Recent snapshots with the time they arrived
+    snapshots: VecDeque<(f64, ServerSnapshot)>,
+    /// Height of the local player's paddle, moved ahead of the server for responsiveness
+    local_y: f32,
+    /// Seconds the match is shown behind the latest snapshot, on top of INTERPOLATION_DELAY
+    delay: f64,
+}
+
+impl RemoteSession {
+    /// Creates a session over an open connection, showing the match `delay` seconds late
+    pub fn new(connection: ServerConnection, delay: f64) -> Self {
+        RemoteSession {
+            connection,
+            snapshots: VecDeque::new(),
+            local_y: 0.0,
+            delay,
+        }
+    }
+
+    /// Paddle controlled by this client, if it is a player
+    fn local_side(&self) -> Option<Side> {
+        match self.connection.role() {
+            Some(ClientRole::Player(side)) => Some(side),
+            _ => None,
+        }
+    }
+
+    /// Latest snapshot that is old enough to be shown at `now`
+    fn shown_snapshot(&self, now: f64) -> Option<&ServerSnapshot> {
+        shown_snapshot(&self.snapshots, now - self.delay)
+    }
+}
+
+/// Collects new snapshots from the server
+pub fn receive_snapshots(time: Res<Time<Real>>, mut session: ResMut<RemoteSession>) {
+    let now = time.elapsed_secs_f64();
+
+    for snapshot in session.connection.poll() {
+        // Packets can arrive out of order, and an old snapshot is no use
+        if session.snapshots.back().is_some_and(|(_, latest)| latest.tick >= snapshot.tick) {
+            continue;
+        }
+        session.snapshots.push_back((now, snapshot));
+    }
+
+    while session.snapshots.len() > 2
+        && session.snapshots.front().is_some_and(|(received, _)| now - received > session.delay + SNAPSHOT_HISTORY)
+    {
+        session.snapshots.pop_front();
+    }
+}
+
+/// Follows the server's game state, score, match clock, serve countdown, serving player,
+/// power-ups and arena
+#[allow(clippy::too_many_arguments)]
+pub fn mirror_server_state(
+    time: Res<Time<Real>>,
+    session: Res<RemoteSession>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut scoreboard: ResMut<ScoreBoard>,
+    mut status: ResMut<MatchStatus>,
+    mut countdown: ResMut<ServeCountdown>,
+    mut serving: ResMut<ServingSide>,
+    mut power_ups: ResMut<PowerUpState>,
+    mut rules: ResMut<MatchRules>,
+) {
+    // The arena is known from the server's Welcome, before the match is shown
+    if let Some(arena) = session.connection.arena()
+        && rules.arena != *arena
+    {
+        rules.arena = arena.clone();
+    }
+    let Some(latest) = session.shown_snapshot(time.elapsed_secs_f64()) else {
+        return;
+    };
+
+    if *state.get() != latest.state {
+        next_state.set(latest.state);
+    }
+    scoreboard.left = latest.score[0];
+    scoreboard.right = latest.score[1];
+    status.winner = latest.winner;
+    status.elapsed = latest.elapsed;
+    // The server's serve timeout is not known, so the countdown is stretched to fit
+    let duration = countdown.0.duration().as_secs_f32().max(latest.countdown);
+    countdown.0.set_duration(Duration::from_secs_f32(duration));
+    countdown.0.set_elapsed(Duration::from_secs_f32(duration - latest.countdown));
+    serving.0 = latest.serving;
+    power_ups.set_if_neq(latest.power_ups.clone());
+}
+
+/// Moves the local player's paddle straight away and tells the server where it is
+///
+/// The server moves the real paddle towards this position at the paddle speed, so
+/// it follows the local one a round trip behind. Whether the player is serving is
+/// sent along with it. Sending every frame also lets the server know the player
+/// is still there.
+pub fn predict_local_paddle(
+    actions: Option<Res<ActionState>>,
+    state: Res<State<GameState>>,
+    settings: Res<PongSettings>,
+    time: Res<Time>,
+    power_ups: Res<PowerUpState>,
+    mut session: ResMut<RemoteSession>,
+) {
+    let Some(side) = session.local_side() else {
+        return;
+    };
+
+    let mut serve = false;
+    if matches!(state.get(), GameState::Countdown | GameState::Playing)
+        && let Some(actions) = actions
+    {
+        let actions = actions.player(Paddle::back(side));
+        let boundary = (settings.field_size.y - settings.paddle_size.y) / 2.0;
+        let axis = actions.paddle_axis() * paddle::control_direction(&power_ups, side);
+        let movement = axis * settings.paddle_speed * time.delta_secs();
+        session.local_y = (session.local_y + movement).clamp(-boundary, boundary);
+        serve = actions.pressed(InputAction::Serve);
+    }
+
+    let local_y = session.local_y;
+    session.connection.send_paddle(local_y, serve);
+}
+
+/// Blends between the two snapshots around the moment being shown
+///
+/// The local player's own paddle is shown where they have moved it rather
+/// than where the server last had it. Only as many balls are shown as the
+/// server has in play, and more are spawned if there are not enough to go round.
+pub fn show_remote_match(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    session: Res<RemoteSession>,
+    settings: Res<PongSettings>,
+    mut ball_query: Query<
+        (&mut Transform, &mut InPlay),
+        (With<Ball>, Without<Paddle>),
+    >,
+    mut paddle_query: Query<(&mut Transform, &Paddle)>,
+) {
+    let render_time = time.elapsed_secs_f64() - session.delay - INTERPOLATION_DELAY;
+    let Some((balls, paddles)) = interpolate_snapshots(&session.snapshots, render_time) else {
+        return;
+    };
+
+    let mut positions = balls.into_iter();
+    for (mut transform, mut in_play) in ball_query.iter_mut() {
+        match positions.next() {
+            Some(position) => {
+                transform.translation = position.extend(transform.translation.z);
+                in_play.set_if_neq(InPlay(true));
+            }
+            None => {
+                in_play.set_if_neq(InPlay(false));
+            }
+        }
+    }
+    for position in positions {
+        let velocity = Velocity { x: 0.0, y: 0.0 };
+        commands.spawn((ball::ball_bundle(&settings, position.extend(0.0), velocity), InPlay(true), StateScoped(InGame)));
+    }
+
+    let local_side = session.local_side();
+    for (mut transform, paddle) in paddle_query.iter_mut() {
+        let position = match paddle.side {
+            Side::Left => paddles[0],
+            Side::Right => paddles[1],
+            // Servers only run two-player matches
+            Side::Top | Side::Bottom => continue,
+        };
+        let y = if local_side == Some(paddle.side) { session.local_y } else { position.y };
+        transform.translation = Vec3::new(position.x, y, transform.translation.z);
+    }
+}
+
+/// Latest of the snapshots that arrived by `time`
+fn shown_snapshot(snapshots: &VecDeque<(f64, ServerSnapshot)>, time: f64) -> Option<&ServerSnapshot> {
+    snapshots
+        .iter()
+        .rev()
+        .find(|(received, _)| *received <= time)
+        .map(|(_, snapshot)| snapshot)
+}
+
+/// Works out where the balls and paddles were at `render_time`
+///
+/// Blends between the snapshots that arrived either side of it, or uses the
+/// nearest one when there is nothing to blend with.
+fn interpolate_snapshots(
+    snapshots: &VecDeque<(f64, ServerSnapshot)>,
+    render_time: f64,
+) -> Option<(Vec<Vec2>, [Vec2; 2])> {
+    let after = snapshots.iter().position(|(received, _)| *received > render_time);
+    let (from, to, blend) = match after {
+        Some(0) => (&snapshots[0].1, &snapshots[0].1, 0.0),
+        Some(index) => {
+            let (start, from) = &snapshots[index - 1];
+            let (end, to) = &snapshots[index];
+            (from, to, ((render_time - start) / (end - start)) as f32)
+        }
+        None => {
+            let (_, latest) = snapshots.back()?;
+            (latest, latest, 0.0)
+        }
+    };
+
+    // A ball that is respawned, parked in front of the serving paddle or sent through
+    // a portal jumps to its new place rather than sliding there
+    let balls = from
+        .balls
+        .iter()
+        .zip(&to.balls)
+        .map(|(from, to)| if from.distance(*to) > TELEPORT_DISTANCE { *to } else { from.lerp(*to, blend) })
+        .collect();
+    let paddles = [
+        from.paddles[0].lerp(to.paddles[0], blend),
+        from.paddles[1].lerp(to.paddles[1], blend),
+    ];
+    Some((balls, paddles))
+}
+
+/// Puts the local paddle back in the middle for a new match
+pub fn reset_local_paddle(mut session: ResMut<RemoteSession>) {
+    session.local_y = 0.0;
+}
+
+/// Tells the player the game is waiting for the server and an opponent
+pub fn spawn_waiting_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    session: Res<RemoteSession>,
+) {
+    let message = match session.connection.role() {
+        Some(ClientRole::Spectator) => "Waiting for the next match...",
+        _ => "Waiting for the server and an opponent...",
+    };
+
+    commands.spawn((
+        Text2d::new(message),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 24.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -180.0, 1.0)),
+        StateScoped(GameState::MainMenu),
+    ));
+}
+
+/// Lets the server know straight away when the game is closed
+pub fn leave_server_on_exit(mut exit_events: EventReader<AppExit>, mut session: ResMut<RemoteSession>) {
+    if exit_events.read().next().is_some() {
+        session.connection.leave();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(tick: u64, ball: Vec2) -> ServerSnapshot {
+        ServerSnapshot {
+            tick,
+            state: GameState::Playing,
+            balls: vec![ball],
+            paddles: [Vec2::new(-335.0, 0.0), Vec2::new(335.0, 0.0)],
+            score: [0, 0],
+            winner: None,
+            countdown: 0.0,
+            serving: Side::Left,
+            power_ups: PowerUpState::default(),
+            elapsed: 0.0,
+        }
+    }
+
+    #[test]
+    fn ball_is_blended_between_snapshots() {
+        let snapshots = VecDeque::from([
+            (1.0, snapshot(2, Vec2::new(10.0, 10.0))),
+            (1.1, snapshot(4, Vec2::new(20.0, 30.0))),
+        ]);
+
+        let (balls, _) = interpolate_snapshots(&snapshots, 1.05).unwrap();
+        assert!(balls[0].distance(Vec2::new(15.0, 20.0)) < 0.01);
+        // Past the newest snapshot the ball waits there
+        let (balls, _) = interpolate_snapshots(&snapshots, 2.0).unwrap();
+        assert_eq!(balls[0], Vec2::new(20.0, 30.0));
+    }
+
+    #[test]
+    fn delayed_spectator_is_shown_older_snapshots() {
+        let snapshots = VecDeque::from([
+            (1.0, snapshot(2, Vec2::ZERO)),
+            (3.0, snapshot(6, Vec2::ZERO)),
+        ]);
+
+        assert_eq!(shown_snapshot(&snapshots, 0.5), None);
+        assert_eq!(shown_snapshot(&snapshots, 2.0).unwrap().tick, 2);
+        assert_eq!(shown_snapshot(&snapshots, 3.0).unwrap().tick, 6);
+
+        let args = ["--connect", "127.0.0.1:7878", "--spectate", "--delay", "30"].map(String::from);
+        assert_eq!(RemoteConfig::from_args(args).unwrap().delay, 30.0);
+        let args = ["--connect", "127.0.0.1:7878", "--delay", "30"].map(String::from);
+        assert_eq!(RemoteConfig::from_args(args).unwrap().delay, 0.0);
+        for value in ["-5", "NaN", "inf"] {
+            let args = ["--connect", "127.0.0.1:7878", "--spectate", "--delay", value].map(String::from);
+            assert_eq!(RemoteConfig::from_args(args).unwrap().delay, 0.0, "accepted {value}");
+        }
+    }
+}
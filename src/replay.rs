@@ -0,0 +1,773 @@
+/// Module for recording matches to files and playing them back
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use bevy::{app::FixedMain, prelude::*};
+use bevy::state::state::StateTransition;
+use serde::{Deserialize, Serialize};
+use crate::ball::{Ball, BounceCount, Held, InPlay, LastHit, RallyTime, Velocity};
+use crate::net::QuantizedInput;
+use crate::paddle::{self, Paddle, PaddleInput};
+use crate::powerup::PowerUpState;
+use crate::scoreboard::{MatchRules, MatchStatus, ScoreBoard};
+use crate::serve::ServingSide;
+use crate::settings::PongSettings;
+use crate::simulation::{GameRng, SimulationSettings};
+use crate::state::{GameState, ServeCountdown};
+
+/// Version written to replay files, bumped whenever the format or gameplay changes
+pub const REPLAY_VERSION: u32 = 9;
+/// Seconds of play between keyframes
+pub const KEYFRAME_INTERVAL: f64 = 5.0;
+/// Directory matches are recorded to when `--replay-dir` is not given
+pub const DEFAULT_REPLAY_DIR: &str = "replays";
+/// Slowest playback speed
+pub const MIN_PLAYBACK_SPEED: f32 = 0.25;
+/// Fastest playback speed
+pub const MAX_PLAYBACK_SPEED: f32 = 8.0;
+/// Seconds skipped by each press of the seek keys
+const SEEK_SECONDS: f64 = 5.0;
+
+/// Plugin recording matches while a ReplayRecorder is inserted and playing one
+/// back while a ReplayPlayback is inserted
+///
+/// Recording notes the paddle inputs of every tick the ball or paddles can move,
+/// with a keyframe of the whole match every KEYFRAME_INTERVAL seconds. Playback
+/// restores the first keyframe and feeds the recorded inputs back in, checking
+/// the match against each later keyframe on the way.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            record_tick
+                .after(crate::game::PongSet::Input)
+                .before(crate::game::PongSet::Movement)
+                .run_if(resource_exists::<ReplayRecorder>),
+        )
+        .add_systems(
+            FixedFirst,
+            drive_playback
+                .after(crate::simulation::apply_state_transitions)
+                .run_if(resource_exists::<ReplayPlayback>),
+        )
+        .add_systems(Last, finish_recording_on_exit.run_if(resource_exists::<ReplayRecorder>));
+    }
+}
+
+/// Everything in a match the simulation depends on, at the start of a tick
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Tick the keyframe was taken before
+    pub tick: u32,
+    /// Current game state
+    pub state: GameState,
+    /// Every ball's position, movement, bounces, rally time, whether it is in play,
+    /// whether a paddle holds it and which paddle last hit it
+    pub balls: Vec<(Vec3, Velocity, BounceCount, RallyTime, InPlay, Held, LastHit)>,
+    /// Position and movement of every paddle, in the order of MatchRules::paddles
+    pub paddles: Vec<(Vec3, Velocity)>,
+    /// The score
+    pub scoreboard: ScoreBoard,
+    /// Match clock and winner
+    pub status: MatchStatus,
+    /// Time left before the next serve
+    pub countdown: ServeCountdown,
+    /// Player whose turn it is to serve
+    pub serving: ServingSide,
+    /// Gameplay random number generator
+    pub rng: GameRng,
+    /// Power-ups on the field and effects running
+    pub power_ups: PowerUpState,
+}
+
+impl Keyframe {
+    /// Copies the state of the match in `world`
+    fn capture(world: &mut World, tick: u32) -> Self {
+        let balls = world
+            .query_filtered::<(&Transform, &Velocity, &BounceCount, &RallyTime, &InPlay, &Held, &LastHit), With<Ball>>()
+            .iter(world)
+            .map(|(transform, velocity, bounce_count, rally_time, in_play, held, last_hit)| {
+                (transform.translation, *velocity, *bounce_count, *rally_time, *in_play, *held, *last_hit)
+            })
+            .collect();
+
+        Keyframe {
+            tick,
+            state: *world.resource::<State<GameState>>().get(),
+            balls,
+            paddles: paddles(world).into_iter().map(|paddle| paddle_state(world, paddle)).collect(),
+            scoreboard: world.resource::<ScoreBoard>().clone(),
+            status: world.resource::<MatchStatus>().clone(),
+            countdown: world.resource::<ServeCountdown>().clone(),
+            serving: *world.resource::<ServingSide>(),
+            rng: world.resource::<GameRng>().clone(),
+            power_ups: world.resource::<PowerUpState>().clone(),
+        }
+    }
+
+    /// Puts the match in `world` back to how it was when the keyframe was taken
+    fn restore(&self, world: &mut World) {
+        // Go through a proper transition so the field is spawned if it isn't there yet
+        if *world.resource::<State<GameState>>().get() != self.state {
+            world.resource_mut::<NextState<GameState>>().set(self.state);
+            world.run_schedule(StateTransition);
+        }
+
+        let mut ball_query = world.query_filtered::<
+            (&mut Transform, &mut Velocity, &mut BounceCount, &mut RallyTime, &mut InPlay, &mut Held, &mut LastHit),
+            With<Ball>,
+        >();
+        for (
+            (mut transform, mut velocity, mut bounce_count, mut rally_time, mut in_play, mut held, mut last_hit),
+            (position, saved_velocity, saved_bounces, saved_rally_time, saved_in_play, saved_held, saved_last_hit),
+        ) in ball_query.iter_mut(world).zip(&self.balls)
+        {
+            transform.translation = *position;
+            *velocity = *saved_velocity;
+            *bounce_count = *saved_bounces;
+            *rally_time = *saved_rally_time;
+            in_play.set_if_neq(*saved_in_play);
+            *held = *saved_held;
+            *last_hit = *saved_last_hit;
+        }
+        for (paddle, &state) in paddles(world).into_iter().zip(&self.paddles) {
+            set_paddle_state(world, paddle, state);
+        }
+
+        world.insert_resource(self.scoreboard.clone());
+        world.insert_resource(self.status.clone());
+        world.insert_resource(self.countdown.clone());
+        world.insert_resource(self.serving);
+        world.insert_resource(self.rng.clone());
+        world.insert_resource(self.power_ups.clone());
+    }
+
+    /// Whether the balls and score in `world` are where the keyframe has them
+    fn matches(&self, world: &mut World) -> bool {
+        let balls: Vec<Vec3> = world
+            .query_filtered::<&Transform, With<Ball>>()
+            .iter(world)
+            .map(|transform| transform.translation)
+            .collect();
+
+        balls.len() == self.balls.len()
+            && balls.iter().zip(&self.balls).all(|(ball, (saved, ..))| ball.distance(*saved) < 1e-3)
+            && *world.resource::<ScoreBoard>() == self.scoreboard
+    }
+}
+
+/// Position and movement of `paddle`
+fn paddle_state(world: &mut World, paddle: Paddle) -> (Vec3, Velocity) {
+    paddle::find_paddle(world, paddle)
+        .and_then(|paddle| world.query::<(&Transform, &Velocity)>().get(world, paddle).ok())
+        .map_or((Vec3::ZERO, Velocity { x: 0.0, y: 0.0 }), |(transform, velocity)| (transform.translation, *velocity))
+}
+
+/// Moves `paddle` back to a saved position and movement
+fn set_paddle_state(world: &mut World, paddle: Paddle, (position, saved_velocity): (Vec3, Velocity)) {
+    let Some(paddle) = paddle::find_paddle(world, paddle) else {
+        return;
+    };
+    if let Ok((mut transform, mut velocity)) = world.query::<(&mut Transform, &mut Velocity)>().get_mut(world, paddle) {
+        transform.translation = position;
+        *velocity = saved_velocity;
+    }
+}
+
+/// The paddles in the match being recorded or played
+fn paddles(world: &World) -> Vec<Paddle> {
+    world.resource::<MatchRules>().paddles()
+}
+
+/// A recorded match
+///
+/// The paddle inputs are stored as runs of identical ticks, which keeps files
+/// small since paddles spend most of a match either still or at full speed.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    /// REPLAY_VERSION of the game that recorded the match
+    pub version: u32,
+    /// Seed the match was started with
+    pub seed: u64,
+    /// Simulation ticks per second
+    pub tick_rate: f64,
+    /// Rules of the match
+    pub rules: MatchRules,
+    /// Dimensions and speeds of the field
+    pub settings: PongSettings,
+    /// Number of ticks recorded
+    pub ticks: u32,
+    /// Quantized paddle inputs in the order of MatchRules::paddles, as a run length followed by the inputs
+    pub inputs: Vec<(u32, Vec<QuantizedInput>)>,
+    /// Full state of the match at regular intervals, starting at tick 0
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Replay {
+    /// Starts an empty recording of the match in `world`
+    fn new(world: &World) -> Self {
+        let settings = world.resource::<SimulationSettings>();
+        Replay {
+            version: REPLAY_VERSION,
+            seed: settings.seed,
+            tick_rate: settings.tick_rate,
+            rules: world.resource::<MatchRules>().clone(),
+            settings: world.resource::<PongSettings>().clone(),
+            ticks: 0,
+            inputs: Vec::new(),
+            keyframes: Vec::new(),
+        }
+    }
+
+    /// Ticks between keyframes
+    pub fn keyframe_interval(&self) -> u32 {
+        ((KEYFRAME_INTERVAL * self.tick_rate).round() as u32).max(1)
+    }
+
+    /// Length of the recording in seconds
+    pub fn duration(&self) -> f64 {
+        self.ticks as f64 / self.tick_rate
+    }
+
+    /// Adds one tick of inputs to the recording
+    fn push_inputs(&mut self, inputs: Vec<QuantizedInput>) {
+        match self.inputs.last_mut() {
+            Some((run, last)) if *last == inputs => *run += 1,
+            _ => self.inputs.push((1, inputs)),
+        }
+        self.ticks += 1;
+    }
+
+    /// Expands the runs of inputs into one entry per tick
+    fn unpack_inputs(&self) -> Vec<Vec<QuantizedInput>> {
+        self.inputs
+            .iter()
+            .flat_map(|(run, inputs)| std::iter::repeat_n(inputs.clone(), *run as usize))
+            .collect()
+    }
+
+    /// Reads a replay from a file
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let text = fs::read_to_string(path)?;
+        let mut replay: Replay = ron::from_str(&text)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(ReplayError::Version(replay.version));
+        }
+        replay.rules.clamp_serve_timeout();
+        replay.rules.validate(&replay.settings).map_err(ReplayError::Rules)?;
+        if replay.keyframes.first().is_none_or(|keyframe| keyframe.tick != 0) {
+            return Err(ReplayError::MissingKeyframe);
+        }
+        Ok(replay)
+    }
+
+    /// Writes the replay to a file
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        fs::write(path, ron::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Errors that can happen while loading or saving a replay
+#[derive(Debug)]
+pub enum ReplayError {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file is not a valid replay
+    Parse(ron::error::SpannedError),
+    /// The replay could not be turned into RON
+    Serialize(ron::Error),
+    /// The replay was recorded by a different version of the game
+    Version(u32),
+    /// The replay has no keyframe to start playing from
+    MissingKeyframe,
+    /// The replay's match rules can't be played, with every problem found
+    Rules(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "{}", error),
+            ReplayError::Parse(error) => write!(f, "invalid replay file: {}", error),
+            ReplayError::Serialize(error) => write!(f, "could not write replay: {}", error),
+            ReplayError::Version(version) => {
+                write!(f, "replay is from version {} but this game plays version {}", version, REPLAY_VERSION)
+            }
+            ReplayError::MissingKeyframe => write!(f, "replay has no starting keyframe"),
+            ReplayError::Rules(problems) => write!(f, "replay's match rules can't be played: {}", problems),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for ReplayError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        ReplayError::Parse(error)
+    }
+}
+
+impl From<ron::Error> for ReplayError {
+    fn from(error: ron::Error) -> Self {
+        ReplayError::Serialize(error)
+    }
+}
+
+/// Command line options for recording and playing back replays
+pub struct ReplayConfig {
+    /// Replay to play instead of starting a game
+    pub play: Option<PathBuf>,
+    /// Directory matches are recorded to, unless recording is turned off
+    pub record_dir: Option<PathBuf>,
+}
+
+impl ReplayConfig {
+    /// Reads the replay options from command line arguments
+    ///
+    /// `--replay <path>` plays back a recorded match. Matches are recorded to
+    /// `--replay-dir <dir>`, DEFAULT_REPLAY_DIR by default, unless `--no-record` is given.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        let mut config = ReplayConfig { play: None, record_dir: Some(PathBuf::from(DEFAULT_REPLAY_DIR)) };
+        let mut record = true;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--replay" => config.play = args.next().map(PathBuf::from),
+                "--replay-dir" => config.record_dir = args.next().map(PathBuf::from),
+                "--no-record" => record = false,
+                _ => {}
+            }
+        }
+
+        if !record {
+            config.record_dir = None;
+        }
+        config
+    }
+}
+
+/// Records every match played while it is inserted
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    /// Where finished recordings are saved, or nowhere if None
+    directory: Option<PathBuf>,
+    /// Recording of the match in progress
+    current: Option<Replay>,
+    /// The last finished recording
+    pub last: Option<Replay>,
+}
+
+impl ReplayRecorder {
+    /// Records matches, saving each one to `directory` once it ends
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        ReplayRecorder { directory, ..default() }
+    }
+
+    /// Ends the recording in progress, if any, and saves it
+    fn finish(&mut self) {
+        let Some(replay) = self.current.take() else {
+            return;
+        };
+
+        if let Some(directory) = &self.directory {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+            let path = directory.join(format!("match-{}-{}.ron", timestamp, replay.seed));
+            match fs::create_dir_all(directory).map_err(ReplayError::from).and_then(|()| replay.save(&path)) {
+                Ok(()) => info!("Saved replay to {}", path.display()),
+                Err(error) => warn!("Could not save replay to {}: {}", path.display(), error),
+            }
+        }
+        self.last = Some(replay);
+    }
+}
+
+/// Notes the paddle inputs of this tick, and a keyframe when one is due
+///
+/// Only ticks in the countdown or in play are recorded, as nothing moves
+/// otherwise. Inputs are quantized before the paddles move, so the match plays
+/// out exactly as it will in the replay. The recording is finished and saved as
+/// soon as the match is over or abandoned.
+pub fn record_tick(world: &mut World) {
+    let state = *world.resource::<State<GameState>>().get();
+
+    world.resource_scope(|world, mut recorder: Mut<ReplayRecorder>| {
+        match state {
+            GameState::Countdown | GameState::Playing => {}
+            // Nothing moves while paused, so the pause is simply left out
+            GameState::Paused => return,
+            _ => {
+                recorder.finish();
+                return;
+            }
+        }
+
+        let replay = recorder.current.get_or_insert_with(|| Replay::new(world));
+        if replay.ticks.is_multiple_of(replay.keyframe_interval()) {
+            replay.keyframes.push(Keyframe::capture(world, replay.ticks));
+        }
+        let inputs = paddles(world).into_iter().map(|paddle| quantize_paddle_input(world, paddle)).collect();
+        replay.push_inputs(inputs);
+    });
+}
+
+/// Saves the match in progress when the game is closed
+pub fn finish_recording_on_exit(mut exit_events: EventReader<AppExit>, mut recorder: ResMut<ReplayRecorder>) {
+    if exit_events.read().next().is_some() {
+        recorder.finish();
+    }
+}
+
+/// Rounds the PaddleInput of `paddle` to what a replay can store
+fn quantize_paddle_input(world: &mut World, paddle: Paddle) -> QuantizedInput {
+    let Some(mut input) = paddle::find_paddle(world, paddle).and_then(|paddle| world.get_mut::<PaddleInput>(paddle)) else {
+        return QuantizedInput::default();
+    };
+    let quantized = QuantizedInput::new(&input);
+    *input = quantized.to_paddle_input();
+    quantized
+}
+
+/// A replay being played back
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    /// The recording
+    replay: Replay,
+    /// Inputs of every tick
+    inputs: Vec<Vec<QuantizedInput>>,
+    /// Next tick to be played
+    tick: u32,
+    /// Whether the first keyframe has been restored
+    started: bool,
+    /// Playback speed, from MIN_PLAYBACK_SPEED to MAX_PLAYBACK_SPEED
+    speed: f32,
+    /// Number of keyframes the playback has disagreed with
+    desyncs: u32,
+}
+
+impl ReplayPlayback {
+    /// Prepares `replay` to be played from the start
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayback {
+            inputs: replay.unpack_inputs(),
+            replay,
+            tick: 0,
+            started: false,
+            speed: 1.0,
+            desyncs: 0,
+        }
+    }
+
+    /// The recording being played
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Next tick to be played
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Current playback speed
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Number of keyframes the playback has disagreed with
+    ///
+    /// Anything but zero means the game no longer plays the recording back
+    /// exactly as it happened.
+    pub fn desyncs(&self) -> u32 {
+        self.desyncs
+    }
+
+    /// Whether every recorded tick has been played
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.replay.ticks
+    }
+}
+
+/// Feeds the recorded inputs for this tick to the paddles
+///
+/// Runs at the start of every fixed tick. Restores the first keyframe when
+/// playback starts, and pauses once the recording runs out.
+pub fn drive_playback(world: &mut World) {
+    world.resource_scope(|world, mut playback: Mut<ReplayPlayback>| {
+        if !playback.started {
+            playback.replay.keyframes[0].restore(world);
+            playback.started = true;
+        }
+
+        let state = *world.resource::<State<GameState>>().get();
+        if !matches!(state, GameState::Countdown | GameState::Playing) {
+            return;
+        }
+        let Some(inputs) = playback.inputs.get(playback.tick as usize).cloned() else {
+            // The recording stopped before the match was over
+            world.resource_mut::<Time<Virtual>>().pause();
+            return;
+        };
+
+        let interval = playback.replay.keyframe_interval();
+        if playback.tick.is_multiple_of(interval)
+            && let Some(keyframe) = playback.replay.keyframes.get((playback.tick / interval) as usize)
+            && !keyframe.matches(world)
+        {
+            warn!("Replay no longer matches the recording at tick {}", playback.tick);
+            playback.desyncs += 1;
+        }
+
+        for (paddle, input) in paddles(world).into_iter().zip(inputs) {
+            set_paddle_input(world, paddle, input);
+        }
+        playback.tick += 1;
+    });
+}
+
+/// Sets the PaddleInput of `paddle`
+fn set_paddle_input(world: &mut World, paddle: Paddle, quantized: QuantizedInput) {
+    if let Some(mut input) = paddle::find_paddle(world, paddle).and_then(|paddle| world.get_mut::<PaddleInput>(paddle)) {
+        *input = quantized.to_paddle_input();
+    }
+}
+
+/// Runs a single fixed tick straight away, whatever the clock says
+fn run_tick(world: &mut World) {
+    let mut fixed = *world.resource::<Time<Fixed>>();
+    let timestep = fixed.timestep();
+    fixed.advance_by(timestep);
+    world.insert_resource(fixed.as_generic());
+    world.run_schedule(FixedMain);
+    let virtual_time = world.resource::<Time<Virtual>>().as_generic();
+    world.insert_resource(virtual_time);
+}
+
+/// Jumps the playback to `target`
+///
+/// Restores the last keyframe at or before the target and plays forward from
+/// there, so seeking backwards costs no more than seeking forwards.
+pub fn seek_playback(world: &mut World, target: u32) {
+    let target = world.resource_scope(|world, mut playback: Mut<ReplayPlayback>| {
+        let target = target.min(playback.replay.ticks);
+        let interval = playback.replay.keyframe_interval();
+        let index = ((target / interval) as usize).min(playback.replay.keyframes.len() - 1);
+
+        // Playing on from the current tick is quicker when it is already close
+        if !playback.started || target < playback.tick || playback.replay.keyframes[index].tick > playback.tick {
+            playback.replay.keyframes[index].restore(world);
+            playback.tick = playback.replay.keyframes[index].tick;
+            playback.started = true;
+        }
+        target
+    });
+
+    while world.resource::<ReplayPlayback>().tick < target
+        && matches!(world.resource::<State<GameState>>().get(), GameState::Countdown | GameState::Playing)
+    {
+        run_tick(world);
+    }
+}
+
+/// Component for the text showing the playback position and controls
+#[derive(Component)]
+pub struct PlaybackText;
+
+/// Shows the playback position and controls along the bottom of the screen
+pub fn spawn_playback_text(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<PongSettings>) {
+    commands.spawn((
+        Text2d::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 16.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -settings.field_size.y / 2.0 + 20.0, 1.0)),
+        PlaybackText,
+    ));
+}
+
+/// Keeps the playback text up to date
+pub fn update_playback_text(
+    playback: Res<ReplayPlayback>,
+    time: Res<Time<Virtual>>,
+    mut query: Query<&mut Text2d, With<PlaybackText>>,
+) {
+    let replay = playback.replay();
+    let position = playback.tick() as f64 / replay.tick_rate;
+    let status = if playback.is_finished() {
+        "Finished"
+    } else if time.is_paused() {
+        "Paused"
+    } else {
+        "Playing"
+    };
+    let line = format!(
+        "{} {} / {}  {}x\nSpace - Pause    Left/Right - Seek    ,/. - Step    Up/Down - Speed    Home - Restart",
+        status,
+        format_clock(position),
+        format_clock(replay.duration()),
+        playback.speed(),
+    );
+
+    for mut text in query.iter_mut() {
+        text.0 = line.clone();
+    }
+}
+
+/// Formats seconds as minutes and seconds
+fn format_clock(seconds: f64) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Handles the playback keys
+///
+/// Space pauses and resumes, Left and Right seek by a few seconds, comma and
+/// period step a single tick back or forward while paused, Up and Down double or
+/// halve the speed and Home goes back to the start. Runs before the fixed ticks
+/// of the frame, while the paddles and ball are at their simulated positions.
+pub fn control_playback(world: &mut World) {
+    let keyboard = world.resource::<ButtonInput<KeyCode>>();
+    let pressed = |key| keyboard.just_pressed(key);
+    let (pause, back, forward, step_back, step_forward, faster, slower, restart) = (
+        pressed(KeyCode::Space),
+        pressed(KeyCode::ArrowLeft),
+        pressed(KeyCode::ArrowRight),
+        pressed(KeyCode::Comma),
+        pressed(KeyCode::Period),
+        pressed(KeyCode::ArrowUp),
+        pressed(KeyCode::ArrowDown),
+        pressed(KeyCode::Home),
+    );
+
+    let playback = world.resource::<ReplayPlayback>();
+    let tick = playback.tick();
+    let seek_ticks = (SEEK_SECONDS * playback.replay().tick_rate) as u32;
+    let speed = playback.speed();
+    let paused = world.resource::<Time<Virtual>>().is_paused();
+
+    if pause {
+        let mut time = world.resource_mut::<Time<Virtual>>();
+        if paused { time.unpause() } else { time.pause() }
+    }
+    if faster || slower {
+        let speed = if faster { speed * 2.0 } else { speed / 2.0 }.clamp(MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED);
+        world.resource_mut::<ReplayPlayback>().speed = speed;
+        world.resource_mut::<Time<Virtual>>().set_relative_speed(speed);
+    }
+
+    if restart {
+        seek_playback(world, 0);
+    } else if back {
+        seek_playback(world, tick.saturating_sub(seek_ticks));
+    } else if forward {
+        seek_playback(world, tick + seek_ticks);
+    } else if paused && step_back {
+        seek_playback(world, tick.saturating_sub(1));
+    } else if paused && step_forward {
+        seek_playback(world, tick + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{AiPlayers, Difficulty};
+    use crate::headless::Simulation;
+
+    fn record_match(seed: u64) -> (Replay, ScoreBoard) {
+        let settings = SimulationSettings { seed, ..default() };
+        let ai_players = AiPlayers { left: Some(Difficulty::Easy), right: Some(Difficulty::Easy), ..default() };
+        let rules = MatchRules { target_score: 2, win_by: 1, ..default() };
+        let mut simulation = Simulation::new(settings, ai_players, rules);
+        simulation.app.insert_resource(ReplayRecorder::new(None));
+        simulation.start_match();
+
+        for _ in 0..(600.0 / simulation.timestep()) as u32 {
+            if simulation.state() == GameState::GameOver {
+                break;
+            }
+            simulation.tick();
+        }
+        simulation.tick();
+        assert_eq!(simulation.state(), GameState::GameOver, "match did not finish");
+
+        let replay = simulation.app.world_mut().resource_mut::<ReplayRecorder>().last.take().unwrap();
+        (replay, simulation.scoreboard().clone())
+    }
+
+    fn playback(replay: Replay) -> Simulation {
+        let settings = SimulationSettings { seed: replay.seed, tick_rate: replay.tick_rate };
+        let mut simulation = Simulation::new(settings, AiPlayers::default(), replay.rules.clone());
+        simulation.app.insert_resource(ReplayPlayback::new(replay));
+        simulation
+    }
+
+    fn ball_position(simulation: &mut Simulation) -> Vec3 {
+        let world = simulation.app.world_mut();
+        world.query_filtered::<&Transform, With<Ball>>().single(world).translation
+    }
+
+    #[test]
+    fn playback_reproduces_the_recorded_match() {
+        let (replay, final_score) = record_match(11);
+        assert!(replay.keyframes.len() > 1);
+        // Long runs of identical inputs are stored once
+        assert!(replay.inputs.len() < replay.ticks as usize);
+
+        let text = ron::to_string(&replay).unwrap();
+        let mut simulation = playback(ron::from_str(&text).unwrap());
+        for _ in 0..replay.ticks + 10 {
+            simulation.tick();
+        }
+
+        let playback = simulation.app.world().resource::<ReplayPlayback>();
+        assert!(playback.is_finished());
+        assert_eq!(playback.desyncs(), 0);
+        assert_eq!(simulation.state(), GameState::GameOver);
+        assert_eq!(*simulation.scoreboard(), final_score);
+    }
+
+    #[test]
+    fn seeking_back_and_forth_lands_on_the_same_tick() {
+        let (replay, _) = record_match(12);
+        let target = replay.keyframe_interval() + 100;
+        let mut simulation = playback(replay);
+        simulation.tick();
+
+        seek_playback(simulation.app.world_mut(), target);
+        let ahead = ball_position(&mut simulation);
+        seek_playback(simulation.app.world_mut(), 10);
+        seek_playback(simulation.app.world_mut(), target);
+
+        assert_eq!(simulation.app.world().resource::<ReplayPlayback>().tick(), target);
+        assert_eq!(ball_position(&mut simulation), ahead);
+    }
+
+    #[test]
+    fn replays_with_rules_that_cant_be_played_are_refused() {
+        let (mut replay, _) = record_match(13);
+        let path = std::env::temp_dir().join(format!("bevy_pong_bad_rules_{}.ron", std::process::id()));
+        replay.save(&path).unwrap();
+        assert!(Replay::load(&path).is_ok());
+
+        replay.rules.win_by = 0;
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(loaded, Err(ReplayError::Rules(_))));
+    }
+}
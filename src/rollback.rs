@@ -0,0 +1,553 @@
+/// Module for online play, keeping two players in step with rollback
+use std::collections::{BTreeMap, VecDeque};
+use bevy::{app::RunFixedMainLoopSystem, prelude::*, state::state::StateTransition};
+use crate::ball::{Ball, BounceCount, Held, InPlay, LastHit, RallyTime, Velocity};
+use crate::input::{ActionState, InputAction};
+use crate::net::{quantize_axis, NetMessage, QuantizedInput, Transport};
+use crate::paddle::{self, Paddle, PaddleInput, Side};
+use crate::powerup::PowerUpState;
+use crate::scoreboard::{MatchRules, MatchStatus, ScoreBoard};
+use crate::serve::ServingSide;
+use crate::settings::PongSettings;
+use crate::simulation::{self, GameRng, SimulationSettings};
+use crate::state::{GameState, ServeCountdown};
+
+/// Frames the simulation may run ahead of the other player's last known input
+///
+/// Beyond this the game waits for the other player instead of guessing further.
+pub const MAX_PREDICTION: u32 = 12;
+/// Most inputs repeated in a single packet
+const MAX_INPUTS_PER_PACKET: usize = 64;
+
+/// Plugin connecting two players and resimulating when a guess about the other
+/// player's input turns out wrong
+///
+/// Does nothing unless a NetSession resource has been inserted.
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedFirst,
+            advance_net_session
+                .before(simulation::apply_state_transitions)
+                .run_if(resource_exists::<NetSession>),
+        )
+        .add_systems(
+            RunFixedMainLoop,
+            read_local_input
+                .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop)
+                .run_if(resource_exists::<NetSession>.and(resource_exists::<ActionState>)),
+        )
+        .add_systems(
+            OnEnter(GameState::MainMenu),
+            spawn_connecting_text
+                .run_if(resource_exists::<NetSession>.and(resource_exists::<AssetServer>)),
+        );
+    }
+}
+
+/// How far the session has got in setting up a match
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    /// Waiting to hear from the other player
+    Connecting,
+    /// Connected and waiting for the match to begin
+    Starting,
+    /// Counting frames of the match
+    Running,
+    /// Gave up on joining because the host's rules can't be played
+    Refused,
+}
+
+/// Gameplay state at the start of a frame, to roll back to
+struct Snapshot {
+    /// Frame the snapshot was taken before
+    frame: u32,
+    /// Current game state
+    state: GameState,
+    /// Game state change waiting to be applied
+    next_state: NextState<GameState>,
+    /// Every ball's position and movement, whether it is in play, whether a paddle holds it
+    /// and which paddle last hit it
+    balls: Vec<(Entity, Transform, Velocity, BounceCount, RallyTime, InPlay, Held, LastHit)>,
+    /// Every paddle's position and movement
+    paddles: Vec<(Entity, Transform, Velocity)>,
+    /// The score
+    scoreboard: ScoreBoard,
+    /// Match clock and winner
+    status: MatchStatus,
+    /// Time left before the next serve
+    countdown: ServeCountdown,
+    /// Player whose turn it is to serve
+    serving: ServingSide,
+    /// Gameplay random number generator
+    rng: GameRng,
+    /// Power-ups on the field and effects running
+    power_ups: PowerUpState,
+}
+
+/// An online match against another player
+///
+/// Each player's paddle input is sent to the other `input_delay` frames ahead
+/// of the frame it applies to. When the other player's input for a frame has
+/// not arrived yet it is assumed to be the same as their last one, and if that
+/// guess proves wrong the game is put back to that frame and simulated again.
+/// The host plays the left paddle and decides the seed and match rules.
+#[derive(Resource)]
+pub struct NetSession {
+    /// Connection to the other player
+    transport: Box<dyn Transport>,
+    /// Paddle controlled on this machine
+    local_side: Side,
+    /// Frames between reading the local input and applying it
+    input_delay: u32,
+    /// Current paddle axis of the local player, from -1 to 1
+    pub local_axis: f32,
+    /// Current forward and back axis of the local player, from -1 to 1
+    pub local_depth: f32,
+    /// Whether the local player is holding their Serve action
+    pub local_serve: bool,
+    /// Progress in setting up the match
+    phase: Phase,
+    /// Next frame to be simulated
+    frame: u32,
+    /// Quantized local input for every frame so far, indexed by frame
+    local_inputs: Vec<QuantizedInput>,
+    /// Number of local frames the other player has acknowledged
+    peer_ack: u32,
+    /// The other player's confirmed input for every frame so far, indexed by frame
+    remote_inputs: Vec<QuantizedInput>,
+    /// Guesses made for the other player's unconfirmed frames
+    predictions: BTreeMap<u32, QuantizedInput>,
+    /// Gameplay state before each frame that may still need rolling back
+    snapshots: VecDeque<Snapshot>,
+    /// Whether the last tick was skipped to wait for the other player
+    stalled: bool,
+    /// Number of times the game has been rolled back
+    rollbacks: u32,
+}
+
+impl NetSession {
+    /// Creates a session playing `local_side` over `transport`
+    pub fn new(transport: impl Transport, local_side: Side, input_delay: u32) -> Self {
+        NetSession {
+            transport: Box::new(transport),
+            local_side,
+            input_delay,
+            local_axis: 0.0,
+            local_depth: 0.0,
+            local_serve: false,
+            phase: Phase::Connecting,
+            frame: 0,
+            local_inputs: Vec::new(),
+            peer_ack: 0,
+            remote_inputs: Vec::new(),
+            predictions: BTreeMap::new(),
+            snapshots: VecDeque::new(),
+            stalled: false,
+            rollbacks: 0,
+        }
+    }
+
+    /// Paddle controlled on this machine
+    pub fn local_side(&self) -> Side {
+        self.local_side
+    }
+
+    /// Number of frames of the match simulated so far
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Number of frames for which the other player's input is known
+    pub fn confirmed_frames(&self) -> u32 {
+        self.remote_inputs.len() as u32
+    }
+
+    /// Number of times the game has been rolled back
+    pub fn rollbacks(&self) -> u32 {
+        self.rollbacks
+    }
+
+    /// Whether the connection is set up and the match has begun
+    pub fn is_running(&self) -> bool {
+        self.phase == Phase::Running
+    }
+
+    /// Sends a message to the other player
+    fn send(&mut self, message: &NetMessage) {
+        self.transport.send(&message.encode());
+    }
+
+    /// Reads the other player's packets, returning the earliest frame guessed wrong
+    fn receive(&mut self, world: &mut World) -> Option<u32> {
+        let mut mispredicted = None;
+
+        for packet in self.transport.receive() {
+            let Some(message) = NetMessage::decode(&packet) else {
+                continue;
+            };
+
+            match message {
+                NetMessage::Hello if self.local_side == Side::Left => {
+                    let seed = world.resource::<SimulationSettings>().seed;
+                    let rules = Box::new(world.resource::<MatchRules>().clone());
+                    self.send(&NetMessage::Welcome { seed, rules });
+                    if self.phase == Phase::Connecting {
+                        info!("Player joined, starting the match");
+                        self.start_match(world);
+                    }
+                }
+                NetMessage::Welcome { seed, rules } if self.phase == Phase::Connecting => {
+                    let mut rules = *rules;
+                    rules.clamp_serve_timeout();
+                    if let Err(problems) = rules.validate(world.resource::<PongSettings>()) {
+                        warn!("The host's match rules can't be played ({}), not joining", problems);
+                        self.phase = Phase::Refused;
+                        continue;
+                    }
+                    info!("Joined the match with seed {}", seed);
+                    world.resource_mut::<SimulationSettings>().seed = seed;
+                    world.insert_resource(rules);
+                    self.start_match(world);
+                }
+                NetMessage::Inputs { first_frame, inputs, ack } => {
+                    self.peer_ack = self.peer_ack.max(ack);
+
+                    // Inputs arrive in runs starting no later than what has been confirmed
+                    let known = self.remote_inputs.len() as u32;
+                    if first_frame > known {
+                        continue;
+                    }
+                    let new_inputs = inputs.iter().skip((known - first_frame) as usize);
+                    for (frame, &input) in (known..).zip(new_inputs) {
+                        if let Some(guess) = self.predictions.remove(&frame)
+                            && guess != input
+                        {
+                            mispredicted = Some(mispredicted.map_or(frame, |earliest: u32| earliest.min(frame)));
+                        }
+                        self.remote_inputs.push(input);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        mispredicted
+    }
+
+    /// Moves to the serve countdown once both players are connected
+    fn start_match(&mut self, world: &mut World) {
+        self.phase = Phase::Starting;
+        world.resource_mut::<NextState<GameState>>().set(GameState::Countdown);
+    }
+
+    /// Records the local input for the frame it will apply to and sends the unacknowledged ones
+    fn send_inputs(&mut self) {
+        let target = (self.frame + self.input_delay) as usize;
+        while self.local_inputs.len() < target {
+            self.local_inputs.push(QuantizedInput::default());
+        }
+        if self.local_inputs.len() == target {
+            self.local_inputs.push(QuantizedInput {
+                axis: quantize_axis(self.local_axis),
+                depth: quantize_axis(self.local_depth),
+                serve: self.local_serve,
+            });
+        }
+
+        let first = (self.peer_ack as usize).min(self.local_inputs.len());
+        let last = self.local_inputs.len().min(first + MAX_INPUTS_PER_PACKET);
+        let message = NetMessage::Inputs {
+            first_frame: first as u32,
+            inputs: self.local_inputs[first..last].to_vec(),
+            ack: self.remote_inputs.len() as u32,
+        };
+        self.send(&message);
+    }
+
+    /// Sets both paddles' input for `frame`, guessing the other player's if it is unknown
+    fn apply_inputs(&mut self, world: &mut World, frame: u32) {
+        let local = self.local_inputs.get(frame as usize).copied().unwrap_or_default();
+        let remote = match self.remote_inputs.get(frame as usize) {
+            Some(&input) => input,
+            None => {
+                // A serve is a moment's press, so the other player is assumed not to be serving
+                let guess = self
+                    .remote_inputs
+                    .last()
+                    .map_or(QuantizedInput::default(), |last| QuantizedInput { serve: false, ..*last });
+                self.predictions.insert(frame, guess);
+                guess
+            }
+        };
+
+        let (left, right) = if self.local_side == Side::Left { (local, remote) } else { (remote, local) };
+        set_paddle_input(world, Side::Left, left);
+        set_paddle_input(world, Side::Right, right);
+    }
+
+    /// Puts the game back to the start of `from` and simulates up to the current frame again
+    fn roll_back(&mut self, world: &mut World, from: u32) {
+        let Some(index) = self.snapshots.iter().position(|snapshot| snapshot.frame == from) else {
+            warn!("No snapshot to roll back to frame {}", from);
+            return;
+        };
+        restore_snapshot(world, &self.snapshots[index]);
+        self.snapshots.truncate(index);
+
+        for frame in from..self.frame {
+            self.snapshots.push_back(take_snapshot(world, frame));
+            self.apply_inputs(world, frame);
+            simulation::apply_state_transitions(world);
+            world.run_schedule(FixedUpdate);
+        }
+
+        self.rollbacks += 1;
+        debug!("Rolled back {} frames", self.frame - from);
+    }
+}
+
+/// Sets the PaddleInput of the paddle on `side`
+fn set_paddle_input(world: &mut World, side: Side, quantized: QuantizedInput) {
+    if let Some(mut input) = paddle::find_paddle(world, Paddle::back(side)).and_then(|paddle| world.get_mut::<PaddleInput>(paddle)) {
+        *input = quantized.to_paddle_input();
+    }
+}
+
+/// Copies everything the simulation depends on
+fn take_snapshot(world: &mut World, frame: u32) -> Snapshot {
+    let balls = world
+        .query_filtered::<
+            (Entity, &Transform, &Velocity, &BounceCount, &RallyTime, &InPlay, &Held, &LastHit),
+            With<Ball>,
+        >()
+        .iter(world)
+        .map(|(entity, transform, velocity, bounce_count, rally_time, in_play, held, last_hit)| {
+            (entity, *transform, *velocity, *bounce_count, *rally_time, *in_play, *held, *last_hit)
+        })
+        .collect();
+    let paddles = world
+        .query_filtered::<(Entity, &Transform, &Velocity), With<PaddleInput>>()
+        .iter(world)
+        .map(|(entity, transform, velocity)| (entity, *transform, *velocity))
+        .collect();
+
+    Snapshot {
+        frame,
+        state: *world.resource::<State<GameState>>().get(),
+        next_state: world.resource::<NextState<GameState>>().clone(),
+        balls,
+        paddles,
+        scoreboard: world.resource::<ScoreBoard>().clone(),
+        status: world.resource::<MatchStatus>().clone(),
+        countdown: world.resource::<ServeCountdown>().clone(),
+        serving: *world.resource::<ServingSide>(),
+        rng: world.resource::<GameRng>().clone(),
+        power_ups: world.resource::<PowerUpState>().clone(),
+    }
+}
+
+/// Puts the simulation back to how it was when `snapshot` was taken
+fn restore_snapshot(world: &mut World, snapshot: &Snapshot) {
+    // Go through a proper transition so state scoped entities come and go as usual
+    if *world.resource::<State<GameState>>().get() != snapshot.state {
+        world.resource_mut::<NextState<GameState>>().set(snapshot.state);
+        world.run_schedule(StateTransition);
+    }
+    *world.resource_mut::<NextState<GameState>>() = snapshot.next_state.clone();
+
+    for &(entity, transform, velocity, bounce_count, rally_time, in_play, held, last_hit) in &snapshot.balls {
+        if let Ok(mut ball) = world.get_entity_mut(entity) {
+            ball.insert((transform, velocity, bounce_count, rally_time, in_play, held, last_hit));
+        }
+    }
+    for &(entity, transform, velocity) in &snapshot.paddles {
+        if let Ok(mut paddle) = world.get_entity_mut(entity) {
+            paddle.insert((transform, velocity));
+        }
+    }
+
+    world.insert_resource(snapshot.scoreboard.clone());
+    world.insert_resource(snapshot.status.clone());
+    world.insert_resource(snapshot.countdown.clone());
+    world.insert_resource(snapshot.serving);
+    world.insert_resource(snapshot.rng.clone());
+    world.insert_resource(snapshot.power_ups.clone());
+}
+
+/// Exchanges inputs with the other player and prepares the next frame
+///
+/// Runs at the start of every fixed tick. Rolls back and resimulates if the
+/// other player's input was guessed wrong, then either sets up the paddle inputs
+/// for this tick or, when too far ahead of the other player, stalls the tick.
+pub fn advance_net_session(world: &mut World) {
+    world.resource_scope(|world, mut session: Mut<NetSession>| {
+        if session.phase == Phase::Connecting && session.local_side == Side::Right {
+            session.send(&NetMessage::Hello);
+        }
+
+        let mispredicted = session.receive(world);
+
+        if session.phase == Phase::Starting && *world.resource::<State<GameState>>().get() == GameState::Countdown {
+            session.phase = Phase::Running;
+        }
+        if session.phase != Phase::Running {
+            return;
+        }
+
+        if let Some(frame) = mispredicted
+            && frame < session.frame
+        {
+            session.roll_back(world, frame);
+        }
+        let confirmed = session.remote_inputs.len() as u32;
+        session.snapshots.retain(|snapshot| snapshot.frame >= confirmed);
+
+        session.send_inputs();
+
+        session.stalled = session.frame >= confirmed + MAX_PREDICTION;
+        if session.stalled {
+            return;
+        }
+
+        let frame = session.frame;
+        session.snapshots.push_back(take_snapshot(world, frame));
+        session.apply_inputs(world, frame);
+        session.frame += 1;
+    });
+}
+
+/// Run condition that is false while an online match waits for the other player
+pub fn not_stalled(session: Option<Res<NetSession>>) -> bool {
+    session.is_none_or(|session| !session.stalled)
+}
+
+/// Reads the local player's paddle axes and serve from their bindings
+pub fn read_local_input(actions: Res<ActionState>, mut session: ResMut<NetSession>) {
+    let actions = actions.player(Paddle::back(session.local_side));
+    session.local_axis = actions.paddle_axis();
+    session.local_depth = actions.depth_axis();
+    session.local_serve = actions.pressed(InputAction::Serve);
+}
+
+/// Tells the player the game is waiting for the other player to connect
+pub fn spawn_connecting_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    session: Res<NetSession>,
+) {
+    let message = if session.local_side == Side::Left {
+        "Waiting for a player to join..."
+    } else {
+        "Connecting to host..."
+    };
+
+    commands.spawn((
+        Text2d::new(message),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 24.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, -180.0, 1.0)),
+        StateScoped(GameState::MainMenu),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AiPlayers;
+    use crate::headless::Simulation;
+    use crate::net::{FakeTransport, LinkConditions};
+
+    fn online_player(transport: FakeTransport, side: Side, seed: u64) -> Simulation {
+        let settings = SimulationSettings { seed, ..default() };
+        let mut app = Simulation::build_app(settings, AiPlayers::default(), MatchRules::default());
+        app.insert_resource(NetSession::new(transport, side, 2));
+        Simulation::from_app(app)
+    }
+
+    fn session(simulation: &mut Simulation) -> Mut<'_, NetSession> {
+        simulation.app.world_mut().resource_mut::<NetSession>()
+    }
+
+    fn positions(simulation: &mut Simulation) -> Vec<Vec3> {
+        let world = simulation.app.world_mut();
+        let mut ball_query = world.query_filtered::<&Transform, With<Ball>>();
+        let mut positions: Vec<Vec3> = ball_query.iter(world).map(|transform| transform.translation).collect();
+        let mut paddle_query = world.query::<(&Transform, &Paddle)>();
+        let mut paddles: Vec<(Vec3, Side)> =
+            paddle_query.iter(world).map(|(transform, paddle)| (transform.translation, paddle.side)).collect();
+        paddles.sort_by_key(|(_, side)| side.index());
+        positions.extend(paddles.into_iter().map(|(translation, _)| translation));
+        positions
+    }
+
+    #[test]
+    fn players_agree_despite_latency_and_packet_loss() {
+        let conditions = LinkConditions { latency: 4, loss: 0.2, seed: 3 };
+        let (host_transport, join_transport) = FakeTransport::pair(conditions);
+        // The joining player's own seed is replaced by the host's
+        let mut host = online_player(host_transport, Side::Left, 11);
+        let mut join = online_player(join_transport, Side::Right, 99);
+
+        for tick in 0..1800 {
+            session(&mut host).local_axis = if (tick / 50) % 2 == 0 { 1.0 } else { -0.5 };
+            session(&mut join).local_axis = if (tick / 70) % 3 == 0 { -1.0 } else { 0.8 };
+            host.tick();
+            join.tick();
+        }
+
+        // Let every input arrive, then line both players up on the same frame
+        session(&mut host).local_axis = 0.0;
+        session(&mut join).local_axis = 0.0;
+        host.run(120);
+        join.run(120);
+        while session(&mut host).frame() != session(&mut join).frame() {
+            if session(&mut host).frame() < session(&mut join).frame() {
+                host.tick();
+            } else {
+                join.tick();
+            }
+        }
+
+        // The joining player starts later, so it is mostly the host that has to guess
+        assert!(session(&mut host).rollbacks() + session(&mut join).rollbacks() > 0);
+        assert_eq!(session(&mut host).frame(), session(&mut join).frame());
+        assert_eq!(positions(&mut host), positions(&mut join));
+        let (host_score, join_score) = (host.scoreboard(), join.scoreboard());
+        assert_eq!((host_score.left, host_score.right), (join_score.left, join_score.right));
+    }
+
+    #[test]
+    fn game_waits_for_a_silent_player() {
+        let (host_transport, _silent) = FakeTransport::pair(LinkConditions::default());
+        let mut host = online_player(host_transport, Side::Left, 1);
+
+        host.run(60);
+
+        assert_eq!(host.state(), GameState::MainMenu);
+        assert!(!session(&mut host).is_running());
+    }
+
+    #[test]
+    fn rules_that_cant_be_played_are_refused() {
+        let (host_transport, join_transport) = FakeTransport::pair(LinkConditions::default());
+        let mut host = online_player(host_transport, Side::Left, 1);
+        let mut join = online_player(join_transport, Side::Right, 2);
+        host.app.world_mut().resource_mut::<MatchRules>().win_by = 0;
+
+        for _ in 0..30 {
+            host.tick();
+            join.tick();
+        }
+
+        assert_eq!(join.state(), GameState::MainMenu);
+        assert!(!session(&mut join).is_running());
+        assert_eq!(join.app.world().resource::<MatchRules>().win_by, MatchRules::default().win_by);
+    }
+}
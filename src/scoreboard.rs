@@ -1,18 +1,82 @@
 /// Module for handling the game's scoreboard functionality
 use bevy::prelude::*;
-use crate::window::{WIDTH, HEIGHT};
+use serde::{Deserialize, Serialize};
+use crate::game::PongSet;
+use crate::arena::Arena;
+use crate::four_player::FourPlayerRules;
+use crate::movement::{PaddleMovement, MAX_ZONE_DEPTH};
+use crate::paddle::{Paddle, PaddleSlot, Side};
+use crate::multiball::MultiBallRules;
+use crate::powerup::PowerUpRules;
+use crate::settings::PongSettings;
+use crate::serve::{ServeRule, SERVE_READY};
+use crate::speed::SpeedProgression;
+use crate::state::{InGame, SERVE_COUNTDOWN};
 
-/// Tracks the score for both the left and right players
-#[derive(Resource, Default)]
+/// Plugin keeping score and deciding when a match is won
+///
+/// Counts ScoreEvents and LifeLost events and advances the match clock in
+/// PongSet::Scoring, sending MatchWon once the MatchRules are met. Unless `headless` is set the score and
+/// clock are also shown at the top of the field, which needs the asset server.
+#[derive(Default)]
+pub struct ScoreboardPlugin {
+    /// Leaves out the score text, for apps without assets or rendering
+    pub headless: bool,
+}
+
+impl Plugin for ScoreboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScoreBoard>()
+            .init_resource::<MatchRules>()
+            .init_resource::<MatchStatus>()
+            .add_event::<ScoreEvent>()
+            .add_event::<LifeLost>()
+            .add_event::<MatchWon>()
+            .add_systems(OnEnter(InGame), reset_match)
+            .add_systems(
+                FixedUpdate,
+                (tick_match_clock, update_scoreboard).in_set(PongSet::Scoring),
+            );
+
+        if !self.headless {
+            app.add_systems(OnEnter(InGame), spawn_scoreboard)
+                .add_systems(Update, (update_scoreboard_text, update_match_clock_text));
+        }
+    }
+}
+
+/// Tracks the score for both the left and right players, or every player's lives in a four-player match
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct ScoreBoard {
     /// Score for the left player
     pub left: u32,
     /// Score for the right player
     pub right: u32,
+    /// Lives left for each player of a four-player match, indexed like Side::ALL
+    #[serde(default)]
+    pub lives: [u32; 4],
+}
+
+impl ScoreBoard {
+    /// The score at the start of a match played by `rules`
+    pub fn new(rules: &MatchRules) -> Self {
+        let lives = rules.four_player.map_or(0, |four_player| four_player.lives);
+        ScoreBoard { lives: [lives; 4], ..default() }
+    }
+
+    /// Whether the player on `side` is still defending their goal
+    pub fn in_play(&self, rules: &MatchRules, side: Side) -> bool {
+        rules.sides().contains(&side) && (rules.four_player.is_none() || self.lives[side.index()] > 0)
+    }
+
+    /// The players still defending their goals
+    pub fn sides_in_play<'a>(&'a self, rules: &'a MatchRules) -> impl Iterator<Item = Side> + 'a {
+        rules.sides().iter().copied().filter(|&side| self.in_play(rules, side))
+    }
 }
 
 /// Events triggered when a player scores
-#[derive(Event)]
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ScoreEvent {
     /// Event when left player scores
     LeftScored,
@@ -20,20 +84,342 @@ pub enum ScoreEvent {
     RightScored,
 }
 
+impl ScoreEvent {
+    /// The player who won the point
+    pub fn scorer(self) -> Side {
+        match self {
+            ScoreEvent::LeftScored => Side::Left,
+            ScoreEvent::RightScored => Side::Right,
+        }
+    }
+
+    /// The point won when a ball gets past the player on `side`, if it is one of the two players who score points
+    pub fn conceded_by(side: Side) -> Option<Self> {
+        match side {
+            Side::Left => Some(ScoreEvent::RightScored),
+            Side::Right => Some(ScoreEvent::LeftScored),
+            Side::Top | Side::Bottom => None,
+        }
+    }
+}
+
+/// Event sent when a ball gets past a player in a four-player match
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LifeLost {
+    /// The player who lost a life
+    pub side: Side,
+}
+
+/// Rules deciding when a match is over
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct MatchRules {
+    /// Points needed to win the match
+    pub target_score: u32,
+    /// Lead the winner must have over the other player
+    pub win_by: u32,
+    /// Optional length of the match in seconds, after which the leader wins
+    ///
+    /// If the scores are level when time runs out, the next point wins.
+    pub time_limit: Option<f32>,
+    /// How the ball speeds up during each rally
+    #[serde(default)]
+    pub speed: SpeedProgression,
+    /// Who serves after each point
+    #[serde(default)]
+    pub serve: ServeRule,
+    /// Seconds before the ball is served for a player who doesn't serve it
+    #[serde(default = "default_serve_timeout")]
+    pub serve_timeout: f32,
+    /// How many balls are in play and when a rally ends
+    #[serde(default)]
+    pub multi_ball: MultiBallRules,
+    /// Which power-ups appear on the field and how they behave
+    #[serde(default)]
+    pub power_ups: PowerUpRules,
+    /// Obstacles, portals and goals of the field
+    #[serde(default)]
+    pub arena: Arena,
+    /// Lives for a four-player match, or None for two players
+    #[serde(default)]
+    pub four_player: Option<FourPlayerRules>,
+    /// Whether each side of a two-player match has a second player on a forward paddle
+    #[serde(default)]
+    pub doubles: bool,
+    /// How paddles speed up, slow down and move about in front of their goal
+    #[serde(default)]
+    pub movement: PaddleMovement,
+}
+
+/// Serve timeout for rules saved before it could be changed
+fn default_serve_timeout() -> f32 {
+    SERVE_COUNTDOWN
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        MatchRules {
+            target_score: 11,
+            win_by: 2,
+            time_limit: None,
+            speed: SpeedProgression::default(),
+            serve: ServeRule::default(),
+            serve_timeout: SERVE_COUNTDOWN,
+            multi_ball: MultiBallRules::default(),
+            power_ups: PowerUpRules::default(),
+            arena: Arena::default(),
+            four_player: None,
+            doubles: false,
+            movement: PaddleMovement::default(),
+        }
+    }
+}
+
+impl MatchRules {
+    /// Reads the match rules from command line arguments
+    ///
+    /// Accepts `--target-score <points>`, `--win-by <points>`, `--time-limit <seconds>`,
+    /// `--speed-curve <mode>`, `--serve <rule>` and `--serve-timeout <seconds>`, along
+    /// with the multi-ball and power-up options read by MultiBallRules::from_args and
+    /// PowerUpRules::from_args, the arena read by Arena::from_args, the
+    /// four-player rules read by FourPlayerRules::from_args, `--doubles` and the
+    /// paddle movement read by PaddleMovement::from_args, keeping the default for
+    /// anything missing. Values that fail to parse, scores below one, time limits
+    /// that aren't positive and serve timeouts shorter than SERVE_READY are
+    /// reported and ignored. Doubles can't be combined with four players, and an
+    /// arena in the way of any of the match's paddles is swapped for the Classic
+    /// field.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let args: Vec<String> = args.into_iter().collect();
+        let mut rules = MatchRules {
+            speed: SpeedProgression::from_args(args.clone()).unwrap_or_default(),
+            multi_ball: MultiBallRules::from_args(args.clone()),
+            power_ups: PowerUpRules::from_args(args.clone()),
+            arena: Arena::from_args(args.clone()),
+            four_player: FourPlayerRules::from_args(args.clone()),
+            doubles: args.iter().any(|arg| arg == "--doubles"),
+            movement: PaddleMovement::from_args(args.clone()),
+            ..default()
+        };
+        if rules.doubles && rules.four_player.is_some() {
+            warn!("Doubles can't be played in a four-player match, playing without");
+            rules.doubles = false;
+        }
+        rules.fit_arena(&PongSettings::default());
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let value = match arg.as_str() {
+                "--target-score" | "--win-by" | "--time-limit" | "--serve" | "--serve-timeout" => {
+                    args.next().unwrap_or_default()
+                }
+                _ => continue,
+            };
+            let parsed = match arg.as_str() {
+                "--target-score" => value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|points| *points >= 1)
+                    .map(|points| rules.target_score = points)
+                    .is_some(),
+                "--win-by" => value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|points| *points >= 1)
+                    .map(|points| rules.win_by = points)
+                    .is_some(),
+                "--serve" => ServeRule::from_name(&value).map(|rule| rules.serve = rule).is_some(),
+                "--serve-timeout" => value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|seconds| seconds.is_finite() && *seconds >= SERVE_READY)
+                    .map(|seconds| rules.serve_timeout = seconds)
+                    .is_some(),
+                _ => value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+                    .map(|seconds| rules.time_limit = Some(seconds))
+                    .is_some(),
+            };
+            if !parsed {
+                warn!("Invalid value '{}' for {}", value, arg);
+            }
+        }
+
+        rules
+    }
+
+    /// Checks rules from elsewhere, such as another player or a replay, before they are played
+    ///
+    /// Applies the same limits as from_args, along with SpeedProgression::validate,
+    /// PowerUpRules::validate, MultiBallRules::validate and Arena::validate. The
+    /// arena also has to leave room for every paddle in the match. Every problem
+    /// found is listed in the error.
+    pub fn validate(&self, settings: &PongSettings) -> Result<(), String> {
+        let mut problems = Vec::new();
+        let positive = |value: f32| value.is_finite() && value > 0.0;
+
+        if self.target_score < 1 || self.win_by < 1 {
+            problems.push("scores have to be at least one".to_string());
+        }
+        if self.time_limit.is_some_and(|seconds| !positive(seconds)) {
+            problems.push("the time limit has to be above zero".to_string());
+        }
+        if !(self.serve_timeout.is_finite() && self.serve_timeout >= SERVE_READY) {
+            problems.push(format!("the serve timeout has to be at least {} seconds", SERVE_READY));
+        }
+        if self.four_player.is_some_and(|four_player| four_player.lives < 1) || (self.doubles && self.four_player.is_some()) {
+            problems.push("four-player matches need lives and can't be doubles".to_string());
+        }
+        let movement = &self.movement;
+        if movement.acceleration.is_some_and(|rate| !positive(rate))
+            || !positive(movement.friction)
+            || movement.zone_depth.is_some_and(|depth| !positive(depth) || depth > MAX_ZONE_DEPTH)
+        {
+            problems.push(format!("paddle movement has to be positive, with zones no deeper than {} pixels", MAX_ZONE_DEPTH));
+        }
+        for checked in [self.speed.validate(), self.power_ups.validate(), self.multi_ball.validate()] {
+            problems.extend(checked.err());
+        }
+        match self.arena.validate(settings) {
+            Ok(()) => problems.extend(self.arena.lane_problems(settings, &self.paddles())),
+            Err(err) => problems.push(err.to_string()),
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems.join(", ")) }
+    }
+
+    /// Brings a serve timeout from elsewhere, such as another player's rules, back into range
+    ///
+    /// A timeout that isn't a finite number gets the default, and one shorter than
+    /// SERVE_READY is lengthened to it.
+    pub fn clamp_serve_timeout(&mut self) {
+        self.serve_timeout = if self.serve_timeout.is_finite() { self.serve_timeout.max(SERVE_READY) } else { SERVE_COUNTDOWN };
+    }
+
+    /// Swaps the arena for the Classic field if it is in the way of any paddle in the match
+    ///
+    /// Returns whether the arena was swapped, after reporting why.
+    pub fn fit_arena(&mut self, settings: &PongSettings) -> bool {
+        let problems = self.arena.lane_problems(settings, &self.paddles());
+        if problems.is_empty() {
+            return false;
+        }
+        warn!("The {} arena doesn't fit this match ({}), playing on the Classic field", self.arena.name, problems.join(", "));
+        self.arena = Arena::default();
+        true
+    }
+
+    /// Number of balls a match played by these rules needs, in play or resting
+    pub fn ball_pool_size(&self) -> u32 {
+        self.multi_ball.pool_size().saturating_add(self.power_ups.extra_balls())
+    }
+
+    /// The sides with a paddle in a match played by these rules
+    pub fn sides(&self) -> &'static [Side] {
+        if self.four_player.is_some() { &Side::ALL } else { &Side::ALL[..2] }
+    }
+
+    /// Drops the four-player and doubles rules, which need every player at one computer
+    ///
+    /// Returns whether there was anything to drop.
+    pub fn strip_local_modes(&mut self) -> bool {
+        let local = self.four_player.is_some() || self.doubles;
+        self.four_player = None;
+        self.doubles = false;
+        local
+    }
+
+    /// Every paddle in a match played by these rules, the back paddles first
+    pub fn paddles(&self) -> Vec<Paddle> {
+        let slots: &[PaddleSlot] = if self.doubles { &[PaddleSlot::Back, PaddleSlot::Forward] } else { &[PaddleSlot::Back] };
+        slots.iter().flat_map(|&slot| self.sides().iter().map(move |&side| Paddle { side, slot })).collect()
+    }
+
+    /// Returns the winner of a match with the given score, if there is one yet
+    ///
+    /// A player wins by reaching the target score with the required lead, or by
+    /// being ahead once `elapsed` has run past the time limit. In a four-player
+    /// match the last player with lives left wins, or the one with the most lives
+    /// once time runs out.
+    pub fn winner(&self, scoreboard: &ScoreBoard, elapsed: f32) -> Option<Side> {
+        let reached = |score: u32, other: u32| score >= self.target_score && score >= other + self.win_by;
+        let time_up = self.time_limit.is_some_and(|limit| elapsed >= limit);
+
+        if self.four_player.is_some() {
+            let standing: Vec<Side> = scoreboard.sides_in_play(self).collect();
+            if let [winner] = standing[..] {
+                return Some(winner);
+            }
+            let most = standing.iter().map(|side| scoreboard.lives[side.index()]).max()?;
+            let mut leaders = standing.into_iter().filter(|side| scoreboard.lives[side.index()] == most);
+            return match (time_up, leaders.next(), leaders.next()) {
+                (true, Some(leader), None) => Some(leader),
+                _ => None,
+            };
+        }
+
+        if reached(scoreboard.left, scoreboard.right) || (time_up && scoreboard.left > scoreboard.right) {
+            Some(Side::Left)
+        } else if reached(scoreboard.right, scoreboard.left) || (time_up && scoreboard.right > scoreboard.left) {
+            Some(Side::Right)
+        } else {
+            None
+        }
+    }
+}
+
+/// Progress of the current match
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MatchStatus {
+    /// Seconds played so far
+    pub elapsed: f32,
+    /// Winner of the match once it is over
+    pub winner: Option<Side>,
+}
+
+/// Event sent once when a player wins the match
+#[derive(Event)]
+pub struct MatchWon {
+    /// The player who won
+    pub winner: Side,
+}
+
 /// Component for score text UI elements
 #[derive(Component)]
 pub struct ScoreText {
-    /// Whether this text represents the left player's score (false for right player)
-    pub is_left: bool,
+    /// The player whose score or lives the text shows
+    pub side: Side,
+}
+
+/// Component for the text showing the time left in a timed match
+#[derive(Component)]
+pub struct MatchClockText;
+
+/// Clears the score and match clock ready for a new match
+pub fn reset_match(mut scoreboard: ResMut<ScoreBoard>, rules: Res<MatchRules>, mut status: ResMut<MatchStatus>) {
+    *scoreboard = ScoreBoard::new(&rules);
+    *status = MatchStatus::default();
+}
+
+/// Advances the match clock while the match is being played
+pub fn tick_match_clock(mut status: ResMut<MatchStatus>, time: Res<Time>) {
+    status.elapsed += time.delta_secs();
 }
 
 /// Updates the scoreboard resource when scoring events occur
 ///
 /// Processes ScoreEvent events and increments the appropriate player's score
-/// in the ScoreBoard resource. Logs the updated score to the console.
+/// in the ScoreBoard resource, and takes a life away for every LifeLost event.
+/// Logs the updated score to the console. Then checks the MatchRules and sends a
+/// MatchWon event once a player has won the match.
 pub fn update_scoreboard(
     mut score_events: EventReader<ScoreEvent>,
+    mut life_events: EventReader<LifeLost>,
     mut scoreboard: ResMut<ScoreBoard>,
+    rules: Res<MatchRules>,
+    mut status: ResMut<MatchStatus>,
+    mut match_won_writer: EventWriter<MatchWon>,
 ) {
     for event in score_events.read() {
         match event {
@@ -47,42 +433,76 @@ pub fn update_scoreboard(
             }
         }
     }
+    for event in life_events.read() {
+        // Balls leaving together can't knock out the last two players at once
+        if scoreboard.sides_in_play(&rules).count() > 1 {
+            let lives = &mut scoreboard.lives[event.side.index()];
+            *lives = lives.saturating_sub(1);
+            info!("{:?} loses a life, {} left", event.side, lives);
+        }
+    }
+
+    if status.winner.is_some() {
+        return;
+    }
+    if let Some(winner) = rules.winner(&scoreboard, status.elapsed) {
+        status.winner = Some(winner);
+        match_won_writer.send(MatchWon { winner });
+    }
 }
 
 /// Spawns the scoreboard UI elements for displaying player scores
 ///
-/// Creates two Text2d entities positioned at the top of the screen that will display
-/// the current score for each player. Each text entity starts with a value of "0".
-pub fn spawn_scoreboard(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let half_width = WIDTH as f32 / 2.0;
-    let half_height = HEIGHT as f32 / 2.0;
+/// Creates a Text2d entity for every player that will display their current
+/// score, at the top of the screen for the two players of a normal match and in
+/// front of each goal for the lives of a four-player match. Timed matches also
+/// get a clock between the two scores.
+pub fn spawn_scoreboard(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    rules: Res<MatchRules>,
+    scoreboard: Res<ScoreBoard>,
+    settings: Res<PongSettings>,
+) {
+    let half_width = settings.field_size.x / 2.0;
+    let half_height = settings.field_size.y / 2.0;
     let font = asset_server.load("fonts/FiraSans-Bold.ttf");
-    
-    // Left score text
-    commands.spawn((
-        Text2d::new("0"),
-        TextFont {
-            font: font.clone(),
-            font_size: 50.0,
-            ..default()
-        },
-        TextLayout::new_with_justify(JustifyText::Center),
-        Transform::from_translation(Vec3::new(-half_width / 2.0, half_height - 60.0, 1.0)),
-        ScoreText { is_left: true },
-    ));
-
-    // Right score text
-    commands.spawn((
-        Text2d::new("0"),
-        TextFont {
-            font: font.clone(),
-            font_size: 50.0,
-            ..default()
-        },
-        TextLayout::new_with_justify(JustifyText::Center),
-        Transform::from_translation(Vec3::new(half_width / 2.0, half_height - 60.0, 1.0)),
-        ScoreText { is_left: false },
-    ));
+
+    for &side in rules.sides() {
+        let position = if rules.four_player.is_some() {
+            -side.inward() * side.orient(settings.field_size).x / 4.0
+        } else {
+            Vec2::new(-side.inward().x * half_width / 2.0, half_height - 60.0)
+        };
+        commands.spawn((
+            Text2d::new(score_label(&scoreboard, &rules, side)),
+            TextFont {
+                font: font.clone(),
+                font_size: 50.0,
+                ..default()
+            },
+            TextLayout::new_with_justify(JustifyText::Center),
+            Transform::from_translation(position.extend(1.0)),
+            ScoreText { side },
+            StateScoped(InGame),
+        ));
+    }
+
+    // Clock for timed matches
+    if rules.time_limit.is_some() {
+        commands.spawn((
+            Text2d::new(""),
+            TextFont {
+                font: font.clone(),
+                font_size: 24.0,
+                ..default()
+            },
+            TextLayout::new_with_justify(JustifyText::Center),
+            Transform::from_translation(Vec3::new(0.0, half_height - 24.0, 1.0)),
+            MatchClockText,
+            StateScoped(InGame),
+        ));
+    }
 }
 
 /// Updates the score text UI components with the current score values
@@ -91,13 +511,135 @@ pub fn spawn_scoreboard(mut commands: Commands, asset_server: Res<AssetServer>)
 /// displayed text to match the current values in the ScoreBoard resource.
 pub fn update_scoreboard_text(
     scoreboard: Res<ScoreBoard>,
+    rules: Res<MatchRules>,
     mut query: Query<(&mut Text2d, &ScoreText)>,
 ) {
     for (mut text, score_text) in query.iter_mut() {
-        if score_text.is_left {
-            text.0 = scoreboard.left.to_string();
-        } else {
-            text.0 = scoreboard.right.to_string();
+        text.0 = score_label(&scoreboard, &rules, score_text.side);
+    }
+}
+
+/// Score shown for the player on `side`, their lives in a four-player match
+fn score_label(scoreboard: &ScoreBoard, rules: &MatchRules, side: Side) -> String {
+    match (rules.four_player, side) {
+        (Some(_), side) => scoreboard.lives[side.index()].to_string(),
+        (None, Side::Left) => scoreboard.left.to_string(),
+        (None, _) => scoreboard.right.to_string(),
+    }
+}
+
+/// Updates the match clock text with the time left in the match
+///
+/// Shows minutes and seconds remaining, counting down to zero. Does nothing
+/// for matches without a time limit.
+pub fn update_match_clock_text(
+    rules: Res<MatchRules>,
+    status: Res<MatchStatus>,
+    mut query: Query<&mut Text2d, With<MatchClockText>>,
+) {
+    let Some(limit) = rules.time_limit else {
+        return;
+    };
+    let remaining = (limit - status.elapsed).max(0.0).ceil() as u32;
+
+    for mut text in query.iter_mut() {
+        text.0 = format!("{}:{:02}", remaining / 60, remaining % 60);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(left: u32, right: u32) -> ScoreBoard {
+        ScoreBoard { left, right, ..default() }
+    }
+
+    #[test]
+    fn target_score_needs_a_clear_lead() {
+        let rules = MatchRules::default();
+
+        assert_eq!(rules.winner(&score(11, 5), 0.0), Some(Side::Left));
+        assert_eq!(rules.winner(&score(11, 10), 0.0), None);
+        assert_eq!(rules.winner(&score(12, 14), 0.0), Some(Side::Right));
+    }
+
+    #[test]
+    fn leader_wins_when_time_runs_out() {
+        let rules = MatchRules { time_limit: Some(60.0), ..default() };
+
+        assert_eq!(rules.winner(&score(3, 2), 59.0), None);
+        assert_eq!(rules.winner(&score(3, 2), 60.0), Some(Side::Left));
+        // Level at the whistle goes to sudden death
+        assert_eq!(rules.winner(&score(4, 4), 75.0), None);
+        assert_eq!(rules.winner(&score(4, 5), 80.0), Some(Side::Right));
+    }
+
+    #[test]
+    fn last_player_with_lives_wins_a_four_player_match() {
+        let rules = MatchRules { four_player: Some(FourPlayerRules { lives: 3 }), time_limit: Some(60.0), ..default() };
+        let lives = |lives: [u32; 4]| ScoreBoard { lives, ..ScoreBoard::new(&rules) };
+
+        assert_eq!(ScoreBoard::new(&rules).lives, [3; 4]);
+        assert_eq!(rules.winner(&lives([0, 2, 1, 0]), 30.0), None);
+        assert_eq!(rules.winner(&lives([0, 2, 0, 0]), 30.0), Some(Side::Right));
+        assert_eq!(rules.winner(&lives([1, 2, 2, 0]), 60.0), None);
+        assert_eq!(rules.winner(&lives([1, 2, 3, 0]), 60.0), Some(Side::Top));
+        assert!(!lives([1, 2, 0, 3]).in_play(&rules, Side::Top));
+    }
+
+    #[test]
+    fn rules_are_parsed_from_args() {
+        let args = ["--target-score", "5", "--win-by", "1", "--time-limit", "x", "--serve", "loser"].map(String::from);
+
+        let rules = MatchRules::from_args(args);
+
+        assert_eq!(rules.target_score, 5);
+        assert_eq!(rules.win_by, 1);
+        assert_eq!(rules.time_limit, None);
+        assert_eq!(rules.serve, ServeRule::Loser);
+
+        let rules = MatchRules::from_args(["--target-score", "0", "--win-by", "0"].map(String::from));
+
+        assert_eq!(rules.target_score, 11);
+        assert_eq!(rules.win_by, 2);
+        for value in ["-5", "0", "NaN", "inf"] {
+            let rules = MatchRules::from_args(["--time-limit", value].map(String::from));
+            assert_eq!(rules.time_limit, None, "accepted {value}");
         }
+        assert_eq!(MatchRules::from_args(["--time-limit", "90"].map(String::from)).time_limit, Some(90.0));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn unusable_serve_timeouts_are_refused() {
+        for value in ["-1", "NaN", "inf", "0.1"] {
+            let rules = MatchRules::from_args(["--serve-timeout", value].map(String::from));
+            assert_eq!(rules.serve_timeout, SERVE_COUNTDOWN, "accepted {value}");
+        }
+
+        let mut rules = MatchRules { serve_timeout: f32::NAN, ..default() };
+        rules.clamp_serve_timeout();
+        assert_eq!(rules.serve_timeout, SERVE_COUNTDOWN);
+        rules.serve_timeout = -1.0;
+        rules.clamp_serve_timeout();
+        assert_eq!(rules.serve_timeout, SERVE_READY);
+    }
+
+    #[test]
+    fn rules_from_elsewhere_are_validated() {
+        let settings = PongSettings::default();
+        assert_eq!(MatchRules::default().validate(&settings), Ok(()));
+
+        let mut overloaded = MatchRules::default();
+        overloaded.multi_ball.balls = u32::MAX;
+        let unusable = [
+            MatchRules { win_by: 0, ..default() },
+            MatchRules { time_limit: Some(f32::NAN), ..default() },
+            MatchRules { doubles: true, four_player: Some(FourPlayerRules::default()), ..default() },
+            overloaded,
+        ];
+        for rules in unusable {
+            assert!(rules.validate(&settings).is_err(), "accepted {rules:?}");
+        }
+    }
+}
@@ -0,0 +1,237 @@
+/// Module for serving the ball from the paddle of the player whose turn it is
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::ball::{Ball, InPlay, LastHit, Velocity};
+use crate::game::PongSet;
+use crate::events::RallyEnded;
+use crate::four_player::FourPlayerRules;
+use crate::paddle::{self, Paddle, PaddleInput, Side};
+use crate::scoreboard::{self, MatchRules, ScoreBoard, ScoreEvent};
+use crate::settings::PongSettings;
+use crate::simulation::GameRng;
+use crate::state::{GameState, InGame, ServeCountdown};
+
+/// Seconds the ball rests on the paddle before the server is allowed to launch it
+pub const SERVE_READY: f32 = 0.5;
+/// Gap in pixels between the serving paddle and the ball resting in front of it
+const SERVE_GAP: f32 = 2.0;
+/// Paddle speed in pixels per second below which the paddle counts as standing still
+const AIM_THRESHOLD: f32 = 1.0;
+
+/// Who serves after a point in a two-player match
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServeRule {
+    /// The player who lost the point
+    Loser,
+    /// The player who won the point
+    #[default]
+    Winner,
+    /// The players take turns, swapping every `every` points
+    Alternate {
+        /// Points served by one player before the other takes over
+        every: u32,
+    },
+}
+
+impl ServeRule {
+    /// Parses `loser`, `winner`, `alternate` (every 2 points) or `alternate:<points>`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.split_once(':') {
+            None if name.eq_ignore_ascii_case("loser") => Some(ServeRule::Loser),
+            None if name.eq_ignore_ascii_case("winner") => Some(ServeRule::Winner),
+            None if name.eq_ignore_ascii_case("alternate") => Some(ServeRule::Alternate { every: 2 }),
+            Some((rule, every)) if rule.eq_ignore_ascii_case("alternate") => match every.parse() {
+                Ok(every) if every > 0 => Some(ServeRule::Alternate { every }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The player serving next, after `scorer` won a point and `points_played` have been played
+    ///
+    /// The left player serves first, so alternating serves depend only on how many
+    /// points have been played.
+    pub fn next_server(self, scorer: Side, points_played: u32) -> Side {
+        match self {
+            ServeRule::Loser => scorer.opposite(),
+            ServeRule::Winner => scorer,
+            ServeRule::Alternate { every } => {
+                if (points_played / every.max(1)).is_multiple_of(2) {
+                    Side::Left
+                } else {
+                    Side::Right
+                }
+            }
+        }
+    }
+}
+
+/// The player whose turn it is to serve
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServingSide(pub Side);
+
+impl Default for ServingSide {
+    fn default() -> Self {
+        ServingSide(Side::Left)
+    }
+}
+
+/// Plugin for the serve at the start of every point
+///
+/// During GameState::Countdown the ball rests in front of the serving player's
+/// paddle and moves along with it. The server launches it with their Serve
+/// action once SERVE_READY has passed, or it is launched for them when the serve
+/// countdown runs out. The direction the paddle is moving in at that moment aims
+/// the serve to one side or the other. After each point the MatchRules' ServeRule
+/// picks the next server, and in a four-player match the player who lost a life
+/// serves.
+pub struct ServePlugin;
+
+impl Plugin for ServePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ServingSide>()
+            .add_systems(OnEnter(InGame), reset_serving_side)
+            .add_systems(
+                FixedUpdate,
+                park_ball
+                    .in_set(PongSet::Movement)
+                    .after(paddle::move_paddles)
+                    .run_if(in_state(GameState::Countdown)),
+            )
+            .add_systems(
+                FixedUpdate,
+                choose_next_server
+                    .in_set(PongSet::Scoring)
+                    .after(scoreboard::update_scoreboard),
+            )
+            .add_systems(
+                FixedUpdate,
+                launch_serve.in_set(PongSet::Flow).run_if(in_state(GameState::Countdown)),
+            );
+    }
+}
+
+/// Gives the first serve of a new match to the left player
+pub fn reset_serving_side(mut serving: ResMut<ServingSide>) {
+    *serving = ServingSide::default();
+}
+
+/// Holds the ball in play just in front of the serving player's paddle
+///
+/// In a doubles match the back paddle serves for its side.
+pub fn park_ball(
+    serving: Res<ServingSide>,
+    settings: Res<PongSettings>,
+    paddle_query: Query<(&Transform, &Paddle), Without<Ball>>,
+    mut ball_query: Query<(&mut Transform, &mut Velocity, &InPlay), With<Ball>>,
+) {
+    let side = serving.0;
+    let Some((paddle, _)) = paddle_query.iter().find(|(_, paddle)| **paddle == Paddle::back(side)) else {
+        return;
+    };
+
+    let depth = (settings.paddle_size.x + side.orient(settings.ball_size).x) / 2.0 + SERVE_GAP;
+    let position = paddle.translation.truncate() + side.inward() * depth;
+    for (mut transform, mut velocity, _) in ball_query.iter_mut().filter(|(_, _, in_play)| in_play.0) {
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        *velocity = Velocity { x: 0.0, y: 0.0 };
+    }
+}
+
+/// Runs the serve countdown and launches the ball when the server serves or time runs out
+///
+/// The ball leaves at the served speed towards the other side of the field,
+/// heading off the way the serving paddle is moving, with the serving paddle as
+/// its last hit. A paddle standing still serves in a direction drawn from the GameRng.
+#[allow(clippy::too_many_arguments)]
+pub fn launch_serve(
+    time: Res<Time>,
+    serving: Res<ServingSide>,
+    settings: Res<PongSettings>,
+    mut countdown: ResMut<ServeCountdown>,
+    mut rng: ResMut<GameRng>,
+    paddle_query: Query<(&PaddleInput, &Velocity, &Paddle), Without<Ball>>,
+    mut ball_query: Query<(&mut Velocity, &mut LastHit, &InPlay), With<Ball>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    countdown.0.tick(time.delta());
+
+    let side = serving.0;
+    let Some((input, paddle_velocity, _)) =
+        paddle_query.iter().find(|(_, _, paddle)| **paddle == Paddle::back(side))
+    else {
+        return;
+    };
+
+    let ready = countdown.0.elapsed_secs() >= SERVE_READY;
+    if !(input.serve && ready) && !countdown.0.finished() {
+        return;
+    }
+
+    let along = side.orient(Vec2::new(paddle_velocity.x, paddle_velocity.y)).y;
+    let aim = if along > AIM_THRESHOLD {
+        1.0
+    } else if along < -AIM_THRESHOLD {
+        -1.0
+    } else if rng.0.random_bool(0.5) {
+        1.0
+    } else {
+        -1.0
+    };
+    let served = (side.inward() + side.orient(Vec2::new(0.0, aim))) * settings.ball_speed;
+    for (mut velocity, mut last_hit, _) in ball_query.iter_mut().filter(|(.., in_play)| in_play.0) {
+        *velocity = Velocity { x: served.x, y: served.y };
+        last_hit.0 = Some(Paddle::back(side));
+    }
+    next_state.set(GameState::Playing);
+}
+
+/// Picks who serves the next point once a rally is over
+///
+/// Points scored in a two-player match go by the MatchRules' ServeRule. In a
+/// four-player match the player the last ball got past serves, or the next player
+/// still in if that was their last life.
+pub fn choose_next_server(
+    mut score_events: EventReader<ScoreEvent>,
+    mut rally_ends: EventReader<RallyEnded>,
+    scoreboard: Res<ScoreBoard>,
+    rules: Res<MatchRules>,
+    mut serving: ResMut<ServingSide>,
+) {
+    for event in score_events.read() {
+        serving.0 = rules.serve.next_server(event.scorer(), scoreboard.left + scoreboard.right);
+    }
+    for event in rally_ends.read() {
+        if rules.four_player.is_some() {
+            serving.0 = FourPlayerRules::next_server(event.conceded, |side| scoreboard.in_play(&rules, side));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serve_rules_pick_the_next_server() {
+        assert_eq!(ServeRule::Loser.next_server(Side::Left, 1), Side::Right);
+        assert_eq!(ServeRule::Winner.next_server(Side::Left, 1), Side::Left);
+
+        let alternate = ServeRule::Alternate { every: 2 };
+        let servers: Vec<Side> = (1..=5).map(|played| alternate.next_server(Side::Right, played)).collect();
+        assert_eq!(servers, [Side::Left, Side::Right, Side::Right, Side::Left, Side::Left]);
+    }
+
+    #[test]
+    fn serve_rules_are_parsed_by_name() {
+        assert_eq!(ServeRule::from_name("loser"), Some(ServeRule::Loser));
+        assert_eq!(ServeRule::from_name("Winner"), Some(ServeRule::Winner));
+        assert_eq!(ServeRule::from_name("alternate"), Some(ServeRule::Alternate { every: 2 }));
+        assert_eq!(ServeRule::from_name("alternate:5"), Some(ServeRule::Alternate { every: 5 }));
+        assert_eq!(ServeRule::from_name("alternate:0"), None);
+        assert_eq!(ServeRule::from_name("sideways"), None);
+    }
+}
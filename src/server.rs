@@ -0,0 +1,673 @@
+/// Module for the authoritative dedicated server and the connection clients use to reach it
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ai::AiPlayers;
+use crate::arena::Arena;
+use crate::ball::{Ball, InPlay};
+use crate::broadcast::{EventStream, MatchEventLog};
+use crate::discovery::{Announcer, Lobby, LobbyKind};
+use crate::headless::Simulation;
+use crate::movement::PaddleMovement;
+use crate::net::{Transport, UdpTransport};
+use crate::paddle::{self, Paddle, PaddleInput, Side};
+use crate::powerup::PowerUpState;
+use crate::scoreboard::MatchRules;
+use crate::serve::ServingSide;
+use crate::settings::PongSettings;
+use crate::simulation::SimulationSettings;
+use crate::state::{GameState, ServeCountdown};
+
+/// Port the server listens on when `--port` is not given
+pub const DEFAULT_SERVER_PORT: u16 = 7878;
+/// Number of ticks between snapshots sent to clients
+pub const SNAPSHOT_INTERVAL: u64 = 2;
+/// Time without hearing from a client before it is dropped
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time between a client's join requests while waiting for an answer, and between
+/// a spectator's keepalives
+const JOIN_INTERVAL: Duration = Duration::from_millis(500);
+/// Seconds the game over screen is shown before the next match starts
+const REMATCH_DELAY: f32 = 5.0;
+/// Largest datagram read from the socket, big enough for a Welcome carrying an arena
+const MAX_PACKET_SIZE: usize = 8192;
+
+/// What a client connected to the server does
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientRole {
+    /// Controls the paddle on the given side
+    Player(Side),
+    /// Only watches
+    Spectator,
+}
+
+/// Messages sent from a client to the server
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// Asks to take part, as a player if a paddle is free and `spectate` is not set
+    Join {
+        /// Whether to watch rather than play
+        spectate: bool,
+    },
+    /// Where the player wants their paddle to be
+    Input {
+        /// Height of the paddle's center as shown on the player's screen
+        paddle_y: f32,
+        /// Whether the player is holding their Serve action
+        #[serde(default)]
+        serve: bool,
+    },
+    /// Tells the server the client is going away
+    Leave,
+}
+
+/// The state of the match as sent to clients
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ServerSnapshot {
+    /// Server tick the snapshot was taken on
+    pub tick: u64,
+    /// Current game state
+    pub state: GameState,
+    /// Position of every ball in play
+    pub balls: Vec<Vec2>,
+    /// Positions of the left and right paddles
+    pub paddles: [Vec2; 2],
+    /// Scores of the left and right players
+    pub score: [u32; 2],
+    /// Winner of the match, once there is one
+    pub winner: Option<Side>,
+    /// Seconds left in the serve countdown
+    pub countdown: f32,
+    /// Player whose turn it is to serve
+    pub serving: Side,
+    /// Power-ups on the field and effects running
+    pub power_ups: PowerUpState,
+    /// Seconds played so far, which moves the arena's obstacles
+    #[serde(default)]
+    pub elapsed: f32,
+}
+
+/// Messages sent from the server to a client
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// Answers a join request with the role the client was given
+    Welcome {
+        /// The client's role
+        role: ClientRole,
+        /// Arena the server's matches are played in
+        #[serde(default)]
+        arena: Arena,
+    },
+    /// The latest state of the match
+    Snapshot(ServerSnapshot),
+}
+
+/// Encodes a message for sending
+fn encode(message: &impl Serialize) -> Vec<u8> {
+    ron::to_string(message).expect("server messages always serialize").into_bytes()
+}
+
+/// Decodes a received packet, returning None for anything malformed
+fn decode<T: for<'de> Deserialize<'de>>(packet: &[u8]) -> Option<T> {
+    ron::de::from_bytes(packet).ok()
+}
+
+/// Turns where a player wants their paddle into a PaddleInput axis
+///
+/// The server never trusts a client's paddle position: the paddle moves towards
+/// `requested` at no more than `max_step` per tick, the distance covered at the
+/// paddle speed, whatever the client claims. Non-finite requests are ignored.
+pub fn validate_paddle_target(current: f32, requested: f32, max_step: f32) -> f32 {
+    if !requested.is_finite() || max_step <= 0.0 {
+        return 0.0;
+    }
+    ((requested - current) / max_step).clamp(-1.0, 1.0)
+}
+
+/// Sends and receives unreliable datagrams for the server, which talks to every client at once
+pub trait ServerTransport: Send + Sync + 'static {
+    /// Sends a packet to the client at `address`
+    fn send_to(&mut self, packet: &[u8], address: SocketAddr);
+    /// Returns every packet that has arrived since the last call, with its sender
+    fn receive(&mut self) -> Vec<(SocketAddr, Vec<u8>)>;
+    /// Address clients reach the server on
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+impl ServerTransport for UdpSocket {
+    fn send_to(&mut self, packet: &[u8], address: SocketAddr) {
+        // A lost snapshot is replaced by the next one
+        let _ = UdpSocket::send_to(self, packet, address);
+    }
+
+    fn receive(&mut self) -> Vec<(SocketAddr, Vec<u8>)> {
+        let mut packets = Vec::new();
+        let mut buffer = [0; MAX_PACKET_SIZE];
+
+        loop {
+            match self.recv_from(&mut buffer) {
+                Ok((length, sender)) => packets.push((sender, buffer[..length].to_vec())),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // Errors about packets to clients that have gone away surface here on some platforms,
+                // so try again next tick
+                Err(_) => break,
+            }
+        }
+
+        packets
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        UdpSocket::local_addr(self)
+    }
+}
+
+/// Packets waiting to be read by each address on a FakeNetwork, with their sender
+type Mailboxes = HashMap<SocketAddr, VecDeque<(SocketAddr, Vec<u8>)>>;
+
+/// In-process network for tests, delivering every packet by the next receive call
+///
+/// Hands out one end for a server and as many client ends as needed, each with an
+/// address of its own, so a GameServer can be tested without sockets or sleeping.
+#[derive(Clone, Default)]
+pub struct FakeNetwork {
+    /// Packets on their way to each end
+    mailboxes: Arc<Mutex<Mailboxes>>,
+}
+
+impl FakeNetwork {
+    /// Creates the end a server listens on at `address`
+    pub fn server(&self, address: SocketAddr) -> FakeServerEnd {
+        self.mailboxes.lock().unwrap().entry(address).or_default();
+        FakeServerEnd { network: self.clone(), address }
+    }
+
+    /// Creates a client end at `address` talking to the server at `server`
+    pub fn client(&self, address: SocketAddr, server: SocketAddr) -> FakeClientEnd {
+        self.mailboxes.lock().unwrap().entry(address).or_default();
+        FakeClientEnd { network: self.clone(), address, server }
+    }
+
+    /// Leaves a packet from `from` for whoever is at `to`, dropping it if nobody is
+    fn deliver(&self, from: SocketAddr, to: SocketAddr, packet: &[u8]) {
+        if let Some(mailbox) = self.mailboxes.lock().unwrap().get_mut(&to) {
+            mailbox.push_back((from, packet.to_vec()));
+        }
+    }
+
+    /// Takes every packet waiting for `address`
+    fn collect(&self, address: SocketAddr) -> Vec<(SocketAddr, Vec<u8>)> {
+        self.mailboxes.lock().unwrap().get_mut(&address).map_or_else(Vec::new, |mailbox| mailbox.drain(..).collect())
+    }
+}
+
+/// A server's end of a FakeNetwork
+pub struct FakeServerEnd {
+    /// Network the end belongs to
+    network: FakeNetwork,
+    /// Address of this end
+    address: SocketAddr,
+}
+
+impl ServerTransport for FakeServerEnd {
+    fn send_to(&mut self, packet: &[u8], address: SocketAddr) {
+        self.network.deliver(self.address, address, packet);
+    }
+
+    fn receive(&mut self) -> Vec<(SocketAddr, Vec<u8>)> {
+        self.network.collect(self.address)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.address)
+    }
+}
+
+/// A client's end of a FakeNetwork
+pub struct FakeClientEnd {
+    /// Network the end belongs to
+    network: FakeNetwork,
+    /// Address of this end
+    address: SocketAddr,
+    /// Address of the server
+    server: SocketAddr,
+}
+
+impl Transport for FakeClientEnd {
+    fn send(&mut self, packet: &[u8]) {
+        self.network.deliver(self.address, self.server, packet);
+    }
+
+    fn receive(&mut self) -> Vec<Vec<u8>> {
+        // Only the server's packets count, as on a connected socket
+        let server = self.server;
+        self.network.collect(self.address).into_iter().filter(|(sender, _)| *sender == server).map(|(_, packet)| packet).collect()
+    }
+}
+
+/// Command line options for the dedicated server
+pub struct ServerConfig {
+    /// UDP port to listen on
+    pub port: u16,
+}
+
+impl ServerConfig {
+    /// Reads the server options from command line arguments
+    ///
+    /// `--port <port>` sets the UDP port, DEFAULT_SERVER_PORT by default.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        let mut config = ServerConfig { port: DEFAULT_SERVER_PORT };
+
+        while let Some(arg) = args.next() {
+            if arg == "--port" && let Some(port) = args.next().and_then(|value| value.parse().ok()) {
+                config.port = port;
+            }
+        }
+
+        config
+    }
+}
+
+/// A client the server has heard from
+struct Client {
+    /// What the client does
+    role: ClientRole,
+    /// When the client last sent anything
+    last_seen: Instant,
+    /// Latest paddle position asked for by a player
+    paddle_y: Option<f32>,
+    /// Whether the player last asked to serve
+    serve: bool,
+}
+
+/// Dedicated server running the only true copy of the match
+///
+/// Simulates the match headlessly, takes paddle positions from the two players,
+/// moving their paddles no faster than the paddle speed allows, and sends
+/// snapshots of the result to players and spectators alike. A match starts once
+/// both paddles have a player, and another follows a few seconds after it ends.
+pub struct GameServer {
+    /// Transport all clients talk to
+    transport: Box<dyn ServerTransport>,
+    /// The match
+    simulation: Simulation,
+    /// Everyone connected, by address
+    clients: HashMap<SocketAddr, Client>,
+    /// Number of ticks simulated
+    tick: u64,
+    /// Seconds spent on the game over screen
+    game_over_time: f32,
+    /// Announces the server on the local network, with the lobby name
+    advertisement: Option<(Announcer, String)>,
+}
+
+impl GameServer {
+    /// Starts a server listening on `address`
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        settings: SimulationSettings,
+        rules: MatchRules,
+    ) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(GameServer::new(socket, settings, rules))
+    }
+
+    /// Starts a server talking to its clients over `transport`
+    pub fn new(
+        transport: impl ServerTransport,
+        settings: SimulationSettings,
+        mut rules: MatchRules,
+    ) -> Self {
+        if rules.strip_local_modes() {
+            warn!("Four-player and doubles matches can only be played on one computer, playing one against one");
+        }
+        // Players ask for where their paddle should be, which leaves no room for inertia or moving off the line
+        if rules.movement != PaddleMovement::default() {
+            warn!("The server moves paddles straight to where players ask, playing with instant paddle movement");
+            rules.movement = PaddleMovement::default();
+        }
+
+        GameServer {
+            transport: Box::new(transport),
+            simulation: Simulation::new(settings, AiPlayers::default(), rules),
+            clients: HashMap::new(),
+            tick: 0,
+            game_over_time: 0.0,
+            advertisement: None,
+        }
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.transport.local_addr()
+    }
+
+    /// The simulated match
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    /// Writes the match's serves, bounces and points to `stream` as they happen
+    pub fn stream_events(&mut self, stream: EventStream) {
+        self.simulation.app.insert_resource(stream).init_resource::<MatchEventLog>();
+    }
+
+    /// Advertises the server on the local network as `name`
+    pub fn advertise(&mut self, announcer: Announcer, name: String) {
+        self.advertisement = Some((announcer, name));
+    }
+
+    /// Runs the server forever, one tick per timestep
+    pub fn run(&mut self) {
+        let timestep = Duration::from_secs_f64(self.simulation.timestep());
+        let mut next_tick = Instant::now();
+
+        loop {
+            self.step();
+            next_tick += timestep;
+            if let Some(wait) = next_tick.checked_duration_since(Instant::now()) {
+                std::thread::sleep(wait);
+            } else {
+                // Fell behind, so don't try to catch up with a burst of ticks
+                next_tick = Instant::now();
+            }
+        }
+    }
+
+    /// Handles client messages, advances the match by one tick and sends out a snapshot
+    pub fn step(&mut self) {
+        self.receive();
+        self.drop_silent_clients();
+        self.announce();
+        self.manage_match();
+        self.apply_inputs();
+        self.simulation.tick();
+        self.tick += 1;
+
+        if self.tick.is_multiple_of(SNAPSHOT_INTERVAL) {
+            let snapshot = self.snapshot();
+            self.broadcast(&ServerMessage::Snapshot(snapshot));
+        }
+    }
+
+    /// Reads every waiting packet
+    fn receive(&mut self) {
+        for (sender, packet) in self.transport.receive() {
+            let Some(message) = decode::<ClientMessage>(&packet) else {
+                continue;
+            };
+            self.handle_message(sender, message);
+        }
+    }
+
+    /// Acts on one message from a client
+    fn handle_message(&mut self, sender: SocketAddr, message: ClientMessage) {
+        match message {
+            ClientMessage::Join { spectate } => {
+                let role = match self.clients.get(&sender).map(|client| client.role) {
+                    Some(role) => role,
+                    None => {
+                        let role = self.free_role(spectate);
+                        info!("{} joined as {:?}", sender, role);
+                        self.clients.insert(sender, Client { role, last_seen: Instant::now(), paddle_y: None, serve: false });
+                        role
+                    }
+                };
+                self.clients.get_mut(&sender).unwrap().last_seen = Instant::now();
+                let arena = self.simulation.app.world().resource::<MatchRules>().arena.clone();
+                self.send(sender, &ServerMessage::Welcome { role, arena });
+            }
+            ClientMessage::Input { paddle_y, serve } => {
+                if let Some(client) = self.clients.get_mut(&sender) {
+                    client.last_seen = Instant::now();
+                    client.paddle_y = Some(paddle_y);
+                    client.serve = serve;
+                }
+            }
+            ClientMessage::Leave => {
+                if self.clients.remove(&sender).is_some() {
+                    info!("{} left", sender);
+                }
+            }
+        }
+    }
+
+    /// Picks a free paddle for a new client, or makes them a spectator
+    fn free_role(&self, spectate: bool) -> ClientRole {
+        if spectate {
+            return ClientRole::Spectator;
+        }
+        [Side::Left, Side::Right]
+            .into_iter()
+            .map(ClientRole::Player)
+            .find(|role| self.clients.values().all(|client| client.role != *role))
+            .unwrap_or(ClientRole::Spectator)
+    }
+
+    /// Forgets clients that have not been heard from for a while
+    fn drop_silent_clients(&mut self) {
+        self.clients.retain(|address, client| {
+            let alive = client.last_seen.elapsed() < CLIENT_TIMEOUT;
+            if !alive {
+                info!("{} timed out", address);
+            }
+            alive
+        });
+    }
+
+    /// Tells the local network about the server and how many players it has
+    fn announce(&mut self) {
+        let players = self.clients.values().filter(|client| client.role != ClientRole::Spectator).count() as u32;
+        let port = self.transport.local_addr().map_or(0, |address| address.port());
+        let target_score = self.simulation.app.world().resource::<MatchRules>().target_score;
+        let Some((announcer, name)) = &mut self.advertisement else {
+            return;
+        };
+
+        announcer.announce(&Lobby {
+            name: name.clone(),
+            port,
+            kind: LobbyKind::Server,
+            target_score,
+            players,
+        });
+    }
+
+    /// Starts matches when both players are present and stops them when one leaves
+    fn manage_match(&mut self) {
+        let has_player = |side| self.clients.values().any(|client| client.role == ClientRole::Player(side));
+        let both_players = has_player(Side::Left) && has_player(Side::Right);
+
+        match self.simulation.state() {
+            GameState::MainMenu if both_players => {
+                info!("Both players connected, starting a match");
+                self.simulation.start_match();
+            }
+            GameState::MainMenu => {}
+            _ if !both_players => {
+                info!("A player left, waiting for players");
+                self.simulation.set_state(GameState::MainMenu);
+            }
+            GameState::GameOver => {
+                self.game_over_time += self.simulation.timestep() as f32;
+                if self.game_over_time >= REMATCH_DELAY {
+                    self.game_over_time = 0.0;
+                    self.simulation.start_match();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Steers each player's paddle towards the position they asked for
+    fn apply_inputs(&mut self) {
+        let world = self.simulation.app.world_mut();
+        let settings = world.resource::<PongSettings>();
+        let max_step = settings.paddle_speed * self.simulation.timestep() as f32;
+
+        for client in self.clients.values() {
+            let (ClientRole::Player(side), Some(requested)) = (client.role, client.paddle_y) else {
+                continue;
+            };
+            let world = self.simulation.app.world_mut();
+            let Some(paddle) = paddle::find_paddle(world, Paddle::back(side)) else {
+                continue;
+            };
+            if let Ok((transform, mut input)) = world.query::<(&Transform, &mut PaddleInput)>().get_mut(world, paddle) {
+                input.axis = validate_paddle_target(transform.translation.y, requested, max_step);
+                input.serve = client.serve;
+            }
+        }
+    }
+
+    /// Captures the match as clients see it
+    fn snapshot(&mut self) -> ServerSnapshot {
+        let state = self.simulation.state();
+        let scoreboard = self.simulation.scoreboard();
+        let score = [scoreboard.left, scoreboard.right];
+        let winner = self.simulation.status().winner;
+        let elapsed = self.simulation.status().elapsed;
+
+        let world = self.simulation.app.world_mut();
+        let countdown = world.resource::<ServeCountdown>().0.remaining_secs();
+        let serving = world.resource::<ServingSide>().0;
+        let power_ups = world.resource::<PowerUpState>().clone();
+        let balls = world
+            .query_filtered::<(&Transform, &InPlay), With<Ball>>()
+            .iter(world)
+            .filter(|(_, in_play)| in_play.0)
+            .map(|(transform, _)| transform.translation.truncate())
+            .collect();
+        let [left, right] = [Side::Left, Side::Right].map(|side| {
+            paddle::find_paddle(world, Paddle::back(side))
+                .and_then(|paddle| world.get::<Transform>(paddle))
+                .map_or(Vec2::ZERO, |transform| transform.translation.truncate())
+        });
+
+        ServerSnapshot {
+            tick: self.tick,
+            state,
+            balls,
+            paddles: [left, right],
+            score,
+            winner,
+            countdown,
+            serving,
+            power_ups,
+            elapsed,
+        }
+    }
+
+    /// Sends a message to one client
+    fn send(&mut self, address: SocketAddr, message: &ServerMessage) {
+        self.transport.send_to(&encode(message), address);
+    }
+
+    /// Sends a message to every client
+    fn broadcast(&mut self, message: &ServerMessage) {
+        let packet = encode(message);
+        for address in self.clients.keys() {
+            self.transport.send_to(&packet, *address);
+        }
+    }
+}
+
+/// A client's connection to a GameServer
+pub struct ServerConnection {
+    /// Transport to the server
+    transport: Box<dyn Transport>,
+    /// Whether to join as a spectator
+    spectate: bool,
+    /// Role given by the server, once it has answered
+    role: Option<ClientRole>,
+    /// Arena the server plays in, once it has answered
+    arena: Option<Arena>,
+    /// When the last join request was sent
+    last_join: Option<Instant>,
+}
+
+impl ServerConnection {
+    /// Opens a connection to the server at `address`, such as 127.0.0.1:7878
+    pub fn connect(address: impl ToSocketAddrs, spectate: bool) -> io::Result<Self> {
+        let server = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "no server address"))?;
+        Ok(ServerConnection::new(UdpTransport::connect(server)?, spectate))
+    }
+
+    /// Talks to a server over `transport`, joining as a spectator if `spectate` is set
+    pub fn new(transport: impl Transport, spectate: bool) -> Self {
+        ServerConnection { transport: Box::new(transport), spectate, role: None, arena: None, last_join: None }
+    }
+
+    /// Role given by the server, once it has answered
+    pub fn role(&self) -> Option<ClientRole> {
+        self.role
+    }
+
+    /// Arena the server plays in, once it has answered
+    pub fn arena(&self) -> Option<&Arena> {
+        self.arena.as_ref()
+    }
+
+    /// Keeps the connection alive and returns the snapshots received since the last call
+    ///
+    /// Join requests are repeated until the server answers. Spectators keep repeating
+    /// them so the server knows they are still watching.
+    pub fn poll(&mut self) -> Vec<ServerSnapshot> {
+        let keep_alive = self.role.is_none() || self.role == Some(ClientRole::Spectator);
+        if keep_alive && self.last_join.is_none_or(|sent| sent.elapsed() >= JOIN_INTERVAL) {
+            self.send(&ClientMessage::Join { spectate: self.spectate });
+            self.last_join = Some(Instant::now());
+        }
+
+        let mut snapshots = Vec::new();
+        for packet in self.transport.receive() {
+            match decode::<ServerMessage>(&packet) {
+                Some(ServerMessage::Welcome { role, arena }) => {
+                    self.role = Some(role);
+                    self.arena = Some(arena);
+                }
+                Some(ServerMessage::Snapshot(snapshot)) => snapshots.push(snapshot),
+                None => {}
+            }
+        }
+        snapshots
+    }
+
+    /// Tells the server where this player wants their paddle and whether they are serving
+    pub fn send_paddle(&mut self, paddle_y: f32, serve: bool) {
+        self.send(&ClientMessage::Input { paddle_y, serve });
+    }
+
+    /// Tells the server this client is leaving
+    pub fn leave(&mut self) {
+        self.send(&ClientMessage::Leave);
+    }
+
+    /// Sends a message to the server
+    fn send(&mut self, message: &ClientMessage) {
+        self.transport.send(&encode(message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paddle_moves_no_faster_than_the_paddle_speed() {
+        assert_eq!(validate_paddle_target(0.0, 2.0, 4.0), 0.5);
+        // Teleporting is turned into moving at full speed
+        assert_eq!(validate_paddle_target(0.0, 200.0, 4.0), 1.0);
+        assert_eq!(validate_paddle_target(10.0, -200.0, 4.0), -1.0);
+        assert_eq!(validate_paddle_target(10.0, f32::NAN, 4.0), 0.0);
+    }
+}
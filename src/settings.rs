@@ -0,0 +1,73 @@
+/// Module for the settings describing the playing field and its pieces
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::ball::{BALL_HEIGHT, BALL_WIDTH, INITIAL_BALL_SPEED};
+use crate::paddle::{Paddle, PaddleSlot, FORWARD_PADDLE_OFFSET, PADDLE_HEIGHT, PADDLE_OFFSET, PADDLE_SPEED, PADDLE_WIDTH};
+use crate::window::{HEIGHT, WIDTH};
+
+/// Dimensions, speeds and colors of the playing field, paddles and ball
+///
+/// Inserted as a resource by PongPlugin and read by every system that needs to
+/// know how big or fast something is. The defaults give the classic Pixel Pong look.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct PongSettings {
+    /// Width and height of the playing field in pixels
+    pub field_size: Vec2,
+    /// Width and height of each paddle
+    pub paddle_size: Vec2,
+    /// Top speed of a paddle in pixels per second
+    pub paddle_speed: f32,
+    /// Gap between a paddle and its edge of the field
+    pub paddle_offset: f32,
+    /// Gap between a forward paddle in a doubles match and its edge of the field
+    #[serde(default = "default_forward_paddle_offset")]
+    pub forward_paddle_offset: f32,
+    /// Width and height of the ball
+    pub ball_size: Vec2,
+    /// Speed of the ball along each axis when it is served
+    pub ball_speed: f32,
+    /// Color of the paddles
+    pub paddle_color: Color,
+    /// Color of the ball
+    pub ball_color: Color,
+    /// Color of the playing field
+    pub background_color: Color,
+    /// Color of the dotted line down the middle of the field
+    pub center_line_color: Color,
+}
+
+impl Default for PongSettings {
+    fn default() -> Self {
+        PongSettings {
+            field_size: Vec2::new(WIDTH as f32, HEIGHT as f32),
+            paddle_size: Vec2::new(PADDLE_WIDTH, PADDLE_HEIGHT),
+            paddle_speed: PADDLE_SPEED,
+            paddle_offset: PADDLE_OFFSET,
+            forward_paddle_offset: FORWARD_PADDLE_OFFSET,
+            ball_size: Vec2::new(BALL_WIDTH, BALL_HEIGHT),
+            ball_speed: INITIAL_BALL_SPEED,
+            paddle_color: Color::WHITE,
+            ball_color: Color::WHITE,
+            background_color: Color::srgb(0.1, 0.1, 0.1),
+            center_line_color: Color::srgb(0.3, 0.3, 0.3),
+        }
+    }
+}
+
+/// Forward paddle offset for settings saved before doubles matches existed
+fn default_forward_paddle_offset() -> f32 {
+    FORWARD_PADDLE_OFFSET
+}
+
+impl PongSettings {
+    /// Position of the center of a paddle at the start of a point
+    pub fn paddle_position(&self, paddle: Paddle) -> Vec2 {
+        let offset = match paddle.slot {
+            PaddleSlot::Back => self.paddle_offset,
+            PaddleSlot::Forward => self.forward_paddle_offset,
+        };
+        let side = paddle.side;
+        let depth = side.orient(self.field_size).x / 2.0 - self.paddle_size.x - offset;
+        -side.inward() * depth
+    }
+}
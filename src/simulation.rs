@@ -0,0 +1,157 @@
+/// Module for running the gameplay on a fixed timestep so matches can be replayed exactly
+use bevy::prelude::*;
+use bevy::state::state::StateTransition;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+/// Simulation ticks per second unless `--tick-rate` is given
+pub const DEFAULT_TICK_RATE: f64 = 120.0;
+
+/// Settings controlling how the gameplay simulation runs
+#[derive(Resource)]
+pub struct SimulationSettings {
+    /// Simulation ticks per second
+    pub tick_rate: f64,
+    /// Seed for the GameRng, so the same seed and inputs always play out the same way
+    pub seed: u64,
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        SimulationSettings {
+            tick_rate: DEFAULT_TICK_RATE,
+            seed: 0,
+        }
+    }
+}
+
+impl SimulationSettings {
+    /// Reads the simulation settings from command line arguments
+    ///
+    /// Accepts `--tick-rate <hz>` and `--seed <number>`. Without a seed a random
+    /// one is picked, which is logged at the start of each match so it can be repeated.
+    /// Tick rates that aren't a positive number are reported and ignored.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut settings = SimulationSettings {
+            seed: rand::random(),
+            ..default()
+        };
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let value = match arg.as_str() {
+                "--tick-rate" | "--seed" => args.next().unwrap_or_default(),
+                _ => continue,
+            };
+            let parsed = match arg.as_str() {
+                "--tick-rate" => value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|hz| *hz > 0.0 && hz.is_finite())
+                    .map(|hz| settings.tick_rate = hz)
+                    .is_some(),
+                _ => value.parse().map(|seed| settings.seed = seed).is_ok(),
+            };
+            if !parsed {
+                warn!("Invalid value '{}' for {}", value, arg);
+            }
+        }
+
+        settings
+    }
+}
+
+/// Random number generator for everything that affects gameplay
+///
+/// Reseeded from SimulationSettings at the start of every match, so gameplay
+/// never draws on an unseeded source of randomness. Uses the same algorithm as
+/// StdRng, but one whose state can be saved in replays.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct GameRng(pub ChaCha12Rng);
+
+impl FromWorld for GameRng {
+    fn from_world(world: &mut World) -> Self {
+        let seed = world.get_resource::<SimulationSettings>().map_or(0, |settings| settings.seed);
+        GameRng(ChaCha12Rng::seed_from_u64(seed))
+    }
+}
+
+/// Reseeds the GameRng so every match starts from the same random sequence
+pub fn reseed_rng(settings: Res<SimulationSettings>, mut rng: ResMut<GameRng>) {
+    info!("Starting match at {} Hz with seed {}", settings.tick_rate, settings.seed);
+    rng.0 = ChaCha12Rng::seed_from_u64(settings.seed);
+}
+
+/// Applies pending state changes at the start of a simulation tick
+///
+/// Bevy normally changes state once per frame, which would let the number of
+/// ticks spent in a state depend on the frame rate. Running the transition here
+/// as well makes the serve countdown and scoring change state on exact ticks.
+pub fn apply_state_transitions(world: &mut World) {
+    let _ = world.try_run_schedule(StateTransition);
+}
+
+/// Component that smooths an entity's rendered position between simulation ticks
+///
+/// The simulation moves the Transform on fixed ticks. Between ticks the Transform
+/// is shown part way from the previous tick's position to the latest one, and put
+/// back to the simulated position before the next ticks run.
+#[derive(Component)]
+pub struct Interpolated {
+    /// Position at the end of the tick before last
+    previous: Vec3,
+    /// Position at the end of the latest tick
+    current: Vec3,
+    /// Position written to the Transform for rendering
+    rendered: Vec3,
+}
+
+impl Interpolated {
+    /// Starts interpolation from a resting position
+    pub fn at(translation: Vec3) -> Self {
+        Interpolated {
+            previous: translation,
+            current: translation,
+            rendered: translation,
+        }
+    }
+}
+
+/// Puts interpolated entities back to their simulated positions before the ticks run
+///
+/// If something outside the simulation has moved the entity since it was drawn,
+/// such as a rematch resetting the field, the new position is taken as is.
+pub fn restore_simulated_translations(mut query: Query<(&mut Transform, &mut Interpolated)>) {
+    for (mut transform, mut interpolated) in query.iter_mut() {
+        if transform.translation == interpolated.rendered {
+            transform.translation = interpolated.current;
+        } else {
+            *interpolated = Interpolated::at(transform.translation);
+        }
+    }
+}
+
+/// Remembers each interpolated entity's position at the start of a tick
+pub fn store_previous_translations(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = transform.translation;
+    }
+}
+
+/// Blends interpolated entities between their last two simulated positions
+///
+/// Uses how far real time has run ahead of the last tick to pick a point between
+/// the two, so motion stays smooth whatever the frame rate.
+pub fn interpolate_translations(
+    fixed_time: Res<Time<Fixed>>,
+    mut query: Query<(&mut Transform, &mut Interpolated)>,
+) {
+    let blend = fixed_time.overstep_fraction();
+
+    for (mut transform, mut interpolated) in query.iter_mut() {
+        interpolated.current = transform.translation;
+        interpolated.rendered = interpolated.previous.lerp(interpolated.current, blend);
+        transform.translation = interpolated.rendered;
+    }
+}
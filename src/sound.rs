@@ -0,0 +1,346 @@
+/// Module for the beeps played when the ball bounces and when a point is scored
+use std::sync::Arc;
+use std::time::Duration;
+use bevy::audio::{AddAudioSource, Decodable, Source, Volume};
+use bevy::prelude::*;
+use crate::events::{PaddleHit, WallBounce};
+use crate::input::{ActionState, InputAction};
+use crate::scoreboard::{LifeLost, ScoreEvent};
+
+/// Samples per second of every generated sound
+pub const SAMPLE_RATE: u32 = 44_100;
+/// Peak amplitude of the square wave, leaving headroom for sounds that overlap
+const AMPLITUDE: f32 = 0.25;
+/// Length of the fade at the end of each beep, which stops it ending in a click
+const FADE_OUT: f32 = 0.004;
+/// Pitch of the first paddle hit of a rally, as in the original arcade game
+const PADDLE_HIT_FREQUENCY: f32 = 459.0;
+/// Pitch of a wall bounce, about an octave below a paddle hit
+const WALL_BOUNCE_FREQUENCY: f32 = 226.0;
+/// Pitch of the tone played when a point is scored
+const SCORE_FREQUENCY: f32 = 490.0;
+/// Number of semitones the paddle hit can climb over a long rally
+const MAX_PITCH_STEPS: u32 = 12;
+
+/// Plugin playing a sound whenever the ball bounces or a point is scored
+///
+/// The sounds are square-wave beeps generated when the game starts, so no
+/// audio files are needed. Requires bevy's AudioPlugin.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_source::<Beep>()
+            .init_resource::<SoundSettings>()
+            .add_systems(Startup, generate_sound_effects)
+            .add_systems(Update, (toggle_mute, play_sound_effects).chain());
+    }
+}
+
+/// Something that makes a sound
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SoundEffect {
+    /// The ball was returned by a paddle, with the ball's bounce count after the hit
+    PaddleHit(u32),
+    /// The ball bounced off the top or bottom wall
+    WallBounce,
+    /// A point was scored
+    Score,
+}
+
+impl SoundEffect {
+    /// Pitch of the sound in hertz
+    ///
+    /// Paddle hits rise by a semitone with every bounce of the rally, up to an
+    /// octave above the first hit.
+    pub fn frequency(self) -> f32 {
+        match self {
+            SoundEffect::PaddleHit(bounces) => {
+                let steps = bounces.saturating_sub(1).min(MAX_PITCH_STEPS);
+                PADDLE_HIT_FREQUENCY * 2f32.powf(steps as f32 / 12.0)
+            }
+            SoundEffect::WallBounce => WALL_BOUNCE_FREQUENCY,
+            SoundEffect::Score => SCORE_FREQUENCY,
+        }
+    }
+
+    /// Length of the sound in seconds
+    pub fn duration(self) -> f32 {
+        match self {
+            SoundEffect::PaddleHit(_) => 0.035,
+            SoundEffect::WallBounce => 0.025,
+            SoundEffect::Score => 0.26,
+        }
+    }
+
+    /// Samples of the sound at SAMPLE_RATE
+    pub fn samples(self) -> Vec<f32> {
+        square_wave(self.frequency(), self.duration())
+    }
+}
+
+/// Generates a square wave of the given pitch and length, fading out at the end
+pub fn square_wave(frequency: f32, duration: f32) -> Vec<f32> {
+    let length = (duration * SAMPLE_RATE as f32).round() as usize;
+    let fade_length = ((FADE_OUT * SAMPLE_RATE as f32) as usize).min(length);
+    let period = SAMPLE_RATE as f32 / frequency;
+
+    (0..length)
+        .map(|index| {
+            let phase = (index as f32 / period).fract();
+            let level = if phase < 0.5 { AMPLITUDE } else { -AMPLITUDE };
+            let remaining = length - index;
+            if remaining < fade_length {
+                level * remaining as f32 / fade_length as f32
+            } else {
+                level
+            }
+        })
+        .collect()
+}
+
+/// A generated sound, played with an `AudioPlayer<Beep>`
+#[derive(Asset, TypePath, Clone)]
+pub struct Beep {
+    /// Mono samples at SAMPLE_RATE
+    samples: Arc<[f32]>,
+}
+
+impl Beep {
+    /// Generates the sound for `effect`
+    pub fn new(effect: SoundEffect) -> Self {
+        Beep { samples: effect.samples().into() }
+    }
+}
+
+impl Decodable for Beep {
+    type DecoderItem = f32;
+    type Decoder = BeepDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        BeepDecoder { samples: self.samples.clone(), position: 0 }
+    }
+}
+
+/// Feeds the samples of a Beep to the audio output
+pub struct BeepDecoder {
+    /// Samples of the sound
+    samples: Arc<[f32]>,
+    /// Index of the next sample
+    position: usize,
+}
+
+impl Iterator for BeepDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for BeepDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.samples.len().saturating_sub(self.position))
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(self.samples.len() as f32 / SAMPLE_RATE as f32))
+    }
+}
+
+/// Volume settings for the sound effects
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct SoundSettings {
+    /// Volume of everything the game plays, from 0 to 1
+    pub master_volume: f32,
+    /// Volume of the sound effects relative to the master volume, from 0 to 1
+    pub effects_volume: f32,
+    /// Whether all sound is turned off
+    pub muted: bool,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        SoundSettings {
+            master_volume: 1.0,
+            effects_volume: 0.8,
+            muted: false,
+        }
+    }
+}
+
+impl SoundSettings {
+    /// Reads the sound settings from command line arguments
+    ///
+    /// Accepts `--volume <0-1>` for the master volume, `--sfx-volume <0-1>` for
+    /// the sound effects and `--mute` to start with the sound off.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut settings = SoundSettings::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let volume = match arg.as_str() {
+                "--volume" => &mut settings.master_volume,
+                "--sfx-volume" => &mut settings.effects_volume,
+                "--mute" => {
+                    settings.muted = true;
+                    continue;
+                }
+                _ => continue,
+            };
+            let value = args.next().unwrap_or_default();
+            match value.parse::<f32>() {
+                Ok(parsed) => *volume = parsed.clamp(0.0, 1.0),
+                Err(_) => warn!("Invalid value '{}' for {}", value, arg),
+            }
+        }
+
+        settings
+    }
+
+    /// Volume sound effects are played at, zero when muted
+    pub fn effects_level(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master_volume * self.effects_volume
+        }
+    }
+}
+
+/// The generated sounds, made once and played many times
+#[derive(Resource)]
+pub struct SoundEffects {
+    /// Paddle hits from the first bounce of a rally up to the highest pitch
+    paddle_hits: Vec<Handle<Beep>>,
+    /// Wall bounce
+    wall_bounce: Handle<Beep>,
+    /// Point scored
+    score: Handle<Beep>,
+}
+
+impl SoundEffects {
+    /// The sound to play for `effect`
+    fn get(&self, effect: SoundEffect) -> Handle<Beep> {
+        match effect {
+            SoundEffect::PaddleHit(bounces) => {
+                let step = bounces.saturating_sub(1).min(MAX_PITCH_STEPS) as usize;
+                self.paddle_hits[step].clone()
+            }
+            SoundEffect::WallBounce => self.wall_bounce.clone(),
+            SoundEffect::Score => self.score.clone(),
+        }
+    }
+}
+
+/// Generates every sound effect
+pub fn generate_sound_effects(mut commands: Commands, mut beeps: ResMut<Assets<Beep>>) {
+    let paddle_hits = (1..=MAX_PITCH_STEPS + 1)
+        .map(|bounces| beeps.add(Beep::new(SoundEffect::PaddleHit(bounces))))
+        .collect();
+
+    commands.insert_resource(SoundEffects {
+        paddle_hits,
+        wall_bounce: beeps.add(Beep::new(SoundEffect::WallBounce)),
+        score: beeps.add(Beep::new(SoundEffect::Score)),
+    });
+}
+
+/// Turns the sound off and on when either player presses the mute binding
+pub fn toggle_mute(actions: Res<ActionState>, mut settings: ResMut<SoundSettings>) {
+    if actions.any_just_pressed(InputAction::Mute) {
+        settings.muted = !settings.muted;
+        info!("Sound {}", if settings.muted { "off" } else { "on" });
+    }
+}
+
+/// Plays a beep for every bounce, point and lost life since the last frame
+///
+/// Each sound plays at most once per frame, so several fixed ticks in one frame,
+/// a network rollback or a jump through a replay don't stack up copies of it.
+pub fn play_sound_effects(
+    mut commands: Commands,
+    mut paddle_hits: EventReader<PaddleHit>,
+    mut wall_bounces: EventReader<WallBounce>,
+    mut score_events: EventReader<ScoreEvent>,
+    mut life_lost_events: EventReader<LifeLost>,
+    settings: Res<SoundSettings>,
+    sound_effects: Option<Res<SoundEffects>>,
+) {
+    let mut effects: Vec<SoundEffect> = paddle_hits
+        .read()
+        .map(|hit| SoundEffect::PaddleHit(hit.bounces))
+        .chain(wall_bounces.read().map(|_| SoundEffect::WallBounce))
+        .chain(score_events.read().map(|_| SoundEffect::Score))
+        .chain(life_lost_events.read().map(|_| SoundEffect::Score))
+        .collect();
+
+    let level = settings.effects_level();
+    let Some(sound_effects) = sound_effects else {
+        return;
+    };
+    if level <= 0.0 {
+        return;
+    }
+
+    effects.sort();
+    effects.dedup();
+    for effect in effects {
+        commands.spawn((
+            AudioPlayer(sound_effects.get(effect)),
+            PlaybackSettings::DESPAWN.with_volume(Volume::new(level)),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Number of times the wave crosses from negative to positive
+    fn rising_edges(samples: &[f32]) -> usize {
+        samples.windows(2).filter(|pair| pair[0] < 0.0 && pair[1] > 0.0).count()
+    }
+
+    #[test]
+    fn square_wave_has_the_right_length_pitch_and_level() {
+        let samples = square_wave(441.0, 0.1);
+
+        assert_eq!(samples.len(), 4410);
+        // 44.1 cycles, the first of which starts high
+        assert_eq!(rising_edges(&samples), 44);
+        assert!(samples[..4000].iter().all(|sample| sample.abs() == AMPLITUDE));
+        assert!(samples.last().unwrap().abs() < AMPLITUDE / 10.0);
+    }
+
+    #[test]
+    fn paddle_hits_rise_in_pitch_up_to_an_octave() {
+        let first = SoundEffect::PaddleHit(1);
+        let twelfth = SoundEffect::PaddleHit(13);
+
+        assert!(SoundEffect::PaddleHit(2).frequency() > first.frequency());
+        assert!((twelfth.frequency() - 2.0 * first.frequency()).abs() < 0.01);
+        assert_eq!(SoundEffect::PaddleHit(40).frequency(), twelfth.frequency());
+        assert!(rising_edges(&twelfth.samples()) > rising_edges(&first.samples()));
+    }
+
+    #[test]
+    fn mute_silences_effects() {
+        let args = ["--volume", "0.5", "--sfx-volume", "2"].map(String::from);
+        let mut settings = SoundSettings::from_args(args);
+
+        assert_eq!(settings.effects_level(), 0.5);
+        settings.muted = true;
+        assert_eq!(settings.effects_level(), 0.0);
+    }
+}
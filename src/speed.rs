@@ -0,0 +1,295 @@
+/// Module for how the ball speeds up over the course of a rally
+use std::fs;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// What a speed curve is measured against
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RallyMeasure {
+    /// Paddle hits so far in the rally
+    #[default]
+    Bounces,
+    /// Seconds since the ball was served
+    Seconds,
+}
+
+/// One entry of a step table
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpeedStep {
+    /// Bounces or seconds from which the step applies
+    pub from: f32,
+    /// Horizontal and vertical speed multipliers
+    pub multiplier: Vec2,
+}
+
+/// Shape of the speed progression, giving horizontal and vertical multipliers
+/// separately
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SpeedCurve {
+    /// The multiplier of the last step reached, or 1 before the first one
+    Steps(Vec<SpeedStep>),
+    /// Grows by `rate` for every bounce or second
+    Linear {
+        /// Increase of the multiplier per bounce or second
+        rate: Vec2,
+    },
+    /// Grows by a factor of `growth` for every bounce or second
+    Exponential {
+        /// Factor the multiplier grows by per bounce or second
+        growth: Vec2,
+    },
+}
+
+impl SpeedCurve {
+    /// Multipliers after `progress` bounces or seconds
+    pub fn multiplier(&self, progress: f32) -> Vec2 {
+        match self {
+            SpeedCurve::Steps(steps) => steps
+                .iter()
+                .filter(|step| progress >= step.from)
+                .max_by(|a, b| a.from.total_cmp(&b.from))
+                .map_or(Vec2::ONE, |step| step.multiplier),
+            SpeedCurve::Linear { rate } => Vec2::ONE + *rate * progress,
+            SpeedCurve::Exponential { growth } => Vec2::new(growth.x.powf(progress), growth.y.powf(progress)),
+        }
+    }
+}
+
+/// How the ball speeds up during a rally
+///
+/// The served speed from PongSettings is multiplied by the curve, measured in
+/// bounces or seconds of the rally, and then capped at `max_speed`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpeedProgression {
+    /// Multipliers over the course of a rally
+    pub curve: SpeedCurve,
+    /// Whether the curve advances with paddle hits or with time
+    pub measure: RallyMeasure,
+    /// Fastest the ball may travel in pixels per second, or None for no limit
+    pub max_speed: Option<f32>,
+}
+
+impl Default for SpeedProgression {
+    fn default() -> Self {
+        SpeedMode::Classic.progression()
+    }
+}
+
+impl SpeedProgression {
+    /// Horizontal and vertical multipliers after `bounces` paddle hits and `rally_time` seconds
+    pub fn multiplier(&self, bounces: u32, rally_time: f32) -> Vec2 {
+        let progress = match self.measure {
+            RallyMeasure::Bounces => bounces as f32,
+            RallyMeasure::Seconds => rally_time,
+        };
+        self.curve.multiplier(progress)
+    }
+
+    /// Movement per second of a ball with the given velocity at this point in the rally
+    pub fn ball_velocity(&self, velocity: Vec2, bounces: u32, rally_time: f32) -> Vec2 {
+        let moving = velocity * self.multiplier(bounces, rally_time);
+        match self.max_speed {
+            Some(max_speed) => moving.clamp_length_max(max_speed),
+            None => moving,
+        }
+    }
+
+    /// Checks that the curve never stops, reverses or endlessly speeds up the ball
+    ///
+    /// Step multipliers have to be finite and above zero, linear rates finite and
+    /// not negative, exponential growth finite and at least 1, and `max_speed`
+    /// above zero.
+    pub fn validate(&self) -> Result<(), String> {
+        let usable = match &self.curve {
+            SpeedCurve::Steps(steps) => steps
+                .iter()
+                .all(|step| step.from.is_finite() && step.multiplier.is_finite() && step.multiplier.min_element() > 0.0),
+            SpeedCurve::Linear { rate } => rate.is_finite() && rate.min_element() >= 0.0,
+            SpeedCurve::Exponential { growth } => growth.is_finite() && growth.min_element() >= 1.0,
+        };
+        if !usable {
+            return Err("the curve would stop, reverse or endlessly speed up the ball".to_string());
+        }
+        if self.max_speed.is_some_and(|max_speed| max_speed.is_nan() || max_speed <= 0.0) {
+            return Err("max_speed has to be above zero".to_string());
+        }
+        Ok(())
+    }
+
+    /// Reads the progression named by `--speed-curve <mode>`
+    ///
+    /// The value is the name of a SpeedMode, or the path of a RON file holding a
+    /// SpeedProgression for a curve of your own, which has to pass validate.
+    /// Returns None if the option is missing or unusable, after reporting why.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut args = args.into_iter();
+        let mut progression = None;
+
+        while let Some(arg) = args.next() {
+            if arg != "--speed-curve" {
+                continue;
+            }
+            let value = args.next().unwrap_or_default();
+            progression = match SpeedMode::from_name(&value) {
+                Some(mode) => Some(mode.progression()),
+                None => match fs::read_to_string(&value)
+                    .map_err(|err| err.to_string())
+                    .and_then(|text| ron::from_str::<SpeedProgression>(&text).map_err(|err| err.to_string()))
+                    .and_then(|progression| progression.validate().map(|()| progression))
+                {
+                    Ok(progression) => Some(progression),
+                    Err(err) => {
+                        warn!("Invalid value '{}' for --speed-curve: {}", value, err);
+                        None
+                    }
+                },
+            };
+        }
+
+        progression
+    }
+}
+
+/// Ready-made speed progressions that can be picked on the menu
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpeedMode {
+    /// Three fixed steps in horizontal speed, as Pixel Pong always played
+    Classic,
+    /// A gentle climb with every hit, up to a modest top speed
+    Steady,
+    /// Every hit makes the ball noticeably faster
+    Rush,
+    /// The ball gathers pace the longer a rally lasts, however it is hit
+    Endurance,
+}
+
+impl SpeedMode {
+    /// Every mode, in the order the menu cycles through them
+    pub const ALL: [SpeedMode; 4] = [SpeedMode::Classic, SpeedMode::Steady, SpeedMode::Rush, SpeedMode::Endurance];
+
+    /// Parses a mode from its lowercase name
+    pub fn from_name(name: &str) -> Option<Self> {
+        SpeedMode::ALL.into_iter().find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Name of the mode as shown in menus
+    pub fn name(self) -> &'static str {
+        match self {
+            SpeedMode::Classic => "Classic",
+            SpeedMode::Steady => "Steady",
+            SpeedMode::Rush => "Rush",
+            SpeedMode::Endurance => "Endurance",
+        }
+    }
+
+    /// The mode after this one, wrapping around to Classic
+    pub fn next(self) -> Self {
+        let index = SpeedMode::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        SpeedMode::ALL[(index + 1) % SpeedMode::ALL.len()]
+    }
+
+    /// The mode `progression` was made from, if it is one of them
+    pub fn of(progression: &SpeedProgression) -> Option<Self> {
+        SpeedMode::ALL.into_iter().find(|mode| mode.progression() == *progression)
+    }
+
+    /// The speed progression of this mode
+    pub fn progression(self) -> SpeedProgression {
+        match self {
+            SpeedMode::Classic => SpeedProgression {
+                curve: SpeedCurve::Steps(vec![
+                    SpeedStep { from: 4.0, multiplier: Vec2::new(1.6, 1.0) },
+                    SpeedStep { from: 12.0, multiplier: Vec2::new(2.1, 1.0) },
+                ]),
+                measure: RallyMeasure::Bounces,
+                max_speed: None,
+            },
+            SpeedMode::Steady => SpeedProgression {
+                curve: SpeedCurve::Linear { rate: Vec2::new(0.08, 0.03) },
+                measure: RallyMeasure::Bounces,
+                max_speed: Some(450.0),
+            },
+            SpeedMode::Rush => SpeedProgression {
+                curve: SpeedCurve::Exponential { growth: Vec2::new(1.12, 1.04) },
+                measure: RallyMeasure::Bounces,
+                max_speed: Some(700.0),
+            },
+            SpeedMode::Endurance => SpeedProgression {
+                curve: SpeedCurve::Linear { rate: Vec2::new(0.06, 0.02) },
+                measure: RallyMeasure::Seconds,
+                max_speed: Some(550.0),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_table_holds_each_multiplier_until_the_next_step() {
+        let classic = SpeedMode::Classic.progression();
+
+        assert_eq!(classic.multiplier(0, 30.0), Vec2::ONE);
+        assert_eq!(classic.multiplier(3, 0.0), Vec2::ONE);
+        assert_eq!(classic.multiplier(4, 0.0), Vec2::new(1.6, 1.0));
+        assert_eq!(classic.multiplier(11, 0.0), Vec2::new(1.6, 1.0));
+        assert_eq!(classic.multiplier(40, 0.0), Vec2::new(2.1, 1.0));
+    }
+
+    #[test]
+    fn linear_ramp_grows_each_axis_at_its_own_rate() {
+        let curve = SpeedCurve::Linear { rate: Vec2::new(0.1, 0.05) };
+
+        assert_eq!(curve.multiplier(0.0), Vec2::ONE);
+        assert!(curve.multiplier(10.0).abs_diff_eq(Vec2::new(2.0, 1.5), 1e-5));
+    }
+
+    #[test]
+    fn exponential_ramp_compounds() {
+        let curve = SpeedCurve::Exponential { growth: Vec2::new(2.0, 1.0) };
+
+        assert_eq!(curve.multiplier(0.0), Vec2::ONE);
+        assert_eq!(curve.multiplier(3.0), Vec2::new(8.0, 1.0));
+    }
+
+    #[test]
+    fn time_based_curve_ignores_bounces_and_speed_is_capped() {
+        let endurance = SpeedMode::Endurance.progression();
+        let served = Vec2::new(125.0, 125.0);
+
+        assert_eq!(endurance.multiplier(50, 0.0), Vec2::ONE);
+        assert!(endurance.ball_velocity(served, 0, 10.0).x > served.x);
+        assert!((endurance.ball_velocity(served, 0, 1000.0).length() - 550.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn custom_curves_load_from_ron() {
+        let text = "(curve: Steps([(from: 2.0, multiplier: (1.5, 1.2))]), measure: Seconds, max_speed: None)";
+
+        let progression: SpeedProgression = ron::from_str(text).unwrap();
+
+        assert_eq!(progression.multiplier(0, 2.5), Vec2::new(1.5, 1.2));
+        assert_eq!(SpeedMode::of(&progression), None);
+        assert_eq!(SpeedMode::of(&SpeedMode::Rush.progression()), Some(SpeedMode::Rush));
+    }
+
+    #[test]
+    fn unusable_curves_are_refused() {
+        let reversing = "(curve: Exponential(growth: (-1.0, 1.0)), measure: Bounces, max_speed: None)";
+        let decaying = "(curve: Exponential(growth: (0.9, 1.0)), measure: Seconds, max_speed: None)";
+        let slowing = "(curve: Linear(rate: (-0.1, 0.0)), measure: Bounces, max_speed: None)";
+        let stopped = "(curve: Steps([(from: 3.0, multiplier: (0.0, 0.0))]), measure: Bounces, max_speed: None)";
+        let endless = "(curve: Steps([(from: 1.0, multiplier: (inf, 1.0))]), measure: Bounces, max_speed: None)";
+        let capped_below_zero = "(curve: Linear(rate: (0.1, 0.0)), measure: Bounces, max_speed: Some(-5.0))";
+        for text in [reversing, decaying, slowing, stopped, endless, capped_below_zero] {
+            let progression: SpeedProgression = ron::from_str(text).unwrap();
+            assert!(progression.validate().is_err(), "accepted {text}");
+        }
+
+        for mode in [SpeedMode::Classic, SpeedMode::Steady, SpeedMode::Rush, SpeedMode::Endurance] {
+            assert_eq!(mode.progression().validate(), Ok(()));
+        }
+    }
+}
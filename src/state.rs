@@ -0,0 +1,173 @@
+/// Module defining the game flow between the menu, serving, playing, pausing and game over
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::input::{ActionState, InputAction, InputBindings};
+use crate::paddle::{Paddle, Side};
+use crate::events::RallyEnded;
+use crate::scoreboard::{MatchRules, MatchWon};
+use crate::serve::ServingSide;
+
+/// Default seconds before the ball is served if the serving player doesn't serve it
+pub const SERVE_COUNTDOWN: f32 = 3.0;
+
+/// The phase the game is in
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameState {
+    /// Title menu where the players pick a mode
+    #[default]
+    MainMenu,
+    /// Screen for remapping the input bindings
+    Controls,
+    /// Screen listing matches found on the local network
+    FindGames,
+    /// Screen for choosing the arena
+    LevelSelect,
+    /// The ball waits on the serving player's paddle until it is served or a countdown runs out
+    Countdown,
+    /// The ball is in play
+    Playing,
+    /// Play is frozen until the pause key is pressed again
+    Paused,
+    /// A player has won and the game over screen is shown
+    GameOver,
+}
+
+/// State that exists while a match is on screen, whatever phase it is in
+///
+/// Paddles, ball and scoreboard are scoped to this state so they are cleaned
+/// up when returning to the main menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = GameState;
+
+    fn compute(state: GameState) -> Option<Self> {
+        match state {
+            GameState::MainMenu | GameState::Controls | GameState::FindGames | GameState::LevelSelect => None,
+            _ => Some(InGame),
+        }
+    }
+}
+
+/// Timer counting down to the next serve
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct ServeCountdown(pub Timer);
+
+impl Default for ServeCountdown {
+    fn default() -> Self {
+        ServeCountdown(Timer::from_seconds(SERVE_COUNTDOWN, TimerMode::Once))
+    }
+}
+
+/// Component for the text showing the seconds left before a serve
+#[derive(Component)]
+pub struct CountdownText;
+
+/// Restarts the serve countdown with the serve timeout from the MatchRules
+pub fn start_serve_countdown(mut countdown: ResMut<ServeCountdown>, rules: Res<MatchRules>) {
+    *countdown = ServeCountdown(Timer::from_seconds(rules.serve_timeout, TimerMode::Once));
+}
+
+/// Shows the serve countdown and who is serving in the middle of the field
+pub fn spawn_countdown_text(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    rules: Res<MatchRules>,
+    serving: Res<ServingSide>,
+    bindings: Res<InputBindings>,
+) {
+    commands.spawn((
+        Text2d::new(countdown_label(rules.serve_timeout, serving.0, &bindings)),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 40.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, 60.0, 1.0)),
+        CountdownText,
+        StateScoped(GameState::Countdown),
+    ));
+}
+
+/// Updates the countdown text with the whole seconds left before the serve
+pub fn update_countdown_text(
+    countdown: Res<ServeCountdown>,
+    serving: Res<ServingSide>,
+    bindings: Res<InputBindings>,
+    mut text_query: Query<&mut Text2d, With<CountdownText>>,
+) {
+    let label = countdown_label(countdown.0.remaining_secs(), serving.0, &bindings);
+    for mut text in text_query.iter_mut() {
+        text.0.clone_from(&label);
+    }
+}
+
+/// Text for the serve countdown, with the serving player's Serve input
+fn countdown_label(remaining: f32, serving: Side, bindings: &InputBindings) -> String {
+    match bindings.player(Paddle::back(serving)).serve.first() {
+        Some(binding) => format!("{}\n{:?} serves - {}", remaining.ceil(), serving, binding.label()),
+        None => format!("{}\n{:?} serves", remaining.ceil(), serving),
+    }
+}
+
+/// Toggles between playing and paused when either player uses their Pause action
+pub fn toggle_pause(
+    actions: Res<ActionState>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !actions.any_just_pressed(InputAction::Pause) {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+/// Shows the pause notice over the frozen field
+pub fn spawn_pause_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<InputBindings>,
+) {
+    let resume = bindings
+        .left
+        .pause
+        .first()
+        .map(|binding| binding.label())
+        .unwrap_or_else(|| InputAction::Pause.name().to_string());
+
+    commands.spawn((
+        Text2d::new(format!("Paused\n{} - Resume", resume)),
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 40.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        Transform::from_translation(Vec3::new(0.0, 0.0, 3.0)),
+        StateScoped(GameState::Paused),
+    ));
+}
+
+/// Moves on once a point has won the match or the rally is over
+///
+/// Goes to the game over screen if a point won the match, and otherwise counts
+/// down to the next serve when the last ball has left the field.
+pub fn advance_after_score(
+    mut rally_ends: EventReader<RallyEnded>,
+    mut match_won_events: EventReader<MatchWon>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if let Some(event) = match_won_events.read().last() {
+        info!("Match over, {:?} player wins", event.winner);
+        next_state.set(GameState::GameOver);
+    } else if rally_ends.read().count() > 0 {
+        next_state.set(GameState::Countdown);
+    }
+}
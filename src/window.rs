@@ -1,23 +1,24 @@
 /// Module for handling window configuration, camera setup, and input events
 use bevy::{
-    input::keyboard::KeyboardInput, 
     prelude::*, 
     render::camera::ScalingMode
 };
+use crate::input::{ActionState, InputAction};
+use crate::settings::PongSettings;
 
-/// Width of the game window in pixels
+/// Default width of the game window and playing field in pixels
 pub const WIDTH: usize = 768;
-/// Height of the game window in pixels
+/// Default height of the game window and playing field in pixels
 pub const HEIGHT: usize = 480;
 
 /// Returns window configuration with default settings for Pixel Pong
 ///
-/// Creates a WindowPlugin with specified dimensions and title.
+/// Creates a WindowPlugin sized to fit the playing field in `settings`.
 /// Sets up the primary window with appropriate resolution and title.
-pub fn get_window_settings() -> WindowPlugin {
+pub fn get_window_settings(settings: &PongSettings) -> WindowPlugin {
     WindowPlugin {
         primary_window: Some(Window {
-            resolution: (WIDTH as f32, HEIGHT as f32).into(),
+            resolution: (settings.field_size.x, settings.field_size.y).into(),
             title: "Pixel Pong".to_string(),
             ..default()
         }),
@@ -25,18 +26,16 @@ pub fn get_window_settings() -> WindowPlugin {
     }
 }
 
-/// System that handles closing the application when Escape key is pressed
+/// System that handles closing the application when a player uses their Quit action
 ///
-/// Listens for keyboard input events and sends an AppExit event when
-/// the Escape key is detected, which will gracefully close the application.
-pub fn close_on_escape(
-    mut keyboard_input: EventReader<KeyboardInput>,
+/// Checks the ActionState for a Quit press, the Escape key by default, and sends
+/// an AppExit event, which will gracefully close the application.
+pub fn close_on_quit(
+    actions: Res<ActionState>,
     mut exit_events: EventWriter<AppExit>,
 ) {
-    for event in keyboard_input.read() {
-        if event.key_code == KeyCode::Escape {
-            exit_events.send(AppExit::Success);
-        }
+    if actions.any_just_pressed(InputAction::Quit) {
+        exit_events.send(AppExit::Success);
     }
 }
 
@@ -45,12 +44,12 @@ pub fn close_on_escape(
 /// Creates a Camera2d entity with an OrthographicProjection configured to
 /// automatically scale while maintaining the minimum specified dimensions.
 /// This ensures consistent gameplay experience across different window sizes.
-pub fn setup_camera(mut commands: Commands) {
+pub fn setup_camera(mut commands: Commands, settings: Res<PongSettings>) {
     let camera = Camera2d;
     let projection = OrthographicProjection {
         scaling_mode: ScalingMode::AutoMin {
-            min_width: WIDTH as f32,
-            min_height: HEIGHT as f32,
+            min_width: settings.field_size.x,
+            min_height: settings.field_size.y,
         },
         ..OrthographicProjection::default_2d()
     };
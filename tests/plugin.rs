@@ -0,0 +1,58 @@
+use std::time::Duration;
+use bevy::{prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+use bevy_pong::ball::Ball;
+use bevy_pong::paddle::{Paddle, Side};
+use bevy_pong::state::GameState;
+use bevy_pong::{PongPlugin, PongSet, PongSettings};
+
+/// Ball position seen by a host system running straight after the physics step
+#[derive(Resource, Default)]
+struct SeenBall(Option<Vec3>);
+
+fn record_ball(mut seen: ResMut<SeenBall>, ball_query: Query<&Transform, With<Ball>>) {
+    seen.0 = ball_query.get_single().ok().map(|transform| transform.translation);
+}
+
+fn embedded_app(settings: PongSettings) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / 120.0)))
+        .add_plugins(PongPlugin { settings, headless: true })
+        .init_resource::<SeenBall>()
+        .add_systems(FixedUpdate, record_ball.after(PongSet::Physics));
+    app.update();
+    app
+}
+
+#[test]
+fn host_systems_can_follow_the_physics_step() {
+    let mut app = embedded_app(PongSettings::default());
+    app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Playing);
+    app.update();
+    app.update();
+
+    let seen = app.world().resource::<SeenBall>().0.expect("ball was not recorded");
+    assert!(seen.x > 0.0 && seen.y > 0.0);
+}
+
+#[test]
+fn settings_size_the_field() {
+    let settings = PongSettings {
+        field_size: Vec2::new(400.0, 200.0),
+        paddle_size: Vec2::new(6.0, 40.0),
+        paddle_offset: 10.0,
+        ..default()
+    };
+    let mut app = embedded_app(settings);
+    app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Countdown);
+    app.update();
+
+    let world = app.world_mut();
+    let (transform, sprite, _) = world
+        .query::<(&Transform, &Sprite, &Paddle)>()
+        .iter(world)
+        .find(|(.., paddle)| paddle.side == Side::Left)
+        .unwrap();
+    assert_eq!(transform.translation.x, -200.0 + 6.0 + 10.0);
+    assert_eq!(sprite.custom_size, Some(Vec2::new(6.0, 40.0)));
+}
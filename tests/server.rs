@@ -0,0 +1,90 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+use bevy_pong::paddle::Side;
+use bevy_pong::scoreboard::MatchRules;
+use bevy_pong::server::{
+    ClientRole, FakeNetwork, GameServer, ServerConnection, ServerSnapshot, SNAPSHOT_INTERVAL,
+};
+use bevy_pong::simulation::SimulationSettings;
+use bevy_pong::state::GameState;
+use bevy_pong::PongSettings;
+
+fn address(text: &str) -> SocketAddr {
+    text.parse().unwrap()
+}
+
+/// Runs the server over an in-process network with two scripted bots and a spectator
+///
+/// The left bot follows the ball, the right bot keeps asking for its paddle to
+/// jump far off the field, which the server must turn into ordinary movement.
+#[test]
+fn bots_play_a_match() {
+    let settings = SimulationSettings::default();
+    let max_step = PongSettings::default().paddle_speed / settings.tick_rate as f32;
+    let network = FakeNetwork::default();
+    let server_address = address("10.0.0.1:7878");
+    let mut server = GameServer::new(network.server(server_address), settings, MatchRules::default());
+
+    let mut honest = ServerConnection::new(network.client(address("10.0.0.2:5000"), server_address), false);
+    let mut cheater = ServerConnection::new(network.client(address("10.0.0.3:5000"), server_address), false);
+    let mut spectator = ServerConnection::new(network.client(address("10.0.0.4:5000"), server_address), true);
+    let mut watched: Vec<ServerSnapshot> = Vec::new();
+
+    // Every packet arrives by the next poll, so the match plays out the same on every run
+    while !watched.last().is_some_and(|snapshot| snapshot.score.iter().sum::<u32>() > 0) {
+        assert!(watched.last().is_none_or(|snapshot| snapshot.tick < 4000), "nobody scored");
+        if let Some(latest) = honest.poll().last()
+            && let Some(ball) = latest.balls.first()
+        {
+            honest.send_paddle(ball.y, false);
+        }
+        cheater.poll();
+        cheater.send_paddle(200.0, false);
+        watched.extend(spectator.poll());
+
+        server.step();
+    }
+
+    let mut roles = [honest.role(), cheater.role()];
+    roles.sort_by_key(|role| format!("{role:?}"));
+    assert_eq!(roles, [Some(ClientRole::Player(Side::Left)), Some(ClientRole::Player(Side::Right))]);
+    assert_eq!(spectator.role(), Some(ClientRole::Spectator));
+
+    assert!(watched.iter().any(|snapshot| snapshot.state == GameState::Countdown));
+    assert!(watched.iter().any(|snapshot| snapshot.state == GameState::Playing));
+
+    let cheater_side = match cheater.role() {
+        Some(ClientRole::Player(Side::Left)) => 0,
+        _ => 1,
+    };
+    for pair in watched.windows(2) {
+        let ticks = (pair[1].tick - pair[0].tick) as f32;
+        let moved = (pair[1].paddles[cheater_side].y - pair[0].paddles[cheater_side].y).abs();
+        assert!(
+            moved <= max_step * ticks + 0.01,
+            "paddle moved {moved} in {ticks} ticks, more than {} allowed per tick",
+            max_step
+        );
+    }
+    assert!(watched.iter().all(|snapshot| snapshot.tick % SNAPSHOT_INTERVAL == 0));
+}
+
+/// Checks a client is welcomed over a real socket on loopback
+#[test]
+fn client_joins_over_loopback() {
+    let mut server =
+        GameServer::bind("127.0.0.1:0", SimulationSettings::default(), MatchRules::default()).unwrap();
+    let mut client = ServerConnection::connect(server.local_addr().unwrap(), false).unwrap();
+
+    // Generous, since the socket is only polled and the test machine may be busy
+    for _ in 0..1000 {
+        client.poll();
+        server.step();
+        if client.role().is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(2));
+    }
+
+    assert_eq!(client.role(), Some(ClientRole::Player(Side::Left)));
+}